@@ -2,12 +2,10 @@
 
 use clap::Parser;
 use derive_more::Display;
-use optimization::commons::{skip_validation, Valid};
+use optimization::commons::skip_validation;
 use optimization::front_end::*;
 use optimization::middle_end::lir;
-use optimization::middle_end::optimization::{
-    constant_prop::*, copy_prop::*, dead_store_elimination::*, inlining::*,
-};
+use optimization::middle_end::optimization::pass_manager::PassManager;
 use std::str::FromStr;
 
 // Input/output file types
@@ -47,51 +45,19 @@ impl FromStr for File {
     }
 }
 
-trait PassT {}
-
+// An optimization pass or pipeline, by name.  `-O1` on the command line
+// gives the name "1", which stands for the pipeline "O1".
 #[derive(Clone)]
-enum Pass {
-    Basic(fn(Valid<lir::Program>) -> Valid<lir::Program>),
-    InlineSmall(usize, usize),
-}
-
-impl Pass {
-    fn run(&self, p: Valid<lir::Program>) -> Valid<lir::Program> {
-        use Pass::*;
-
-        match self {
-            Basic(f) => f(p),
-            InlineSmall(n, m) => inline_small_fns(p, *n, *m),
-        }
-    }
-}
+struct Pass(String);
 
 impl FromStr for Pass {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        use Pass::*;
-
-        let pass = match s {
-            "dse" => Basic(dead_store_elim),
-            "const-prop" => Basic(constant_prop),
-            "copy-prop" => Basic(copy_prop),
-            "inline-leaves" => Basic(inline_leaf_functions),
-            _ if s.starts_with("inline-small-") => {
-                let s = &s["inline-small-".len()..];
-                let dash = s
-                    .find('-')
-                    .ok_or(format!("unknown optimization pass: {s}"))?;
-                let n = s[0..dash].parse::<usize>().map_err(|e| e.to_string())?;
-                let m = s[(dash + 1)..]
-                    .parse::<usize>()
-                    .map_err(|e| e.to_string())?;
-                InlineSmall(n, m)
-            }
-            _ => return Err(format!("unknown optimization pass: {s}")),
-        };
-
-        Ok(pass)
+        let name = if s.chars().all(|c| c.is_ascii_digit()) { format!("O{s}") } else { s.to_string() };
+        PassManager::new().add_named(&name)?;
+
+        Ok(Pass(name))
     }
 }
 
@@ -135,9 +101,11 @@ pub fn main() {
 
     let mut program = program.validate().unwrap();
 
-    for pass in args.optimization_passes {
-        program = pass.run(program);
+    let mut passes = PassManager::new();
+    for Pass(name) in &args.optimization_passes {
+        passes.add_named(name).unwrap();
     }
+    program = passes.run(program);
 
     let output = match args.output_file.typ {
         FileType::Lir => program.0.to_string().into_bytes(),
@@ -0,0 +1,73 @@
+// Soundness checking: runs LIR programs with the interpreter and checks that
+// the values of their variables are among the ones an analysis allows.
+//
+// Usage: check_soundness <analysis> <path>...
+//
+// where <analysis> is one of `interval`, `constant`, `points-to`,
+// `steensgaard` (the unification-based points-to analysis) or `flow-points-to`
+// (the flow-sensitive one) and each path is a LIR file or a directory to
+// search for them.  The exit status is 1
+// if the analysis missed a value in some program.
+
+use std::env;
+use std::path::Path;
+
+use optimization::commons::{lir_files, read_from, Valid};
+use optimization::middle_end::analysis::soundness::*;
+use optimization::middle_end::analysis_constraints::points_to_steensgaard;
+use optimization::middle_end::lir::Program;
+
+pub fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 || !["interval", "constant", "points-to", "steensgaard", "flow-points-to"].contains(&args[1].as_str()) {
+        eprintln!("Usage: {} interval|constant|points-to|steensgaard|flow-points-to <path>...", args[0]);
+        std::process::exit(2);
+    }
+
+    let mut files = vec![];
+    for path in &args[2..] {
+        lir_files(Path::new(path), &mut files);
+    }
+
+    let mut unsound = 0;
+    for file in &files {
+        let program = match read_from(file).parse::<Program>().map_err(|e| format!("{e:?}")) {
+            Ok(program) => program.validate().map_err(|e| format!("{e:?}")),
+            Err(e) => Err(e),
+        };
+        let program = match program {
+            Ok(program) => program,
+            Err(e) => {
+                println!("skipped {}: not a valid program: {e}", file.display());
+                continue;
+            }
+        };
+
+        let violations = violations(&args[1], &program);
+
+        if violations.is_empty() {
+            println!("ok {}", file.display());
+        } else {
+            unsound += 1;
+            println!("FAILED {}:", file.display());
+            for violation in violations {
+                println!("  {violation}");
+            }
+        }
+    }
+
+    println!("{} programs checked, {unsound} unsound", files.len());
+    if unsound > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn violations(analysis: &str, program: &Valid<Program>) -> Vec<Violation> {
+    match analysis {
+        "interval" => check(program, &Intervals::new(program)),
+        "constant" => check(program, &Constants::new(program)),
+        "points-to" => check(program, &PointsTo::new(program)),
+        "flow-points-to" => check(program, &FlowPointsTo::new(program)),
+        _ => check(program, &PointsTo(points_to_steensgaard(program))),
+    }
+}
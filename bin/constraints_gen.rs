@@ -1,9 +1,7 @@
-use optimization::middle_end::{analysis::*, lir::*};
-use optimization::middle_end::analysis_constraints::constraints_gen::{Env, analyze};
+use optimization::middle_end::lir::*;
 use optimization::middle_end::analysis_constraints::*;
 use optimization::commons::Valid;
-use std::collections::{BTreeMap as Map, BTreeSet as Set};
-use pretty_assertions::assert_eq;
+use std::collections::BTreeSet as Set;
 use std::env;
 
 use optimization::middle_end::constraints::*;
@@ -15,8 +13,10 @@ pub fn run() {
 
     let lir_file_name = &args[1];
     let json_file_name = &args[2];
+    // with `--fields`, pointers to different fields of a struct get different locations.
+    let fields = if args[3..].iter().any(|arg| arg == "--fields") { Fields::Separate } else { Fields::Merged };
 
-    let output = constraints_gen_lir(lir_file_name, json_file_name);
+    let output = constraints_gen_lir(lir_file_name, json_file_name, fields);
     println!("{output}");
 }
 
@@ -25,10 +25,7 @@ pub fn run_test() {
     let lir_file_name = "./test-inputs-03/gen/no_call3.lir";
     let json_file_name = "./test-inputs-03/gen/no_call3.lir.json";
 
-    let lir_file_name = "./test-inputs-03/failed/02.lir";
-    let json_file_name = "./test-inputs-03/failed/02.lir";
-
-    let output = constraints_gen_lir(lir_file_name, json_file_name);
+    let output = constraints_gen_lir(lir_file_name, json_file_name, Fields::Merged);
     println!("{output}");
 }
 fn main() {
@@ -37,23 +34,13 @@ fn main() {
 
 }
 
-
-fn final_path(package: &str, path: &str) -> String{
-    format!("{package}{path}")
-}
-
-
-fn lir_path(path: &str) -> String {
-    format!("{path}.lir")
-}
-
-fn constraints_gen_lir(lir_file_name: &str, json_file_name: &str) -> String {
+fn constraints_gen_lir(lir_file_name: &str, _json_file_name: &str, fields: Fields) -> String {
     let input_string = read_from(lir_file_name);
     
     let lir_parsed = parse_lir(&input_string);
 
-    let temp_set: Set<Constraint> = lir_parsed.0.functions.iter()
-        .flat_map(|(funcid, _)| constraints_gen::analyze(&lir_parsed, funcid.clone()))
+    let temp_set: Set<Constraint> = lir_parsed.0.functions.keys()
+        .flat_map(|funcid| constraints_gen::analyze_with(&lir_parsed, funcid.clone(), fields))
         .collect();
 
     nicely(&temp_set)
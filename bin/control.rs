@@ -1,11 +1,10 @@
 
 
-use optimization::middle_end::{analysis::*, lir::*, control_analysis::control::Env};
-use optimization::middle_end::{control_analysis::control::{analyze, analyze2}};
+use optimization::middle_end::lir::*;
+use optimization::middle_end::control_analysis::control::analyze;
 
 use optimization::commons::Valid;
 use std::collections::{BTreeMap as Map, BTreeSet as Set};
-use pretty_assertions::assert_eq;
 use std::env;
 
 
@@ -42,7 +41,7 @@ fn main() {
 
 }
 
-fn dominance_lir(lir_file_name: &str, json_file_name: &str, function_name: &str) -> String {
+fn dominance_lir(lir_file_name: &str, _json_file_name: &str, function_name: &str) -> String {
     let input_string = read_from(lir_file_name);
     
     let lir_parsed = parse_lir(&input_string);
@@ -77,16 +76,6 @@ fn nicely2(result: &Map<BbId, Set<BbId>>) -> String {
     s
 }
 
-fn nicely(result: &Map<BbId, Env>) -> String {
-    // this is very lazy and bad haha !
-    let mut s = String::new();
-    for (bb, env) in result {
-        
-        s += &env.to_string();
-    }
-    s
-}
-
 fn parse_lir(input: &str) -> Valid<Program> {
     input.parse::<Program>().unwrap().validate().unwrap()
 }
\ No newline at end of file
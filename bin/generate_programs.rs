@@ -0,0 +1,43 @@
+// Random program generation, for differential testing.
+//
+// Usage: generate_programs <seed> <count> <out-dir>
+//
+// writes the programs generated from seeds <seed> to <seed> + <count> - 1 to
+// <out-dir>, as cflat (`<seed>.cb`) and lowered to LIR (`<seed>.lir`).  The
+// LIR files can be checked with validate_passes.
+
+use std::env;
+use std::path::Path;
+
+use optimization::commons::Valid;
+use optimization::front_end::ast::validate::validate;
+use optimization::front_end::generate::{generate, Config};
+use optimization::front_end::lower;
+
+pub fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 4 {
+        eprintln!("Usage: {} <seed> <count> <out-dir>", args[0]);
+        std::process::exit(2);
+    }
+
+    let (Ok(seed), Ok(count)) = (args[1].parse::<u64>(), args[2].parse::<u64>()) else {
+        eprintln!("The seed and the count must be non-negative integers");
+        std::process::exit(2);
+    };
+    let out_dir = Path::new(&args[3]);
+    std::fs::create_dir_all(out_dir).unwrap_or_else(|_| panic!("Could not create the directory {}", out_dir.display()));
+
+    for seed in seed..seed + count {
+        let program = generate(seed, &Config::default());
+        validate(&program).expect("The generated program is not valid");
+        let lowered = lower(&Valid(program.clone()));
+
+        write_to(&out_dir.join(format!("{seed}.cb")), &program.pretty_print());
+        write_to(&out_dir.join(format!("{seed}.lir")), &lowered.to_string());
+    }
+}
+
+fn write_to(path: &Path, contents: &str) {
+    std::fs::write(path, contents).unwrap_or_else(|_| panic!("Could not write the output file {}", path.display()));
+}
@@ -1,7 +1,11 @@
 // The LIR interpreter
 
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
 use clap::Parser;
-use optimization::interpreter::interpret_with_output;
+use optimization::interpreter::interpret_observed;
+use optimization::interpreter::trace::{Coverage, Trace};
 use optimization::middle_end::lir;
 
 // Command-line arguments
@@ -9,13 +13,21 @@ use optimization::middle_end::lir;
 #[command(version, about)]
 struct Args {
     program: String,
+    /// Write a trace of the blocks, instructions and assigned values to this
+    /// file, or to stdout if it is `-`
+    #[arg(long)]
+    trace: Option<String>,
+    /// Write how many times each block and edge ran to this file, as JSON
+    #[arg(long)]
+    coverage: Option<String>,
 }
 
 pub fn main() {
-    let input_file = Args::parse().program;
+    let args = Args::parse();
+    let input_file = &args.program;
 
     let input_string = String::from_utf8(
-        std::fs::read(&input_file)
+        std::fs::read(input_file)
             .unwrap_or_else(|_| panic!("Could not read the input file {}", input_file)),
     )
     .expect("The input file does not contain valid utf-8 text");
@@ -24,7 +36,36 @@ pub fn main() {
 
     lir::validate(&program).unwrap();
 
-    let (ret, out) = interpret_with_output(program).unwrap();
+    let trace = args.trace.as_deref().map(|path| {
+        let out: Box<dyn Write> = match path {
+            "-" => Box::new(io::stdout()),
+            path => Box::new(File::create(path).unwrap_or_else(|_| panic!("Could not create {path}"))),
+        };
+        Trace::new(BufWriter::new(out))
+    });
+    let coverage = args.coverage.as_ref().map(|_| Coverage::new(&program));
+
+    let mut observer = (trace, coverage);
+    let result = interpret_observed(program, &mut observer);
+
+    let (trace, coverage) = observer;
+    if let Some(trace) = trace {
+        trace
+            .into_inner()
+            .and_then(|mut out| out.flush())
+            .expect("Could not write the trace");
+    }
+    if let (Some(path), Some(coverage)) = (&args.coverage, coverage) {
+        std::fs::write(path, format!("{:#}\n", coverage.to_json()))
+            .unwrap_or_else(|_| panic!("Could not write {path}"));
+    }
+
+    let (ret, out) = result.unwrap_or_else(|(stop, out)| {
+        for n in out {
+            println!("{n}");
+        }
+        panic!("{stop}")
+    });
     for n in out {
         println!("{n}");
     }
@@ -1,9 +1,8 @@
-use optimization::middle_end::{analysis::*, lir::*};
-use optimization::middle_end::{analysis_rdef::reaching_defs::{Env, analyze}};
+use optimization::middle_end::lir::*;
+use optimization::middle_end::analysis_rdef::reaching_defs::analyze;
 use optimization::middle_end::analysis_rdef::ProgramPoint;
 use optimization::commons::Valid;
 use std::collections::{BTreeMap as Map, BTreeSet as Set};
-use pretty_assertions::assert_eq;
 use std::env;
 
 
@@ -27,10 +26,6 @@ pub fn run() {
 
 pub fn run_test() {
 
-    let lir_file_name = "./test-inputs-02/complex/binary_trees.lir";
-    let json_file_name = "./test-inputs-02/complex/binary_trees.lir.json";
-    let function_name = "main";
-
     let lir_file_name = "./test-inputs-02/simple/call_ext.lir";
     let json_file_name = "./test-inputs-02/simple/call_ext.lir.json";
     let function_name = "main";
@@ -44,7 +39,7 @@ fn main() {
 
 }
 
-fn rdef_lir(lir_file_name: &str, json_file_name: &str, function_name: &str) -> String {
+fn rdef_lir(lir_file_name: &str, _json_file_name: &str, function_name: &str) -> String {
     let input_string = read_from(lir_file_name);
     
     let lir_parsed = parse_lir(&input_string);
@@ -60,25 +55,6 @@ fn read_from(path: &str) -> String {
     .expect("The input file does not contain valid utf-8 text")
 }
 
-fn nicely_v2(result: &Map<ProgramPoint, Set<ProgramPoint>>) -> String {
-    let mut s = String::new();
-    for (bb, soln) in result {
-        if soln.is_empty() {
-            continue;
-        }
-        s += &bb.to_string();
-        s += " -> {";
-        for pp in soln {
-            s += &format!("{pp}, ");
-        }
-        s.pop();
-        s.pop();
-        s += "}\n";
-    }
-    s += "\n";
-    s
-}
-
 fn nicely_v3(result: &Map<ProgramPoint, Set<ProgramPoint>>) -> String {
     let mut output = result.iter()
         .filter(|(_, soln)| !soln.is_empty())
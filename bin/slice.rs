@@ -1,13 +1,8 @@
-use optimization::middle_end::{analysis::*, lir::*};
-use optimization::middle_end::slice::slice_solve::{slice, slice_ptrs};
-use optimization::middle_end::slice::*;
+use optimization::middle_end::lir::*;
+use optimization::middle_end::slice::slice_solve::slice;
 use optimization::commons::Valid;
-use std::collections::{BTreeMap as Map, BTreeSet as Set};
-use pretty_assertions::assert_eq;
 use std::env;
 
-use optimization::middle_end::constraints::*;
-
 // cheesing the assignment might not have been worth it i should just write a parser
 
 pub fn run() {
@@ -15,7 +10,6 @@ pub fn run() {
 
     let lir_file_name = &args[1];
     let mut target = args[3].split('#'); // <function>#<basicblock>#{<index> | term}
-    let pts_to_path = &args[4];
     let function = target.next().unwrap();
     let basicblock = target.next().unwrap();
     let idx = target.next().unwrap();
@@ -53,17 +47,7 @@ fn slice_lir(lir_file_name: &str, function: &str, basicblock: &str, term: Option
     let input_string = read_from(lir_file_name);
     
     let lir_parsed = parse_lir(&input_string);
-    let analyzed = slice(&lir_parsed, function, basicblock, term);
-    analyzed
-}
-
-fn slice_lir_ptrs(lir_file_name: &str, function: &str, basicblock: &str, term: Option<usize>, pts_to_path: &str) -> String {
-    let input_string = read_from(lir_file_name);
-    let pts_to_str = read_from(pts_to_path);
-
-    let lir_parsed = parse_lir(&input_string);
-    let analyzed = slice_ptrs(&lir_parsed, function, basicblock, term, &pts_to_str);
-    analyzed
+    slice(&lir_parsed, function, basicblock, term)
 }
 
 fn main() {
@@ -73,15 +57,6 @@ fn main() {
 }
 
 
-fn final_path(package: &str, path: &str) -> String{
-    format!("{package}{path}")
-}
-
-
-fn lir_path(path: &str) -> String {
-    format!("{path}.lir")
-}
-
 fn read_from(path: &str) -> String {
     String::from_utf8(
         std::fs::read(path)
@@ -93,12 +68,3 @@ fn read_from(path: &str) -> String {
 fn parse_lir(input: &str) -> Valid<Program> {
     input.parse::<Program>().unwrap().validate().unwrap()
 }
-    
-fn nicely(result: &Set<Constraint>) -> String {
-    use std::fmt::Write;
-
-    result.iter().fold(String::new(), |mut output, b| {
-        let _ = writeln!(output, "{b}");
-        output
-    })
-}
\ No newline at end of file
@@ -1,13 +1,9 @@
-    use optimization::middle_end::{analysis::*, lir::*};
+    use optimization::middle_end::lir::*;
     use optimization::middle_end::taint::taint_analysis::analyze;
-    use optimization::middle_end::taint::*;
     use optimization::commons::Valid;
     use std::collections::{BTreeMap as Map, BTreeSet as Set};
-    use pretty_assertions::assert_eq;
     use std::env;
 
-    use optimization::middle_end::constraints::*;
-
     // cheesing the assignment might not have been worth it i should just write a parser
 
     pub fn run() {
@@ -19,9 +15,9 @@
         }
 
         let lir_file_name = &args[1];
-        let json_file_name = &args[2]; // Not used in your code but required for submission
+        let _json_file_name = &args[2]; // Not used in your code but required for submission
         let pts_to_path = &args[3];
-        let context_sensitivity = &args[4];
+        let _context_sensitivity = &args[4];
 
         // Since you're not using the function name from command-line, you can set it to "main" or adjust as needed
         let function_name = "main";
@@ -35,7 +31,6 @@
     pub fn run_test() {
 
         let lir_file_name = "./test-inputs-taint/tainted01.lir";
-        let soln_path = "./test-inputs-taint/tainted01.lir.soln";
         let pts_to_path = "./test-inputs-taint/tainted01.lir.ptsto";
 
         let output = taint_lir(lir_file_name, "main", pts_to_path);
@@ -77,15 +72,6 @@
         pts_to_map
     }
 
-    fn final_path(package: &str, path: &str) -> String{
-        format!("{package}{path}")
-    }
-
-
-    fn lir_path(path: &str) -> String {
-        format!("{path}.lir")
-    }
-
     fn read_from(path: &str) -> String {
         String::from_utf8(
             std::fs::read(path)
@@ -97,12 +83,3 @@
     fn parse_lir(input: &str) -> Valid<Program> {
         input.parse::<Program>().unwrap().validate().unwrap()
     }
-        
-    fn nicely(result: &Set<Constraint>) -> String {
-        use std::fmt::Write;
-
-        result.iter().fold(String::new(), |mut output, b| {
-            let _ = writeln!(output, "{b}");
-            output
-        })
-    }
\ No newline at end of file
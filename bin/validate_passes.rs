@@ -0,0 +1,71 @@
+// Translation validation: runs optimization passes on LIR programs and checks
+// with the interpreter that every pass keeps what the programs do.
+//
+// Usage: validate_passes <passes> <path>...
+//
+// where <passes> is a comma-separated list of pass and pipeline names (e.g.
+// `O1` or `const-prop,dse`) and each path is a LIR file or a directory to
+// search for them.  The exit status is 1 if a pass broke some program.
+
+use std::env;
+use std::path::Path;
+
+use optimization::commons::{lir_files, read_from};
+use optimization::middle_end::lir::Program;
+use optimization::middle_end::optimization::pass_manager::PassManager;
+use optimization::middle_end::optimization::translation_validation::validate_pipeline;
+
+pub fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 3 {
+        eprintln!("Usage: {} <passes> <path>...", args[0]);
+        std::process::exit(2);
+    }
+
+    let passes: Vec<&str> = args[1].split(',').collect();
+    if let Err(e) = pipeline(&passes) {
+        eprintln!("{e}");
+        std::process::exit(2);
+    }
+
+    let mut files = vec![];
+    for path in &args[2..] {
+        lir_files(Path::new(path), &mut files);
+    }
+
+    let mut broken = 0;
+    for file in &files {
+        let program = match read_from(file).parse::<Program>().map_err(|e| format!("{e:?}")) {
+            Ok(program) => program.validate().map_err(|e| format!("{e:?}")),
+            Err(e) => Err(e),
+        };
+        let program = match program {
+            Ok(program) => program,
+            Err(e) => {
+                println!("skipped {}: not a valid program: {e}", file.display());
+                continue;
+            }
+        };
+
+        match validate_pipeline(&mut pipeline(&passes).unwrap(), program) {
+            Ok(_) => println!("ok {}", file.display()),
+            Err(failure) => {
+                broken += 1;
+                println!("FAILED {}: {failure}", file.display());
+            }
+        }
+    }
+
+    println!("{} programs checked, {broken} broken", files.len());
+    if broken > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn pipeline(passes: &[&str]) -> Result<PassManager, String> {
+    let mut pm = PassManager::new();
+    for pass in passes {
+        pm.add_named(pass)?;
+    }
+    Ok(pm)
+}
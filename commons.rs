@@ -3,6 +3,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet as Set;
+use std::path::{Path, PathBuf};
 
 // SECTION: validation errors
 
@@ -63,3 +64,35 @@ pub struct Valid<T>(pub T);
 pub fn skip_validation<T>(x: T) -> Valid<T> {
     Valid(x)
 }
+
+// SECTION: input files
+
+/// Adds the LIR files at `path` to `files`, searching directories recursively,
+/// in order.  A path that is not a directory is added whatever its extension.
+pub fn lir_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return;
+    }
+
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(path)
+        .unwrap_or_else(|_| panic!("Could not read the directory {}", path.display()))
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    entries.sort();
+
+    for entry in entries {
+        if entry.is_dir() || entry.extension().is_some_and(|ext| ext == "lir") {
+            lir_files(&entry, files);
+        }
+    }
+}
+
+/// The contents of the text file at `path`.
+pub fn read_from(path: &Path) -> String {
+    String::from_utf8(
+        std::fs::read(path)
+            .unwrap_or_else(|_| panic!("Could not read the input file {}", path.display())),
+    )
+    .expect("The input file does not contain valid utf-8 text")
+}
@@ -5,10 +5,7 @@ use serde::{Deserialize, Serialize};
 pub mod associated_impl;
 pub mod display_impl;
 pub mod fromstr_impl;
-
-pub use associated_impl::*;
-pub use display_impl::*;
-pub use fromstr_impl::*;
+pub mod validate;
 
 // SECTION: cflat types
 
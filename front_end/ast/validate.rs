@@ -335,7 +335,7 @@ fn check_types(program: &Program) -> ValidationError {
 
                     arg_ty
                         .into_iter()
-                        .zip(param_ty.clone().into_iter())
+                        .zip(param_ty.clone())
                         .try_for_each(|(ty1, ty2)| {
                             check_type_eq(ctx, "argument of function call", ty1, FullType(ty2))
                         })?;
@@ -0,0 +1,596 @@
+// random cflat programs, for differential testing.
+//
+// `generate` builds a program from a seed: the same seed and configuration
+// always give the same program.  the programs pass `ast::validate::validate`,
+// lower to valid LIR, and run without runtime errors, well within the
+// interpreter's instruction limit:
+//
+// - functions only call the functions defined before them, directly or through
+//   function pointers, so there is no recursion.
+// - loops count up to a small constant, with a counter that nothing else
+//   assigns and that is incremented first thing in the body, so `continue`
+//   cannot skip it.
+// - every function estimates the statements it runs, calls and loop
+//   iterations included, and stops adding loops and calls over its budget.
+// - pointers are never null: pointer variables are allocated before anything
+//   else, and the pointer fields of a struct are set right after allocating it.
+// - arrays all have the same length, and are only indexed by constants below
+//   it.
+// - divisions are by non-zero constants, and integers in variables and memory
+//   stay below 1000 in absolute value: only assignments to variables compute,
+//   reducing the result modulo 1000 afterwards, so nothing overflows.
+//
+// `break`, `continue` and early returns only end the then branch of a
+// conditional, so no statement is unreachable.
+
+use super::ast::*;
+
+/// The shape of the generated programs.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// the number of functions besides `main`.
+    pub functions: usize,
+    pub structs: usize,
+    pub globals: usize,
+    /// the most statements in a block.
+    pub stmts: usize,
+    /// the most nested conditionals and loops.
+    pub depth: usize,
+    /// the most iterations of a loop.
+    pub trip_count: i32,
+    /// the most statements a function may run, estimated.  a statement runs
+    /// a dozen LIR instructions or so.
+    pub budget: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            functions: 4,
+            structs: 2,
+            globals: 2,
+            stmts: 6,
+            depth: 3,
+            trip_count: 5,
+            budget: 5000,
+        }
+    }
+}
+
+// the length of every array.
+const ARRAY_LEN: i32 = 4;
+// integers stay strictly below this, in absolute value.
+const BOUND: i32 = 1000;
+
+/// The program for a seed.
+pub fn generate(seed: u64, config: &Config) -> Program {
+    let mut gen = Generator {
+        rng: Rng(seed),
+        config,
+        sigs: vec![],
+        functions: vec![],
+    };
+
+    let typedefs: Vec<Typedef> = (0..config.structs)
+        .map(|k| Typedef {
+            name: format!("S{k}"),
+            fields: vec![
+                decl("v", int_ty()),
+                decl("w", int_ty()),
+                decl("next", record_ty(k)),
+                decl("arr", array_ty()),
+            ],
+        })
+        .collect();
+
+    gen.sigs = vec![vec![], vec![int_ty()], vec![int_ty(), int_ty()], vec![array_ty(), int_ty()]];
+    gen.sigs.extend((0..config.structs).map(|k| vec![record_ty(k), int_ty()]));
+
+    let globals: Vec<Decl> = (0..config.globals).map(|i| decl(&format!("g{i}"), int_ty())).collect();
+
+    let mut functions = vec![];
+    for i in 0..config.functions {
+        let sig = gen.rng.below(gen.sigs.len());
+        functions.push(gen.function(&format!("f{i}"), Some(sig), &globals));
+    }
+    functions.push(gen.function("main", None, &globals));
+
+    Program {
+        globals,
+        typedefs,
+        externs: vec![decl("print", func_ty(None, vec![int_ty()]))],
+        functions,
+    }
+}
+
+fn decl(name: &str, typ: Type) -> Decl {
+    Decl {
+        name: name.to_string(),
+        typ,
+    }
+}
+
+fn array_ty() -> Type {
+    ptr_ty(int_ty())
+}
+
+fn record_ty(k: usize) -> Type {
+    ptr_ty(struct_ty(struct_id(&format!("S{k}"))))
+}
+
+fn fn_ptr_ty(params: &[Type]) -> Type {
+    ptr_ty(func_ty(Some(int_ty()), params.to_vec()))
+}
+
+fn id(x: impl ToString) -> Exp {
+    Exp::Id(x.to_string())
+}
+
+fn num(n: i32) -> Exp {
+    Exp::Num(n)
+}
+
+fn arith(lhs: Exp, op: ArithOp, rhs: Exp) -> Exp {
+    Exp::Arith(Box::new(lhs), op, Box::new(rhs))
+}
+
+fn assign(lhs: Lval, rhs: Exp) -> Stmt {
+    Stmt::Assign { lhs, rhs: Rhs::Exp(rhs) }
+}
+
+fn field(ptr: Lval, field: &str) -> Lval {
+    Lval::FieldAccess {
+        ptr: Box::new(ptr),
+        field: field.to_string(),
+    }
+}
+
+fn var(x: impl ToString) -> Lval {
+    Lval::Id(x.to_string())
+}
+
+// SplitMix64.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // a number in 0..n, for n > 0.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    // a number in lo..=hi.
+    fn range(&mut self, lo: i32, hi: i32) -> i32 {
+        lo + self.below((hi - lo + 1) as usize) as i32
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<'a, T>(&mut self, xs: &'a [T]) -> &'a T {
+        &xs[self.below(xs.len())]
+    }
+}
+
+struct Generator<'a> {
+    rng: Rng,
+    config: &'a Config,
+    // the parameter types of the functions other than main, which all return
+    // an int.
+    sigs: Vec<Vec<Type>>,
+    // the functions generated so far: their name, signature and estimated
+    // cost.
+    functions: Vec<(String, usize, u64)>,
+}
+
+// The variables of the function being generated.
+#[derive(Default)]
+struct Scope {
+    decls: Vec<Decl>,
+    // the int variables that can be assigned.
+    ints: Vec<String>,
+    // the loop counters, which can only be read.
+    counters: Vec<String>,
+    arrays: Vec<String>,
+    // the pointers to each struct.
+    records: Vec<Vec<String>>,
+    // the function pointers, with their signature.
+    fn_ptrs: Vec<(String, usize)>,
+    fresh: usize,
+    // the number of enclosing loops.
+    loops: usize,
+    // how many times the current statement runs per call, at most.
+    runs: u64,
+    // the estimated statements run so far.
+    cost: u64,
+}
+
+impl Scope {
+    fn fresh(&mut self, prefix: &str, typ: Type) -> String {
+        let name = format!("{prefix}{}", self.fresh);
+        self.fresh += 1;
+        self.decls.push(decl(&name, typ));
+        name
+    }
+}
+
+impl Generator<'_> {
+    fn function(&mut self, name: &str, sig: Option<usize>, globals: &[Decl]) -> Function {
+        let mut scope = Scope {
+            ints: globals.iter().map(|g| g.name.clone()).collect(),
+            records: vec![vec![]; self.config.structs],
+            runs: 1,
+            ..Scope::default()
+        };
+
+        let params: Vec<Decl> = sig.map_or(vec![], |sig| self.sigs[sig].clone())
+            .into_iter()
+            .enumerate()
+            .map(|(i, typ)| decl(&format!("n{i}"), typ))
+            .collect();
+        for param in &params {
+            match self.kind(&param.typ) {
+                Kind::Int => scope.ints.push(param.name.clone()),
+                Kind::Array => scope.arrays.push(param.name.clone()),
+                Kind::Record(k) => scope.records[k].push(param.name.clone()),
+            }
+        }
+
+        let mut stmts = self.prologue(&mut scope);
+        stmts.extend(self.block(&mut scope, self.config.depth));
+        if name == "main" {
+            for x in scope.ints.clone() {
+                stmts.push(print(id(&x)));
+            }
+        }
+        stmts.push(Stmt::Return(Some(self.int_atom(&scope))));
+
+        if let Some(sig) = sig {
+            self.functions.push((name.to_string(), sig, scope.cost));
+        }
+
+        Function {
+            name: name.to_string(),
+            params,
+            rettyp: Some(int_ty()),
+            body: Body {
+                decls: scope.decls.into_iter().map(|d| (d, None)).collect(),
+                stmts,
+            },
+        }
+    }
+
+    fn kind(&self, typ: &Type) -> Kind {
+        if *typ == int_ty() {
+            Kind::Int
+        } else if *typ == array_ty() {
+            Kind::Array
+        } else {
+            Kind::Record((0..self.config.structs).find(|k| *typ == record_ty(*k)).unwrap())
+        }
+    }
+
+    // Declare the locals, and allocate the pointers.
+    fn prologue(&mut self, scope: &mut Scope) -> Vec<Stmt> {
+        let mut stmts = vec![];
+
+        for _ in 0..self.rng.range(2, 4) {
+            let x = scope.fresh("x", int_ty());
+            scope.ints.push(x);
+        }
+
+        for _ in 0..self.rng.range(1, 2) {
+            let a = scope.fresh("a", array_ty());
+            stmts.push(alloc_array(&a));
+            scope.arrays.push(a);
+        }
+
+        for k in 0..self.config.structs {
+            let p = scope.fresh("p", record_ty(k));
+            stmts.extend(self.alloc_record(scope, &p, k));
+            scope.records[k].push(p);
+        }
+
+        for sig in 0..self.sigs.len() {
+            let callees: Vec<&String> = self.functions.iter().filter(|(_, s, _)| *s == sig).map(|(f, _, _)| f).collect();
+            if callees.is_empty() || !self.rng.chance(50) {
+                continue;
+            }
+            let f = self.rng.pick(&callees).to_string();
+            let fp = scope.fresh("fp", fn_ptr_ty(&self.sigs[sig]));
+            stmts.push(assign(var(&fp), id(&f)));
+            scope.fn_ptrs.push((fp, sig));
+        }
+
+        stmts
+    }
+
+    // Allocate a struct and set its pointer fields.
+    fn alloc_record(&mut self, scope: &Scope, p: &str, k: usize) -> Vec<Stmt> {
+        let a = self.rng.pick(&scope.arrays).clone();
+        vec![
+            Stmt::Assign {
+                lhs: var(p),
+                rhs: Rhs::New {
+                    typ: struct_ty(struct_id(&format!("S{k}"))),
+                    num: None,
+                },
+            },
+            assign(field(var(p), "next"), id(p)),
+            assign(field(var(p), "arr"), id(&a)),
+        ]
+    }
+
+    fn affordable(&self, scope: &Scope, cost: u64) -> bool {
+        scope.cost + scope.runs * cost <= self.config.budget
+    }
+
+    fn block(&mut self, scope: &mut Scope, depth: usize) -> Vec<Stmt> {
+        let mut stmts = vec![];
+        for _ in 0..self.rng.range(1, self.config.stmts as i32) {
+            if !self.affordable(scope, 2) {
+                break;
+            }
+            self.stmt(scope, depth, &mut stmts);
+        }
+        stmts
+    }
+
+    fn stmt(&mut self, scope: &mut Scope, depth: usize, stmts: &mut Vec<Stmt>) {
+        scope.cost += scope.runs;
+
+        match self.rng.below(100) {
+            0..=29 => self.assign_int(scope, stmts),
+            30..=44 => {
+                let lhs = self.int_cell(scope);
+                let rhs = self.int_atom(scope);
+                stmts.push(assign(lhs, rhs));
+            }
+            45..=54 => self.assign_ptr(scope, stmts),
+            55..=69 => self.call(scope, stmts),
+            70..=74 => {
+                let x = self.int_atom(scope);
+                stmts.push(print(x));
+            }
+            _ if depth == 0 => self.assign_int(scope, stmts),
+            75..=86 => {
+                let guard = self.int_exp(scope, 2);
+                let mut tt = self.block(scope, depth - 1);
+                if self.rng.chance(20) {
+                    tt.push(self.exit(scope));
+                }
+                let ff = if self.rng.chance(50) { self.block(scope, depth - 1) } else { vec![] };
+                stmts.push(Stmt::If { guard, tt, ff });
+            }
+            _ => self.while_loop(scope, depth, stmts),
+        }
+    }
+
+    // A statement leaving the loop, the iteration or the function.
+    fn exit(&mut self, scope: &Scope) -> Stmt {
+        match self.rng.below(3) {
+            0 if scope.loops > 0 => Stmt::Break,
+            1 if scope.loops > 0 => Stmt::Continue,
+            _ => Stmt::Return(Some(self.int_atom(scope))),
+        }
+    }
+
+    fn while_loop(&mut self, scope: &mut Scope, depth: usize, stmts: &mut Vec<Stmt>) {
+        let trips = self.rng.range(1, self.config.trip_count);
+        // the guard and the increment run on every iteration.
+        if !self.affordable(scope, trips as u64 * 4) {
+            return self.assign_int(scope, stmts);
+        }
+
+        let c = scope.fresh("c", int_ty());
+        stmts.push(assign(var(&c), num(0)));
+
+        let runs = scope.runs;
+        scope.runs *= trips as u64;
+        scope.cost += scope.runs * 2;
+        scope.loops += 1;
+        scope.counters.push(c.clone());
+
+        let mut body = vec![assign(var(&c), arith(id(&c), ArithOp::Add, num(1)))];
+        body.extend(self.block(scope, depth - 1));
+
+        scope.counters.pop();
+        scope.loops -= 1;
+        scope.runs = runs;
+
+        stmts.push(Stmt::While {
+            guard: Exp::Compare(Box::new(id(&c)), CompareOp::Lt, Box::new(num(trips))),
+            body,
+        });
+    }
+
+    // Assign a computed int to a variable, keeping it below the bound.
+    fn assign_int(&mut self, scope: &mut Scope, stmts: &mut Vec<Stmt>) {
+        let x = self.rng.pick(&scope.ints).clone();
+        let e = self.int_exp(scope, 2);
+        let computed = !matches!(e, Exp::Num(_) | Exp::Id(_) | Exp::Deref(_) | Exp::ArrayAccess { .. } | Exp::FieldAccess { .. } | Exp::Call { .. });
+        stmts.push(assign(var(&x), e));
+        if !computed {
+            return;
+        }
+
+        // x = x - x / BOUND * BOUND
+        let quotient = arith(arith(id(&x), ArithOp::Divide, num(BOUND)), ArithOp::Multiply, num(BOUND));
+        stmts.push(assign(var(&x), arith(id(&x), ArithOp::Subtract, quotient)));
+        scope.cost += scope.runs;
+    }
+
+    fn assign_ptr(&mut self, scope: &mut Scope, stmts: &mut Vec<Stmt>) {
+        let k = self.rng.below(self.config.structs.max(1));
+        let p = (self.config.structs > 0).then(|| self.rng.pick(&scope.records[k]).clone());
+        let a = self.rng.pick(&scope.arrays).clone();
+
+        match (self.rng.below(6), p) {
+            (0, _) => {
+                let b = self.rng.pick(&scope.arrays).clone();
+                stmts.push(assign(var(&a), id(&b)));
+            }
+            (1, Some(p)) => {
+                let q = self.rng.pick(&scope.records[k]).clone();
+                stmts.push(assign(var(&p), id(&q)));
+            }
+            (2, Some(p)) => stmts.push(assign(var(&p), Exp::FieldAccess {
+                ptr: Box::new(id(&p)),
+                field: "next".to_string(),
+            })),
+            (3, Some(p)) => {
+                let q = self.rng.pick(&scope.records[k]).clone();
+                stmts.push(assign(field(var(&p), "next"), id(&q)));
+            }
+            (4, Some(p)) => stmts.push(assign(field(var(&p), "arr"), id(&a))),
+            (5, Some(p)) => stmts.extend(self.alloc_record(scope, &p, k)),
+            _ => stmts.push(alloc_array(&a)),
+        }
+    }
+
+    fn call(&mut self, scope: &mut Scope, stmts: &mut Vec<Stmt>) {
+        // the callees, with their signature and cost.  a function pointer
+        // costs as much as the costliest function it could point to.
+        let mut callees: Vec<(String, usize, u64)> = self.functions.clone();
+        for (fp, sig) in &scope.fn_ptrs {
+            let cost = self.functions.iter().filter(|(_, s, _)| s == sig).map(|(_, _, c)| *c).max().unwrap();
+            callees.push((fp.clone(), *sig, cost));
+        }
+        callees.retain(|(_, _, cost)| self.affordable(scope, cost + 1));
+
+        if callees.is_empty() {
+            let x = self.int_atom(scope);
+            return stmts.push(print(x));
+        }
+
+        let (callee, sig, cost) = self.rng.pick(&callees).clone();
+        scope.cost += scope.runs * cost;
+
+        let args: Vec<Exp> = self.sigs[sig]
+            .clone()
+            .iter()
+            .map(|typ| match self.kind(typ) {
+                Kind::Int => self.int_atom(scope),
+                Kind::Array => id(self.rng.pick(&scope.arrays)),
+                Kind::Record(k) => id(self.rng.pick(&scope.records[k])),
+            })
+            .collect();
+        if self.rng.chance(80) {
+            let x = self.rng.pick(&scope.ints).clone();
+            let call = Exp::Call {
+                callee: Box::new(id(&callee)),
+                args,
+            };
+            stmts.push(assign(var(&x), call));
+        } else {
+            stmts.push(Stmt::Call { callee: var(&callee), args });
+        }
+    }
+
+    // An int expression, nested up to `depth` operators deep.  Its value is
+    // below BOUND^(2^depth), with operands below BOUND.
+    fn int_exp(&mut self, scope: &Scope, depth: usize) -> Exp {
+        if depth == 0 || self.rng.chance(30) {
+            return self.int_atom(scope);
+        }
+
+        let sub = |gen: &mut Self| Box::new(gen.int_exp(scope, depth - 1));
+        match self.rng.below(10) {
+            0 => Exp::Neg(sub(self)),
+            1 => Exp::Not(sub(self)),
+            2 => Exp::Arith(sub(self), ArithOp::Divide, Box::new(num(self.rng.range(1, 9)))),
+            3 => {
+                let op = *self.rng.pick(&[CompareOp::Equal, CompareOp::NotEq, CompareOp::Lt, CompareOp::Lte, CompareOp::Gt, CompareOp::Gte]);
+                Exp::Compare(sub(self), op, sub(self))
+            }
+            4 => Exp::And(sub(self), sub(self)),
+            5 => Exp::Or(sub(self), sub(self)),
+            _ => {
+                let op = *self.rng.pick(&[ArithOp::Add, ArithOp::Subtract, ArithOp::Multiply]);
+                Exp::Arith(sub(self), op, sub(self))
+            }
+        }
+    }
+
+    // A constant, or an int read from a variable or memory.
+    fn int_atom(&mut self, scope: &Scope) -> Exp {
+        match self.rng.below(8) {
+            0 | 1 => num(self.rng.range(0, 20)),
+            2 if !scope.counters.is_empty() => id(self.rng.pick(&scope.counters)),
+            3 | 4 => lval_exp(&self.int_cell(scope)),
+            _ => id(self.rng.pick(&scope.ints)),
+        }
+    }
+
+    // An int in memory.
+    fn int_cell(&mut self, scope: &Scope) -> Lval {
+        let a = var(self.rng.pick(&scope.arrays));
+        let index = num(self.rng.range(0, ARRAY_LEN - 1));
+
+        if self.config.structs == 0 || self.rng.chance(40) {
+            return match self.rng.below(2) {
+                0 => Lval::Deref(Box::new(a)),
+                _ => Lval::ArrayAccess { ptr: Box::new(a), index },
+            };
+        }
+
+        let k = self.rng.below(self.config.structs);
+        let p = var(self.rng.pick(&scope.records[k]));
+        match self.rng.below(4) {
+            0 => field(p, "v"),
+            1 => field(p, "w"),
+            2 => field(field(p, "next"), "v"),
+            _ => Lval::ArrayAccess {
+                ptr: Box::new(field(p, "arr")),
+                index,
+            },
+        }
+    }
+}
+
+enum Kind {
+    Int,
+    Array,
+    Record(usize),
+}
+
+fn alloc_array(a: &str) -> Stmt {
+    Stmt::Assign {
+        lhs: var(a),
+        rhs: Rhs::New {
+            typ: int_ty(),
+            num: Some(num(ARRAY_LEN)),
+        },
+    }
+}
+
+fn print(x: Exp) -> Stmt {
+    Stmt::Call {
+        callee: var("print"),
+        args: vec![x],
+    }
+}
+
+// The expression reading an lval.
+fn lval_exp(lval: &Lval) -> Exp {
+    match lval {
+        Lval::Id(x) => id(x),
+        Lval::Deref(ptr) => Exp::Deref(Box::new(lval_exp(ptr))),
+        Lval::ArrayAccess { ptr, index } => Exp::ArrayAccess {
+            ptr: Box::new(lval_exp(ptr)),
+            index: Box::new(index.clone()),
+        },
+        Lval::FieldAccess { ptr, field } => Exp::FieldAccess {
+            ptr: Box::new(lval_exp(ptr)),
+            field: field.clone(),
+        },
+    }
+}
@@ -4,6 +4,7 @@
 use std::collections::{BTreeMap as Map, BTreeSet as Set};
 
 pub mod ast;
+pub mod generate;
 pub mod lexer;
 pub mod lower;
 pub mod parser;
@@ -2,6 +2,7 @@
 
 use super::*;
 
+mod generate_tests;
 mod lex_tests;
 mod lower_tests;
 mod parse_tests;
@@ -0,0 +1,53 @@
+// random program generator tests.
+
+use super::*;
+use crate::front_end::ast::validate::validate;
+use crate::front_end::generate::{generate, Config};
+use crate::interpreter::interpret_with_output;
+use crate::middle_end::lir;
+
+const SEEDS: u64 = 200;
+
+#[test]
+fn programs_are_valid() {
+    for seed in 0..SEEDS {
+        let program = generate(seed, &Config::default());
+        if let Err(err) = validate(&program) {
+            panic!("seed {seed}: {err:?}\n{}", program.pretty_print());
+        }
+        lir::validate(&lower(&Valid(program))).unwrap_or_else(|err| panic!("seed {seed}: {err:?}"));
+    }
+}
+
+#[test]
+fn programs_run_to_completion() {
+    for seed in 0..SEEDS {
+        let program = generate(seed, &Config::default());
+        let lowered = lower(&Valid(program.clone()));
+        if let Err(err) = interpret_with_output(lowered) {
+            panic!("seed {seed}: {err}\n{}", program.pretty_print());
+        }
+    }
+}
+
+#[test]
+fn programs_are_deterministic() {
+    let config = Config {
+        functions: 6,
+        depth: 4,
+        ..Config::default()
+    };
+    for seed in 0..20 {
+        assert_eq!(generate(seed, &config), generate(seed, &config));
+    }
+    assert_ne!(generate(1, &config), generate(2, &config));
+}
+
+#[test]
+fn programs_print_and_parse_back() {
+    for seed in 0..SEEDS {
+        let program = generate(seed, &Config::default());
+        assert_eq!(parse(&program.pretty_print()).as_ref(), Ok(&program), "seed {seed}");
+    }
+}
+
@@ -2,9 +2,15 @@
 
 use crate::middle_end::lir::*;
 use derive_more::Display;
-use std::{collections::BTreeMap as Map, mem};
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+use std::mem;
 use Address::ToHeap;
 
+pub mod trace;
+
+#[cfg(test)]
+mod tests;
+
 // Maximum number of instructions to run a program for.
 const INST_LIMIT: usize = 1_000_000;
 
@@ -16,16 +22,26 @@ pub fn interpret(program: Program) -> Result<i64, RuntimeError> {
 // Interpret given program, return the return value of `main` and the integers
 // printed via the external function `print`.
 pub fn interpret_with_output(program: Program) -> Result<(i64, Vec<i64>), RuntimeError> {
+    interpret_observed(program, &mut ()).map_err(|(stop, _)| RuntimeError(stop.to_string()))
+}
+
+// Interpret given program like `interpret_with_output`, showing the state of
+// the program to `observer` before each instruction and terminal runs.  The
+// integers printed are returned whether or not the program finishes.
+pub fn interpret_observed(
+    program: Program,
+    observer: &mut dyn Observer,
+) -> Result<(i64, Vec<i64>), (Stop, Vec<i64>)> {
     let mut s = State::new(program);
     for _ in 0..INST_LIMIT {
-        if let Some(r) = s.step()? {
-            return Ok((r, s.output));
+        match s.step(observer) {
+            Ok(Some(r)) => return Ok((r, s.output)),
+            Ok(None) => {}
+            Err(e) => return Err((Stop::Error(e), s.output)),
         }
     }
 
-    Err(RuntimeError(format!(
-        "the program did not finish in {INST_LIMIT} instructions."
-    )))
+    Err((Stop::OutOfInstructions, s.output))
 }
 
 // A runtime error with explanatory message.
@@ -33,6 +49,129 @@ pub fn interpret_with_output(program: Program) -> Result<(i64, Vec<i64>), Runtim
 pub struct RuntimeError(pub String);
 impl std::error::Error for RuntimeError {}
 
+// Why a program stopped before `main` returned.
+#[derive(Clone, Debug, Display, Eq, PartialEq)]
+pub enum Stop {
+    Error(RuntimeError),
+    #[display(fmt = "the program did not finish in {} instructions.", INST_LIMIT)]
+    OutOfInstructions,
+}
+
+// Instrumentation of the interpreter, e.g. to check analysis results against
+// what the program does.  Every method does nothing unless overridden.
+pub trait Observer {
+    // Called when control enters the current block of the frame, from the
+    // given block of the same function, or from the caller (or the start of
+    // the program) if None.
+    fn enter(&mut self, _from: Option<&BbId>, _frame: &Frame) {}
+
+    // Called before the instruction at `point` runs, where the terminal of a
+    // block is at index `insts.len()`.  The phis at the start of a block run
+    // together, so the points between them are not shown.
+    fn before(&mut self, _point: &(BbId, usize), _frame: &Frame) {}
+
+    // Called after the instruction at `point` of `func` ran.  After a call or
+    // a return, the frame is the one control went to.
+    fn after(&mut self, _func: &FuncId, _point: &(BbId, usize), _frame: &Frame) {}
+}
+
+impl Observer for () {}
+
+impl<O: Observer> Observer for Option<O> {
+    fn enter(&mut self, from: Option<&BbId>, frame: &Frame) {
+        if let Some(o) = self {
+            o.enter(from, frame);
+        }
+    }
+
+    fn before(&mut self, point: &(BbId, usize), frame: &Frame) {
+        if let Some(o) = self {
+            o.before(point, frame);
+        }
+    }
+
+    fn after(&mut self, func: &FuncId, point: &(BbId, usize), frame: &Frame) {
+        if let Some(o) = self {
+            o.after(func, point, frame);
+        }
+    }
+}
+
+impl<A: Observer, B: Observer> Observer for (A, B) {
+    fn enter(&mut self, from: Option<&BbId>, frame: &Frame) {
+        self.0.enter(from, frame);
+        self.1.enter(from, frame);
+    }
+
+    fn before(&mut self, point: &(BbId, usize), frame: &Frame) {
+        self.0.before(point, frame);
+        self.1.before(point, frame);
+    }
+
+    fn after(&mut self, func: &FuncId, point: &(BbId, usize), frame: &Frame) {
+        self.0.after(func, point, frame);
+        self.1.after(func, point, frame);
+    }
+}
+
+// The state of the running function, as an observer sees it.
+pub struct Frame<'a> {
+    state: &'a State,
+}
+
+impl Frame<'_> {
+    pub fn func(&self) -> &FuncId {
+        &self.state.func
+    }
+
+    // The block running.
+    pub fn block(&self) -> &BasicBlock {
+        &self.state.control
+    }
+
+    // The variables the last instruction assigned, with their new values.  A
+    // call assigns the parameters of the callee.
+    pub fn changed(&self) -> Vec<(VarId, Observed)> {
+        self.state
+            .changed
+            .iter()
+            .filter_map(|x| Some((x.clone(), self.state.observe(&self.state.lookup(x).ok()?))))
+            .collect()
+    }
+
+    // The values of the variables that have one so far: the parameters, the
+    // locals assigned in this call, and the globals assigned since the
+    // program started.  The rest still hold their zero initialization.
+    //
+    // Ints are reported as is, and pointers by the allocation site or the
+    // variable they point into, named by [VarId::to_scope_string] like the
+    // locations of the points-to analysis.
+    pub fn values(&self) -> Map<VarId, Observed> {
+        self.state
+            .assigned
+            .iter()
+            .chain(&self.state.glob_assigned)
+            .filter_map(|x| Some((x.clone(), self.state.observe(&self.state.lookup(x).ok()?))))
+            .collect()
+    }
+}
+
+// A value of a variable, as it is shown to observers.
+#[derive(Clone, Debug, Display, Eq, PartialEq)]
+pub enum Observed {
+    Int(i64),
+    #[display(fmt = "nil")]
+    Nil,
+    // a pointer into the given location
+    #[display(fmt = "&{_0}")]
+    Loc(String),
+    // a pointer to the given function
+    #[display(fmt = "&{_0}")]
+    Fn(FuncId),
+    #[display(fmt = "<struct>")]
+    Struct,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
 enum Address {
     // null pointer
@@ -59,7 +198,11 @@ struct CallSite {
     next: BasicBlock,
     dst: Option<VarId>,
     env: Map<VarId, Value>,
+    boxed: Map<VarId, u32>,
     func: FuncId,
+    // the block making the call, which is the predecessor of `next`.
+    from: BbId,
+    assigned: Set<VarId>,
 }
 
 // Interpreter state. This is a CESK machine
@@ -73,10 +216,31 @@ struct State {
     func: FuncId,
     // current environment
     env: Map<VarId, Value>,
+    // the heap cells holding the current function's locals whose address was
+    // taken.  These locals are read and written through the store.
+    boxed: Map<VarId, u32>,
+    // the parameters of the current function and the locals assigned in the
+    // current call.
+    assigned: Set<VarId>,
     // global environment
     glob: Map<VarId, Value>,
+    // the globals assigned so far.
+    glob_assigned: Set<VarId>,
+    // the variables assigned by the last instruction.
+    changed: Vec<VarId>,
+    // the heap cells holding the globals whose address was taken.
+    glob_boxed: Map<VarId, u32>,
+    // the block control came from in the current function, to pick the
+    // arguments of phis.  None on entry to a function.
+    prev: Option<BbId>,
+    // the predecessors of each block of each function, in the order of phi
+    // arguments.
+    preds: Map<FuncId, Map<BbId, Set<BbId>>>,
     // store/heap
     store: Map<u32, Value>,
+    // the first address of each allocation in the store, with the allocation
+    // site or the variable it holds.
+    sites: Map<u32, VarId>,
     // call stack
     stack: Vec<CallSite>,
     // next available heap address
@@ -104,12 +268,34 @@ impl State {
             })
             .collect::<Map<VarId, Value>>();
 
+        let preds = program
+            .functions
+            .iter()
+            .map(|(f, func)| {
+                let mut preds: Map<BbId, Set<BbId>> = Map::new();
+                for (id, bb) in &func.body {
+                    for succ in bb.term.successors() {
+                        preds.entry(succ.clone()).or_default().insert(id.clone());
+                    }
+                }
+                (f.clone(), preds)
+            })
+            .collect();
+
         let mut state = State {
             control,
             program,
             env: Map::new(),
+            boxed: Map::new(),
+            assigned: Set::new(),
             glob: Map::new(),
+            glob_assigned: Set::new(),
+            changed: vec![],
+            glob_boxed: Map::new(),
+            prev: None,
+            preds,
             store: Map::new(),
+            sites: Map::new(),
             stack: vec![],
             func: func_id("main"),
             next_address: 1,
@@ -144,19 +330,83 @@ impl State {
 
     // Take a step: execute a whole basic block.  Returns Some(main's return
     // value) if this is the final step.
-    pub fn step(&mut self) -> Result<Option<i64>, RuntimeError> {
-        for inst in self.control.insts.clone() {
+    pub fn step(&mut self, observer: &mut dyn Observer) -> Result<Option<i64>, RuntimeError> {
+        let insts = self.control.insts.clone();
+        let num_phis = insts
+            .iter()
+            .take_while(|inst| matches!(inst, Instruction::Phi { .. }))
+            .count();
+
+        let func = self.func.clone();
+        let bb = self.control.id.clone();
+        observer.enter(self.prev.as_ref(), &Frame { state: self });
+
+        if num_phis > 0 {
+            let point = (bb.clone(), 0);
+            observer.before(&point, &Frame { state: self });
+            self.changed.clear();
+            self.execute_phis(&insts[..num_phis])?;
+            observer.after(&func, &point, &Frame { state: self });
+        }
+        for (i, inst) in insts.into_iter().enumerate().skip(num_phis) {
+            let point = (bb.clone(), i);
+            observer.before(&point, &Frame { state: self });
+            self.changed.clear();
             self.execute_inst(inst)?;
+            observer.after(&func, &point, &Frame { state: self });
         }
 
-        self.execute_terminal()
+        let point = (bb, self.control.insts.len());
+        observer.before(&point, &Frame { state: self });
+        self.changed.clear();
+        let r = self.execute_terminal()?;
+        observer.after(&func, &point, &Frame { state: self });
+        Ok(r)
     }
 
-    fn alloc_array(&mut self, n: u32, typ: &Type) -> Address {
+    // The phis at the start of a block all read the argument for the block
+    // control came from, before any of them is assigned.
+    fn execute_phis(&mut self, phis: &[Instruction]) -> Result<(), RuntimeError> {
+        if phis.is_empty() {
+            return Ok(());
+        }
+
+        let i = match &self.prev {
+            Some(prev) => self.preds[&self.func][&self.control.id]
+                .iter()
+                .position(|pred| pred == prev)
+                .unwrap(),
+            None => return self.err(format!("{} has phis but no predecessor", self.control.id)),
+        };
+
+        let values = phis
+            .iter()
+            .map(|phi| match phi {
+                Instruction::Phi { lhs, args } => Ok((lhs.clone(), self.eval(&args[i])?)),
+                _ => unreachable!(),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (lhs, v) in values {
+            self.bind(lhs, v)?;
+        }
+
+        Ok(())
+    }
+
+    // Jump to the given block of the current function.
+    fn goto(&mut self, bb: &BbId) {
+        self.prev = Some(self.control.id.clone());
+        self.control = self.program.functions[&self.func].body[bb].clone();
+    }
+
+    fn alloc_array(&mut self, n: u32, site: &VarId) -> Address {
+        let typ = &site.typ();
         let a = self.next_address;
         self.next_address += n.max(1); // make sure that each address is unique.
 
         let zero_initialized_value = self.zero_init(typ);
+        self.sites.insert(a, site.clone());
 
         for i in a..(a + n) {
             self.store.insert(i, zero_initialized_value.clone());
@@ -177,7 +427,17 @@ impl State {
             v
         };
 
-        if let Some(existing) = self.env.get_mut(&x).or(self.glob.get_mut(&x)) {
+        self.changed.push(x.clone());
+        if x.is_global() {
+            self.glob_assigned.insert(x.clone());
+        } else {
+            self.assigned.insert(x.clone());
+        }
+
+        if let Some(a) = self.boxed.get(&x).or(self.glob_boxed.get(&x)) {
+            self.store.insert(*a, v);
+            Ok(())
+        } else if let Some(existing) = self.env.get_mut(&x).or(self.glob.get_mut(&x)) {
             *existing = v;
             Ok(())
         } else {
@@ -190,15 +450,38 @@ impl State {
         use Value::Ptr;
 
         match inst.clone() {
+            // the first time its address is taken, a variable moves to a new
+            // heap cell, where it stays for the rest of the call.
             AddrOf { lhs, op } => {
-                todo!()
+                let a = match self.boxed.get(&op).or(self.glob_boxed.get(&op)) {
+                    Some(a) => *a,
+                    None => {
+                        let v = self.lookup(&op)?;
+                        let a = self.next_address;
+                        self.next_address += 1;
+                        self.store.insert(a, v);
+                        self.sites.insert(a, op.clone());
+
+                        // from now on, the variable may change through
+                        // pointers.
+                        if op.is_global() {
+                            self.glob_assigned.insert(op.clone());
+                            self.glob_boxed.insert(op, a);
+                        } else {
+                            self.assigned.insert(op.clone());
+                            self.boxed.insert(op, a);
+                        }
+                        a
+                    }
+                };
+                self.bind(lhs, Ptr(ToHeap(a)))?;
             }
             Alloc { lhs, num, id } => {
                 let n = match self.eval_to_int(&num)? {
                     n if n >= 0 => n as u32,
                     _ => return self.err("cannot allocate a negative number of elements".into()),
                 };
-                let a = self.alloc_array(n, &id.typ());
+                let a = self.alloc_array(n, &id);
                 self.bind(lhs, Ptr(a))?;
             }
             Arith { lhs, aop, op1, op2 } => {
@@ -292,7 +575,7 @@ impl State {
                 v => self.err(format!("expected a non-nil pointer in $store, got {v:?}"))?,
             },
             Phi { .. } => {
-                unreachable!("generated LIR code shouldn't contain $phi instructions.")
+                unreachable!("phis are executed on entry to their block.")
             }
             Store { dst, op } => {
                 let value = self.eval(&op)?;
@@ -314,7 +597,7 @@ impl State {
                     Value::Int(_) => tt,
                     _ => self.err("argument of $branch is not an int".into())?,
                 };
-                self.control = self.program.functions[&self.func].body[next_id].clone();
+                self.goto(next_id);
                 Ok(None)
             }
             Terminal::CallDirect {
@@ -333,7 +616,7 @@ impl State {
                 v => self.err(format!("tried to call non-function value {v:?}")),
             },
             Terminal::Jump(bb_id) => {
-                self.control = self.program.functions[&self.func].body[bb_id].clone();
+                self.goto(bb_id);
                 Ok(None)
             }
             Terminal::Ret(None) => {
@@ -341,7 +624,10 @@ impl State {
                     next,
                     dst,
                     env,
+                    boxed,
                     func,
+                    from,
+                    assigned,
                 } = self
                     .stack
                     .pop()
@@ -349,7 +635,10 @@ impl State {
                 assert!(dst.is_none());
                 self.control = next;
                 self.env = env;
+                self.boxed = boxed;
+                self.assigned = assigned;
                 self.func = func;
+                self.prev = Some(from);
                 Ok(None)
             }
             Terminal::Ret(Some(e)) => {
@@ -358,12 +647,18 @@ impl State {
                     next,
                     dst,
                     env,
+                    boxed,
                     func,
+                    from,
+                    assigned,
                 }) = self.stack.pop()
                 {
                     self.env = env;
+                    self.boxed = boxed;
+                    self.assigned = assigned;
                     self.func = func;
                     self.control = next;
+                    self.prev = Some(from);
                     if let Some(dst) = dst {
                         self.bind(dst, v)?;
                     }
@@ -396,6 +691,10 @@ impl State {
     }
 
     fn lookup(&self, x: &VarId) -> Result<Value, RuntimeError> {
+        if let Some(a) = self.boxed.get(x).or_else(|| self.glob_boxed.get(x)) {
+            return Ok(self.store[a].clone());
+        }
+
         self.env
             .get(x)
             .or_else(|| self.glob.get(x))
@@ -403,6 +702,27 @@ impl State {
             .ok_or_else(|| RuntimeError(format!("undefined variable {x}")))
     }
 
+    fn observe(&self, v: &Value) -> Observed {
+        match v {
+            Value::Int(n) => Observed::Int(*n),
+            Value::FnPtr(f) => Observed::Fn(f.clone()),
+            Value::Ptr(address) => match self.site(address) {
+                Some(site) => Observed::Loc(site.to_scope_string()),
+                None => Observed::Nil,
+            },
+            Value::Struct(_) => Observed::Struct,
+        }
+    }
+
+    // the allocation site or variable the address points into, if any.
+    fn site(&self, address: &Address) -> Option<&VarId> {
+        match address {
+            Address::Nil => None,
+            ToHeap(a) => self.sites.range(..=a).next_back().map(|(_, site)| site),
+            Address::Field(base, _) => self.site(base),
+        }
+    }
+
     fn zero_init(&self, typ: &Type) -> Value {
         use LirType::*;
         match &*typ.0 {
@@ -463,9 +783,14 @@ impl State {
             next: self.program.functions[&self.func].body[next_bb].clone(),
             dst: lhs.clone(),
             env: mem::replace(&mut self.env, new_env),
+            boxed: mem::take(&mut self.boxed),
             func: mem::replace(&mut self.func, callee.clone()),
+            from: self.control.id.clone(),
+            assigned: mem::replace(&mut self.assigned, params.iter().cloned().collect()),
         });
+        self.changed = params.clone();
         self.control = self.program.functions[callee].body[&bb_id("entry")].clone();
+        self.prev = None;
         Ok(None)
     }
 }
@@ -0,0 +1,215 @@
+// Interpreter tests for the parts of LIR the lowering never generates.
+
+use super::*;
+
+fn run(code: &str) -> Result<(i64, Vec<i64>), RuntimeError> {
+    let program = code.parse::<Program>().unwrap().validate().unwrap();
+    interpret_with_output(program.0)
+}
+
+// The arguments of phis are picked by the block control came from, including
+// when coming back from a call.
+#[test]
+fn phis_follow_the_predecessor() {
+    let code = r#"
+    fn id(x:int) -> int {
+      entry:
+        $ret x
+    }
+
+    fn main() -> int {
+      let a:int, b:int, c:int, r:int
+      entry:
+        c = $copy 0
+        $branch c left right
+      left:
+        $jump join
+      right:
+        r = $call_dir id(7) then join
+      join:
+        a = $phi(1, r)
+        b = $phi(2, 30)
+        r = $arith add a b
+        $ret r
+    }
+    "#;
+
+    assert_eq!(run(code).unwrap().0, 37);
+    assert_eq!(run(&code.replace("c = $copy 0", "c = $copy 1")).unwrap().0, 3);
+}
+
+#[test]
+fn phis_are_parallel() {
+    let code = r#"
+    fn main() -> int {
+      let a:int, b:int, i:int, j:int, c:int, r:int
+      entry:
+        $jump loop
+      loop:
+        a = $phi(1, b)
+        b = $phi(2, a)
+        i = $phi(0, j)
+        j = $arith add i 1
+        c = $cmp lt j 2
+        $branch c loop exit
+      exit:
+        r = $arith mul a 10
+        r = $arith add r b
+        $ret r
+    }
+    "#;
+
+    assert_eq!(run(code).unwrap().0, 21);
+}
+
+#[test]
+fn addr_of_locals_and_globals() {
+    let code = r#"
+    g:int
+
+    fn set(p:&int, v:int) -> _ {
+      entry:
+        $store p v
+        $ret
+    }
+
+    fn main() -> int {
+      let x:int, y:int, p:&int, q:&int, r:int
+      entry:
+        x = $copy 1
+        p = $addrof x
+        q = $addrof x
+        $store p 5
+        y = $load q
+        $call_dir set(q, 7) then next
+      next:
+        p = $addrof g
+        $store p 30
+        r = $arith add x y
+        r = $arith add r g
+        $ret r
+    }
+    "#;
+
+    assert_eq!(run(code).unwrap().0, 42);
+}
+
+#[test]
+fn addr_of_struct_fields() {
+    let code = r#"
+    struct pair {
+      fst:int
+      snd:int
+    }
+
+    fn main() -> int {
+      let s:pair, p:&pair, f:&int, r:int, t:int
+      entry:
+        p = $addrof s
+        f = $gfp p snd
+        $store f 3
+        f = $gfp p fst
+        $store f 4
+        p = $alloc 1 [_a]
+        f = $gfp p fst
+        $store f 100
+        p = $addrof s
+        f = $gfp p snd
+        r = $load f
+        f = $gfp p fst
+        t = $load f
+        r = $arith mul r t
+        $ret r
+    }
+    "#;
+
+    assert_eq!(run(code).unwrap().0, 12);
+}
+
+const CALL_IN_LOOP: &str = r#"
+    fn inc(x:int) -> int {
+      let y:int
+      entry:
+        y = $arith add x 1
+        $ret y
+    }
+
+    fn main() -> int {
+      let i:int, c:int
+      entry:
+        $jump loop
+      loop:
+        c = $cmp lt i 2
+        $branch c body exit
+      body:
+        i = $call_dir inc(i) then loop
+      exit:
+        $ret i
+    }
+"#;
+
+#[test]
+fn trace_shows_blocks_instructions_and_assignments() {
+    use trace::Trace;
+
+    let program = CALL_IN_LOOP.parse::<Program>().unwrap().validate().unwrap().0;
+    let mut trace = Trace::new(vec![]);
+    assert_eq!(interpret_observed(program, &mut trace).unwrap().0, 2);
+    let trace = String::from_utf8(trace.into_inner().unwrap()).unwrap();
+
+    let expected = "\
+main entry:
+  $jump loop
+main loop:
+  c = $cmp lt i 2
+    c = 1
+  $branch c body exit
+main body:
+  i = $call_dir inc(i) then loop
+    x = 0
+inc entry:
+  y = $arith add x 1
+    y = 1
+  $ret y
+    i = 1
+main loop:
+";
+    assert!(trace.starts_with(expected), "{trace}");
+    assert!(trace.ends_with("main exit:\n  $ret i\n"), "{trace}");
+}
+
+#[test]
+fn coverage_counts_blocks_and_edges() {
+    use trace::Coverage;
+
+    let program = CALL_IN_LOOP.parse::<Program>().unwrap().validate().unwrap().0;
+    let mut coverage = Coverage::new(&program);
+    interpret_observed(program, &mut coverage).unwrap();
+
+    let main = &coverage.blocks[&func_id("main")];
+    assert_eq!(main[&bb_id("loop")], 3);
+    assert_eq!(main[&bb_id("body")], 2);
+    assert_eq!(coverage.blocks[&func_id("inc")][&bb_id("entry")], 2);
+
+    // returning from a call takes the edge from the calling block.
+    let edges = &coverage.edges[&func_id("main")];
+    assert_eq!(edges[&(bb_id("body"), bb_id("loop"))], 2);
+    assert_eq!(edges[&(bb_id("loop"), bb_id("exit"))], 1);
+
+    let json = coverage.to_json();
+    assert_eq!(json["main"]["blocks"]["exit"], 1);
+    assert_eq!(
+        json["main"]["edges"][0],
+        serde_json::json!({"from": "body", "to": "loop", "count": 2})
+    );
+
+    // a block that never runs is still there.
+    let program = CALL_IN_LOOP.replace("c = $cmp lt i 2", "c = $copy 0").parse::<Program>().unwrap();
+    let mut coverage = Coverage::new(&program);
+    interpret_observed(program, &mut coverage).unwrap();
+    assert_eq!(coverage.blocks[&func_id("main")][&bb_id("body")], 0);
+    assert_eq!(
+        coverage.to_json()["main"]["edges"][2],
+        serde_json::json!({"from": "loop", "to": "body", "count": 0})
+    );
+}
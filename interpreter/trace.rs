@@ -0,0 +1,124 @@
+// Observers for debugging programs: a trace of what the interpreter does, and
+// how many times each block and edge runs.
+
+use std::io::{self, Write};
+
+use serde_json::{json, Value as Json};
+
+use super::*;
+
+// Writes every block entered, every instruction run and the variables it
+// assigns to a writer, as the program runs.  For example:
+//
+//   main entry:
+//     x = $copy 1
+//       x = 1
+//     $branch x bb1 bb2
+//   main bb1:
+//
+// The first error writing stops the trace.
+pub struct Trace<W: Write> {
+    out: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Trace<W> {
+    pub fn new(out: W) -> Self {
+        Trace { out, error: None }
+    }
+
+    // The writer, or the error that stopped the trace.
+    pub fn into_inner(self) -> io::Result<W> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.out),
+        }
+    }
+
+    fn write(&mut self, line: std::fmt::Arguments) {
+        if self.error.is_none() {
+            if let Err(e) = self.out.write_fmt(line) {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+impl<W: Write> Observer for Trace<W> {
+    fn enter(&mut self, _from: Option<&BbId>, frame: &Frame) {
+        self.write(format_args!("{} {}:\n", frame.func(), frame.block().id));
+    }
+
+    fn before(&mut self, (_, i): &(BbId, usize), frame: &Frame) {
+        let bb = frame.block();
+        match bb.insts.get(*i) {
+            // the phis run together.
+            Some(Instruction::Phi { .. }) => {
+                for phi in bb.insts.iter().take_while(|inst| matches!(inst, Instruction::Phi { .. })) {
+                    self.write(format_args!("  {phi}\n"));
+                }
+            }
+            Some(inst) => self.write(format_args!("  {inst}\n")),
+            None => self.write(format_args!("  {}\n", bb.term)),
+        }
+    }
+
+    fn after(&mut self, _func: &FuncId, _point: &(BbId, usize), frame: &Frame) {
+        for (x, v) in frame.changed() {
+            self.write(format_args!("    {x} = {v}\n"));
+        }
+    }
+}
+
+// The number of times each block of each function ran, and each edge between
+// them was taken.  Blocks and edges that never ran are counted too, as zeros.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Coverage {
+    pub blocks: Map<FuncId, Map<BbId, usize>>,
+    pub edges: Map<FuncId, Map<(BbId, BbId), usize>>,
+}
+
+impl Coverage {
+    pub fn new(program: &Program) -> Self {
+        let mut coverage = Coverage::default();
+        for (f, func) in &program.functions {
+            let blocks = coverage.blocks.entry(f.clone()).or_default();
+            let edges = coverage.edges.entry(f.clone()).or_default();
+            for (id, bb) in &func.body {
+                blocks.insert(id.clone(), 0);
+                for succ in bb.term.successors() {
+                    edges.insert((id.clone(), succ.clone()), 0);
+                }
+            }
+        }
+        coverage
+    }
+
+    // The counts as JSON, with an object for each function:
+    //
+    //   {"main": {"blocks": {"entry": 1, ...},
+    //             "edges": [{"from": "entry", "to": "bb1", "count": 1}, ...]}}
+    pub fn to_json(&self) -> Json {
+        let funcs = self.blocks.iter().map(|(f, blocks)| {
+            let blocks: serde_json::Map<String, Json> =
+                blocks.iter().map(|(bb, n)| (bb.to_string(), json!(n))).collect();
+            let edges: Vec<Json> = self.edges[f]
+                .iter()
+                .map(|((from, to), n)| json!({"from": from.to_string(), "to": to.to_string(), "count": n}))
+                .collect();
+            (f.to_string(), json!({"blocks": blocks, "edges": edges}))
+        });
+        Json::Object(funcs.collect())
+    }
+}
+
+impl Observer for Coverage {
+    fn enter(&mut self, from: Option<&BbId>, frame: &Frame) {
+        let f = frame.func();
+        let bb = &frame.block().id;
+        *self.blocks.entry(f.clone()).or_default().entry(bb.clone()).or_default() += 1;
+        if let Some(from) = from {
+            *self.edges.entry(f.clone()).or_default().entry((from.clone(), bb.clone())).or_default() += 1;
+        }
+    }
+}
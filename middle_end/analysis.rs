@@ -1,19 +1,31 @@
 //! Static analysis of lir programs.
+//!
+//! This module holds the one intraprocedural dataflow framework that every
+//! analysis in the middle end plugs into: the [Cfg], [ProgramPoint], the
+//! [AbstractValue]/[AbstractEnv] traits and the worklist solver.  Analyses that
+//! need to produce something besides per-block states (e.g. the reaching
+//! definitions solution or the generated constraints) thread it through the
+//! context parameter of [AbstractEnv].
 
 #![allow(dead_code)]
 
-use std::collections::VecDeque;
 use std::collections::{BTreeMap as Map, BTreeSet as Set};
 use std::fmt::Display;
+use std::time::{Duration, Instant};
 
 use super::lir::*;
+use loops::LoopForest;
 
 pub mod call_graph;
 pub mod constant_prop;
+pub mod copy_prop;
+pub mod dominators;
 pub mod integer_interval;
-// pub mod copy_prop;
 pub mod liveness;
+pub mod loops;
+pub mod points_to;
 pub mod reaching_defs;
+pub mod soundness;
 
 
 #[cfg(test)]
@@ -23,6 +35,78 @@ mod tests;
 /// index of the instruction in the block.
 pub type InstId = (BbId, usize);
 
+/// A program point inside a function: either an instruction or the terminal of
+/// a basic block.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ProgramPoint {
+    Instruction {
+        bb: BbId,
+        i: usize,
+    },
+    Terminal {
+        bb: BbId,
+    },
+}
+
+use std::cmp::Ordering;
+
+impl PartialOrd for ProgramPoint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// program points are ordered by basic block, then instructions in order, then
+// the terminal.
+impl Ord for ProgramPoint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        use ProgramPoint::*;
+
+        let bb_order = self.get_bb().cmp(other.get_bb());
+
+        if bb_order != Ordering::Equal {
+            return bb_order;
+        }
+
+        match (self, other) {
+            (Instruction { i: i1, .. }, Instruction { i: i2, .. }) => i1.cmp(i2),
+            (Instruction { .. }, Terminal { .. }) => Ordering::Less,
+            (Terminal { .. }, Instruction { .. }) => Ordering::Greater,
+            (Terminal { .. }, Terminal { .. }) => Ordering::Equal,
+        }
+    }
+}
+
+impl ProgramPoint {
+    pub fn from(bb: BbId, i: Option<usize>) -> Self {
+        match i {
+            Some(i) => ProgramPoint::Instruction { bb, i },
+            None => ProgramPoint::Terminal { bb },
+        }
+    }
+
+    pub fn from_instid(instid: InstId) -> Self {
+        let (bb, i) = instid;
+        ProgramPoint::Instruction { bb, i }
+    }
+
+    pub fn get_bb(&self) -> &BbId {
+        match self {
+            Self::Instruction { bb, .. } => bb,
+            Self::Terminal { bb } => bb,
+        }
+    }
+}
+
+impl Display for ProgramPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgramPoint::Instruction { bb, i } => write!(f, "{bb}.{i}"),
+            ProgramPoint::Terminal { bb } => write!(f, "{bb}.term"),
+        }
+    }
+}
+
 /// The control-flow graph *for a function* (abstracted so that we can easily
 /// get successors and predecessors and also perform forward analyses on the
 /// actual cfg or backwards analyses by reversing the edges to get a backwards
@@ -33,6 +117,9 @@ pub struct Cfg {
     pub exit: BbId,
     succ_edges: Map<BbId, Set<BbId>>,
     pred_edges: Map<BbId, Set<BbId>>,
+    pub loops: LoopForest,
+    // the blocks where widening happens: the loop headers, plus a block on
+    // every cycle of an irreducible graph.
     pub loop_headers: Set<BbId>,
     pub addr_taken: Set<VarId>,
     pub addr_taken_ints: Set<VarId>,
@@ -46,10 +133,8 @@ impl Cfg {
     pub fn new(function: &Function, globals: Set<VarId>, structs: Map<StructId, Set<FieldId>>) -> Self {
         fn insert_edge(map: &mut Map<BbId, Set<BbId>>, key_bbid: &BbId, value_bbid: &BbId) {
             map.entry(key_bbid.clone())
-                .and_modify(|s| {
-                    s.insert(value_bbid.clone());
-                })
-                .or_insert([value_bbid.clone()].into());
+                .or_default()
+                .insert(value_bbid.clone());
         }
         let entry = bb_id("entry");
         let mut exit = bb_id("exit");
@@ -58,53 +143,28 @@ impl Cfg {
 
         pred_edges.insert(entry.clone(), [].into());
 
-        for (bbid, bb) in function.body.clone() {
-            match bb.term {
+        for (bbid, bb) in &function.body {
+            match &bb.term {
                 Terminal::Branch { cond: _, tt, ff } => {
-                    insert_edge(&mut succ_edges, &bbid, &tt);
-                    insert_edge(&mut succ_edges, &bbid, &ff);
+                    insert_edge(&mut succ_edges, bbid, tt);
+                    insert_edge(&mut succ_edges, bbid, ff);
 
-                    insert_edge(&mut pred_edges, &tt, &bbid);
-                    insert_edge(&mut pred_edges, &ff, &bbid);
-                }
-                Terminal::CallDirect {
-                    lhs: _,
-                    callee: _,
-                    args: _,
-                    next_bb,
-                } => {
-                    insert_edge(&mut succ_edges, &bbid, &next_bb);
-                    insert_edge(&mut pred_edges, &next_bb, &bbid);
+                    insert_edge(&mut pred_edges, tt, bbid);
+                    insert_edge(&mut pred_edges, ff, bbid);
                 }
-                Terminal::CallIndirect {
-                    lhs: _,
-                    callee: _,
-                    args: _,
-                    next_bb,
-                } => {
-                    insert_edge(&mut succ_edges, &bbid, &next_bb);
-                    insert_edge(&mut pred_edges, &next_bb, &bbid);
-                }
-                Terminal::Jump(next_bb) => {
-                    insert_edge(&mut succ_edges, &bbid, &next_bb);
-                    insert_edge(&mut pred_edges, &next_bb, &bbid);
+                Terminal::CallDirect { next_bb, .. }
+                | Terminal::CallIndirect { next_bb, .. }
+                | Terminal::Jump(next_bb) => {
+                    insert_edge(&mut succ_edges, bbid, next_bb);
+                    insert_edge(&mut pred_edges, next_bb, bbid);
                 }
                 Terminal::Ret(_) => {
                     succ_edges.insert(bbid.clone(), [].into());
-                    exit = bbid;
+                    exit = bbid.clone();
                 }
             }
         }
 
-        /*
-        println!("{:#?}", Cfg {
-            entry: entry.clone(),
-            exit: exit.clone(),
-            succ_edges: succ_edges.clone(),
-            pred_edges: pred_edges.clone(),
-        });
-        */
-
         let mut return_cfg = Cfg {
             entry,
             exit,
@@ -112,49 +172,78 @@ impl Cfg {
             pred_edges,
             addr_taken: Set::new(),
             addr_taken_ints: Set::new(),
-            loop_headers : Set::new(),
+            loops: LoopForest::default(),
+            loop_headers: Set::new(),
             globals,
             structs: structs.clone(),
             structs_that_reach_int: Set::new(),
         };
 
-        return_cfg.loop_headers();
+        return_cfg.find_loops();
         return_cfg.get_addr_takens(&function.body);
         return_cfg.calculate_structs_that_reach_int(&structs);
 
         return_cfg
     }
 
+    // the same cfg with every edge flipped, so that the exit becomes the entry.
+    // running a forward analysis on this performs a backward analysis.
+    pub fn reversed(&self) -> Self {
+        let mut new_cfg = self.clone();
+        new_cfg.pred_edges = self.succ_edges.clone();
+        new_cfg.succ_edges = self.pred_edges.clone();
+        new_cfg.entry = self.exit.clone();
+        new_cfg.exit = self.entry.clone();
+        new_cfg
+    }
+
     // an iterator over the successor edges of bb.
     pub fn succ(&self, bb: &BbId) -> impl Iterator<Item = &BbId> {
-        self.succ_edges[bb].iter()
+        self.succ_edges.get(bb).into_iter().flatten()
     }
 
     // an iterator over the predecessor edges of bb.
     pub fn pred(&self, bb: &BbId) -> impl Iterator<Item = &BbId> {
-        self.pred_edges[bb].iter()
+        self.pred_edges.get(bb).into_iter().flatten()
+    }
+
+    // the blocks reachable from the entry in reverse postorder: every block
+    // comes before its successors, except along back edges.
+    pub fn reverse_postorder(&self) -> Vec<BbId> {
+        let mut postorder = vec![];
+        let mut visited = Set::from([&self.entry]);
+        let mut stack = vec![(&self.entry, self.succ(&self.entry))];
+
+        while let Some((bb, succs)) = stack.last_mut() {
+            match succs.find(|succ| !visited.contains(succ)) {
+                Some(succ) => {
+                    visited.insert(succ);
+                    stack.push((succ, self.succ(succ)));
+                }
+                None => {
+                    postorder.push((*bb).clone());
+                    stack.pop();
+                }
+            }
+        }
+
+        postorder.reverse();
+        postorder
     }
 
     // get all addr_taken variables analysis
     fn get_addr_takens(&mut self, body: &Map<BbId, BasicBlock>) {
-
         for bb in body.values() {
             for inst in &bb.insts {
-                match inst {
-                    Instruction::AddrOf { lhs: _, op } => {
-                        let op = op.clone();
-                        self.addr_taken.insert(op.clone());
-
-                        if op.typ().is_int() {
-                            self.addr_taken_ints.insert(op);
-                        }
-                    },
-                    Instruction::CallExt { lhs: _, ext_callee: _, args: _ } => (),
-                    _ => (),
+                if let Instruction::AddrOf { lhs: _, op } = inst {
+                    self.addr_taken.insert(op.clone());
+
+                    if op.typ().is_int() {
+                        self.addr_taken_ints.insert(op.clone());
+                    }
                 }
             }
         }
-
     }
 
     fn calculate_structs_that_reach_int(&mut self, structs: &Map<StructId, Set<FieldId>>) {
@@ -169,7 +258,9 @@ impl Cfg {
         }
     }
 
-    fn reachable_types(&self, typ: &Type) -> Set<Type> {
+    // all types reachable from a value of type `typ` by dereferencing pointers
+    // and projecting struct fields (not including `typ` itself).
+    pub fn reachable_types(&self, typ: &Type) -> Set<Type> {
         use LirType::*;
 
         fn reachable_inner(typ_stack: &mut Vec<Type>, reachables: &mut Set<Type>, structs: &Map<StructId, Set<FieldId>>) {
@@ -177,14 +268,14 @@ impl Cfg {
                 if reachables.contains(&t) {
                     continue;
                 }
-                
+
                 match &*(t.clone()).0 {
                     Int => {
                         reachables.insert(t.clone());
                     },
                     Struct(struct_id) => {
                         reachables.insert(t.clone());
-    
+
                         let mut field_types: Vec<Type> = structs[struct_id].iter().map(|a| a.typ.clone()).collect();
                         typ_stack.append(&mut field_types);
                     },
@@ -192,8 +283,7 @@ impl Cfg {
                         reachables.insert(t.clone());
                         typ_stack.push(inner_typ.clone());
                     },
-                    Function{ret_ty, param_ty} => (),
-    
+                    Function { .. } => (),
                 }
             }
         }
@@ -221,34 +311,22 @@ impl Cfg {
         self.reachable_types(&var_type).contains(&int_ty())
     }
 
-    
 
-    // returns all loop headers for widening
-    fn loop_headers(&mut self) {
 
-        let curr_block: &BbId = &self.entry;
-        let mut visited: Set<&BbId> = Set::new();
-        visited.insert(curr_block);
-        let mut headers: Set<BbId> = Set::new();
-        
-        self.recursive_search(curr_block, visited, &mut headers);
+    // compute the loop forest and the widening points.  Any cycle has an edge
+    // going backwards in reverse postorder, so the targets of those edges
+    // cover the cycles that aren't natural loops too.
+    fn find_loops(&mut self) {
+        self.loops = LoopForest::new(self);
 
-        self.loop_headers = headers;
-    }
-
-    fn recursive_search(&self, curr_block: &BbId, visited: Set<&BbId>, headers: &mut Set<BbId>) {
-        let succ_blocks = self.succ(curr_block);
-        for succ in succ_blocks {
-            if visited.contains(succ) {
-                headers.insert(succ.clone());
-            } else if *succ == self.exit {
+        let rpo = self.reverse_postorder();
+        let index: Map<&BbId, usize> = rpo.iter().enumerate().map(|(i, bb)| (bb, i)).collect();
 
-            } else {
-                let mut new_visited = visited.clone();
-                new_visited.insert(succ);
-                self.recursive_search(succ, new_visited, headers);
-            }
-        }
+        self.loop_headers = rpo
+            .iter()
+            .flat_map(|bb| self.succ(bb).filter(|succ| index[succ] <= index[bb]))
+            .cloned()
+            .collect();
     }
 }
 
@@ -274,21 +352,28 @@ pub trait AbstractValue: Clone + Display + Eq + PartialEq {
 /// The abstract environment (the abstract state) used for any dfa.  It needs to
 /// know how to combine with other stores and how to modify itself when
 /// processing an instruction or terminal.
-pub trait AbstractEnv: Clone {
+///
+/// `C` is the analysis context: anything the transfer functions need to read
+/// besides the cfg (e.g. a points-to solution) or write as a side output that
+/// isn't part of the abstract state (e.g. the reaching definitions solution or
+/// generated constraints).  Analyses that need neither use `()`.
+pub trait AbstractEnv<C = ()>: Clone {
     // compute self = self ⊔ rhs
     //
     // `block` is the basic block self belongs to.
     //
     // Return whether the block has changed as the result of this operation.
     fn join_with(&mut self, rhs: &Self, block: &BbId, join_type: i64) -> bool;
-    
+
     // Transfer function for instructions.  Emulates what an instruction would
     // do.  Note that this function changes the current state!
-    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg);
+    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg, ctx: &mut C);
 
     // Transfer function for terminals.  Emulates what a terminal would do.
     // Note that this function changes the current state!
-    fn analyze_term(&mut self, inst: &Terminal, cfg: &Cfg) -> Set<BbId>;
+    //
+    // Returns the successors that are infeasible from this state.
+    fn analyze_term(&mut self, inst: &Terminal, cfg: &Cfg, ctx: &mut C) -> Set<BbId>;
 
     // Transfer function for basic blocks.
     //
@@ -299,55 +384,74 @@ pub trait AbstractEnv: Clone {
     // If this environment is part of a backward analysis, `self` is the post
     // state for the basic block, and this function should return all the pre
     // states for all instructions and the terminal in the block.
-    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg) -> (Vec<Self>, Set<BbId>);
+    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg, ctx: &mut C) -> (Vec<Self>, Set<BbId>);
 }
 
 /// An abstract environment built as a pointwise extension of the abstract
-/// domain `A`.  It is a map from variables to abstract values.
+/// domain `A`.  It is a map from keys (variables, unless stated otherwise) to
+/// abstract values.
 ///
 /// To use this in an analysis, we need to provide the abstract domain `A` for
-/// each variable.
+/// each key.
 #[derive(Clone, Debug)]
-pub struct PointwiseEnv<A: AbstractValue> {
-    pub values: Map<VarId, A>,
+pub struct PointwiseEnv<A: AbstractValue, K: Clone + Ord = VarId> {
+    pub values: Map<K, A>,
     pub curr_inst: Option<InstId>,
 }
 
-impl<A: AbstractValue> PointwiseEnv<A> {
-    fn new(values: Map<VarId, A>) -> Self {
+impl<A: AbstractValue, K: Clone + Ord> PointwiseEnv<A, K> {
+    pub fn new(values: Map<K, A>) -> Self {
         Self {
             values,
             curr_inst: None,
         }
     }
 
-    // get the value of a variable, or bottom if it isn't present.
-    pub fn get(&self, key: &VarId) -> A {
+    // get the value of a key, or bottom if it isn't present.
+    pub fn get(&self, key: &K) -> A {
         self.values.get(key).unwrap_or(&A::BOTTOM).clone()
     }
 
-    // insert a value for a variable.
-    fn insert(&mut self, key: &VarId, val: &A) {
+    // insert a value for a key.
+    pub fn insert(&mut self, key: &K, val: &A) {
         self.values.insert(key.clone(), val.clone());
     }
 
-    // get a mutable reference to the value of a variable, which will be inserted
+    // get a mutable reference to the value of a key, which will be inserted
     // with value bottom if not already present.
-    fn get_mut(&mut self, key: &VarId) -> &mut A {
+    pub fn get_mut(&mut self, key: &K) -> &mut A {
         self.values.entry(key.clone()).or_insert(A::BOTTOM)
     }
 
-    fn get_env(self) -> Map<VarId, A> {
+    pub fn get_env(self) -> Map<K, A> {
         self.values
     }
+
+    // compute self = self ⊔ rhs key by key, treating missing keys as bottom.
+    //
+    // Return whether any value has changed.
+    pub fn pointwise_join(&mut self, rhs: &Self) -> bool {
+        let mut changed = false;
+
+        for (x, rhs) in &rhs.values {
+            let lhs = self.get_mut(x);
+            let joined = lhs.join(rhs);
+
+            if joined != *lhs {
+                *lhs = joined;
+                changed = true;
+            }
+        }
+
+        changed
+    }
 }
-/*
-impl<A: AbstractValue> Display for PointwiseEnv<A> {
+
+impl<A: AbstractValue, K: Clone + Ord + Display> Display for PointwiseEnv<A, K> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         let str = self
             .values
             .iter()
-            .filter(|(x, _)| x.scope().is_some())
             .fold("".to_string(), |acc, (var, val)| {
                 if *val == A::BOTTOM {
                     acc
@@ -357,26 +461,126 @@ impl<A: AbstractValue> Display for PointwiseEnv<A> {
             });
         write!(f, "{str}")
     }
-}*/
+}
 
-impl<A: AbstractValue> Display for PointwiseEnv<A> {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        let str = self
-            .values
-            .iter()
-            .fold("".to_string(), |acc, (var, val)| {
-                if *val == A::BOTTOM {
-                    acc
-                } else {
-                    format!("{acc}{var} -> {val}\n")
-                }
-            });
-        write!(f, "{str}")
+/// The facts that hold on every path to a program point, for must analyses.
+///
+/// The join is set intersection, so the lattice is flipped: the state every
+/// block starts from (the solver's `bottom_state`) is [MustSet::Top], the set
+/// of all facts, which is the identity of intersection.  A block keeps it until
+/// some path reaches it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MustSet<T: Ord> {
+    Top,
+    Facts(Set<T>),
+}
+
+impl<T: Clone + Ord> MustSet<T> {
+    pub fn empty() -> Self {
+        MustSet::Facts(Set::new())
+    }
+
+    pub fn contains(&self, fact: &T) -> bool {
+        match self {
+            MustSet::Top => true,
+            MustSet::Facts(facts) => facts.contains(fact),
+        }
+    }
+
+    // the facts, if there are finitely many.
+    pub fn facts(&self) -> Option<&Set<T>> {
+        match self {
+            MustSet::Top => None,
+            MustSet::Facts(facts) => Some(facts),
+        }
+    }
+
+    pub fn insert(&mut self, fact: T) {
+        if let MustSet::Facts(facts) = self {
+            facts.insert(fact);
+        }
+    }
+
+    // drop the facts not satisfying `keep`.  The top state has to be left
+    // alone: the facts it holds cannot be enumerated.
+    pub fn retain(&mut self, keep: impl FnMut(&T) -> bool) {
+        if let MustSet::Facts(facts) = self {
+            facts.retain(keep);
+        }
+    }
+
+    // compute self = self ∩ rhs.
+    //
+    // Return whether self has changed.
+    pub fn intersect_with(&mut self, rhs: &Self) -> bool {
+        match (&mut *self, rhs) {
+            (_, MustSet::Top) => false,
+            (MustSet::Top, _) => {
+                *self = rhs.clone();
+                true
+            }
+            (MustSet::Facts(lhs), MustSet::Facts(rhs)) => {
+                let old = lhs.len();
+                lhs.retain(|fact| rhs.contains(fact));
+                old != lhs.len()
+            }
+        }
     }
 }
 
 // SECTION: intraprocedural dataflow analysis framework
 
+/// Counters collected while solving an analysis, to compare how quickly
+/// different domains converge.
+#[derive(Clone, Debug, Default)]
+pub struct AnalysisStats {
+    /// Number of times a basic block was analyzed.
+    pub block_visits: usize,
+    /// Number of states joined into a successor.
+    pub joins: usize,
+    /// Number of those joins at loop headers, where the domain may widen.
+    pub widenings: usize,
+    /// Time spent in the solver.
+    pub time: Duration,
+}
+
+impl Display for AnalysisStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "block visits: {}, joins: {}, widenings: {}, time: {:?}",
+            self.block_visits, self.joins, self.widenings, self.time
+        )
+    }
+}
+
+// The blocks waiting to be analyzed, each at most once, handed out by their
+// priority.
+struct Worklist {
+    priority: Map<BbId, usize>,
+    queue: Set<(usize, BbId)>,
+}
+
+impl Worklist {
+    // a worklist prioritizing blocks in the given order.  Blocks missing from
+    // `order` come last.
+    fn new(order: Vec<BbId>) -> Self {
+        Self {
+            priority: order.into_iter().enumerate().map(|(i, bb)| (bb, i)).collect(),
+            queue: Set::new(),
+        }
+    }
+
+    fn push(&mut self, bb: &BbId) {
+        let priority = self.priority.get(bb).copied().unwrap_or(usize::MAX);
+        self.queue.insert((priority, bb.clone()));
+    }
+
+    fn pop(&mut self) -> Option<BbId> {
+        self.queue.pop_first().map(|(_, bb)| bb)
+    }
+}
+
 /// Analyze the given function.  Assumes that the function is from a valid
 /// program.
 ///
@@ -384,48 +588,75 @@ impl<A: AbstractValue> Display for PointwiseEnv<A> {
 /// returns:
 ///
 /// (1) the pre state for each basic block
-/// (2) the pre state for each instruction
+/// (2) the state after each instruction (and the terminal, at index
+///     `insts.len()`) as returned by [AbstractEnv::analyze_bb]
 ///
 /// bottom_state is the bottom value for the abstract state `A`.  You should use
-/// it as the starting state for the analysis.
-///
-/// Hint: You can compute (1) first, then process each block only once to compute
-/// (2).
+/// it as the starting state for the analysis.  For must analyses, whose join is
+/// an intersection, this is the top of the usual order (see [MustSet]).
 pub fn forward_analysis<A: AbstractEnv>(
     f: &Function,
     cfg: &Cfg,
     entry_state: &A,
     bottom_state: &A,
 ) -> (Map<BbId, A>, Map<InstId, A>) {
-    // 1. Create an initial solution that maps entry block → entry state.
-    // 2. Create a worklist containing entry.
-    // 3. Implement the worklist algorithm.
-    // 4. Compute per-instruction pre states.
-    let mut bb_pre_states = Map::new();
-    let mut inst_pre_states = Map::new();
-    let mut worklist = VecDeque::new();
+    forward_analysis_with(f, cfg, entry_state, bottom_state, &mut ())
+}
+
+/// [forward_analysis] for analyses with a context.  `ctx` is handed to every
+/// transfer function, so it holds whatever the analysis accumulated once the
+/// fixpoint is reached.
+pub fn forward_analysis_with<C, A: AbstractEnv<C>>(
+    f: &Function,
+    cfg: &Cfg,
+    entry_state: &A,
+    bottom_state: &A,
+    ctx: &mut C,
+) -> (Map<BbId, A>, Map<InstId, A>) {
+    let (bb_pre_states, inst_post_states, _) =
+        forward_analysis_with_stats(f, cfg, entry_state, bottom_state, ctx);
+    (bb_pre_states, inst_post_states)
+}
+
+/// [forward_analysis_with] that also reports how much work the solver did.
+///
+/// Blocks are analyzed in reverse postorder, so a block is usually analyzed
+/// only after all its forward predecessors are.  A block is analyzed once it
+/// is reached from a feasible edge, and again whenever its pre state changes.
+pub fn forward_analysis_with_stats<C, A: AbstractEnv<C>>(
+    f: &Function,
+    cfg: &Cfg,
+    entry_state: &A,
+    bottom_state: &A,
+    ctx: &mut C,
+) -> (Map<BbId, A>, Map<InstId, A>, AnalysisStats) {
+    let start = Instant::now();
+    let mut stats = AnalysisStats::default();
 
-    let mut visited = Set::new();
+    let mut bb_pre_states = Map::new();
+    let mut inst_post_states = Map::new();
+    let mut worklist = Worklist::new(cfg.reverse_postorder());
+    let mut reached = Set::from([cfg.entry.clone()]);
 
     // Initialize
     for bbid in f.body.keys() {
         bb_pre_states.insert(bbid.clone(), bottom_state.clone());
-        // if !visited.insert(bbid) {unreachable!("double bbid in forward_analysis visited")};
-        // inst_pre_states.insert((bbid.clone(), 0), bottom_state.clone());
     }
 
     bb_pre_states.insert(cfg.entry.clone(), entry_state.clone());
-    worklist.push_back(cfg.entry.clone());
+    worklist.push(&cfg.entry);
+
     // Worklist algorithm
-    while let Some(bb_id) = worklist.pop_front() {
-        let state = bb_pre_states.get(&bb_id).unwrap_or(bottom_state).clone();
-        let bb = f.body[&bb_id].clone();
-        let (post_states, skip_state) = state.analyze_bb(&bb, cfg);
-        // unncessary here
+    while let Some(bb_id) = worklist.pop() {
+        stats.block_visits += 1;
+
+        let state = bb_pre_states[&bb_id].clone();
+        let (post_states, skip_state) = state.analyze_bb(&f.body[&bb_id], cfg, ctx);
+
         for (i, post_state) in post_states.iter().enumerate() {
-            inst_pre_states.insert((bb_id.clone(), i), post_state.clone());
+            inst_post_states.insert((bb_id.clone(), i), post_state.clone());
         }
-        
+
         for succ in cfg.succ(&bb_id) {
             if skip_state.contains(succ) {
                 continue;
@@ -433,40 +664,84 @@ pub fn forward_analysis<A: AbstractEnv>(
             let succ_state = bb_pre_states.get_mut(succ).unwrap();
 
             let join_type: i64 = if cfg.loop_headers.contains(succ) { 1 } else { 0 };
+            stats.joins += 1;
+            stats.widenings += join_type as usize;
 
-            if succ_state.join_with(post_states.last().unwrap(), succ, join_type) || !visited.contains(succ) {
-                visited.insert(succ);
-                worklist.push_back(succ.clone());
+            if succ_state.join_with(post_states.last().unwrap(), succ, join_type) | reached.insert(succ.clone()) {
+                worklist.push(succ);
             }
         }
     }
-    (bb_pre_states, inst_pre_states)
+
+    stats.time = start.elapsed();
+    (bb_pre_states, inst_post_states, stats)
 }
 
 /// Analyze the given function.  Assumes that the function is from a valid
 /// program.
 ///
-/// This function starts from the exit block in the CFG (which may have a name
-/// other than "exit"), end performs a backward analysis.  It returns:
+/// This function starts from the exit blocks in the CFG (every block ending in
+/// `$ret`, the last of which by name is [Cfg::exit]), and performs a backward
+/// analysis.  It returns:
 ///
 /// (1) the post state for each basic block
-/// (2) the post state for each instruction
+/// (2) the state before each instruction (and the terminal, at index
+///     `insts.len()`) as returned by [AbstractEnv::analyze_bb]
 ///
-/// bottom_state is the bottom value for the abstract state `A`.  You should use
-/// it as the starting state for the analysis.
-///
-/// Hint: You can compute (1) first, then process each block only once to compute
-/// (2).
+/// Blocks that cannot reach an exit (e.g. infinite loops) start from
+/// bottom_state, as does every block before the analysis reaches it.
 pub fn backward_analysis<A: AbstractEnv>(
-    _f: &Function,
-    _cfg: &Cfg,
-    _exit_state: &A,
-    _bottom_state: &A,
+    f: &Function,
+    cfg: &Cfg,
+    exit_state: &A,
+    bottom_state: &A,
 ) -> (Map<BbId, A>, Map<InstId, A>) {
-    // 1. Create an initial solution that maps exit block → exit state.
-    // 2. Create a worklist containing exit.
-    // 3. Implement the worklist algorithm.
-    // 4. Compute per-instruction pre states.
+    backward_analysis_with(f, cfg, exit_state, bottom_state, &mut ())
+}
+
+/// [backward_analysis] for analyses with a context.
+pub fn backward_analysis_with<C, A: AbstractEnv<C>>(
+    f: &Function,
+    cfg: &Cfg,
+    exit_state: &A,
+    bottom_state: &A,
+    ctx: &mut C,
+) -> (Map<BbId, A>, Map<InstId, A>) {
+    let mut bb_post_states = Map::new();
+    let mut inst_pre_states = Map::new();
+
+    // postorder visits a block after its successors (except along back
+    // edges), which is what a backward analysis wants.
+    let mut postorder = cfg.reverse_postorder();
+    postorder.reverse();
+    let mut worklist = Worklist::new(postorder);
+
+    // every block is analyzed at least once, so that blocks that never reach
+    // an exit still get per-instruction states.
+    for bbid in f.body.keys() {
+        let state = if cfg.succ(bbid).next().is_none() { exit_state } else { bottom_state };
+        bb_post_states.insert(bbid.clone(), state.clone());
+        worklist.push(bbid);
+    }
+
+    while let Some(bb_id) = worklist.pop() {
+        let state = bb_post_states[&bb_id].clone();
+        let (pre_states, _) = state.analyze_bb(&f.body[&bb_id], cfg, ctx);
+
+        for (i, pre_state) in pre_states.iter().enumerate() {
+            inst_pre_states.insert((bb_id.clone(), i), pre_state.clone());
+        }
+
+        for pred in cfg.pred(&bb_id) {
+            let pred_state = bb_post_states.get_mut(pred).unwrap();
+
+            let join_type: i64 = if cfg.loop_headers.contains(&bb_id) { 1 } else { 0 };
+
+            if pred_state.join_with(&pre_states[0], pred, join_type) {
+                worklist.push(pred);
+            }
+        }
+    }
 
-    todo!()
+    (bb_post_states, inst_pre_states)
 }
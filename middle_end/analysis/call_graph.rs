@@ -1,6 +1,302 @@
-//! Call graph analysis without resolving indirect calls.
+//! Call graph analysis.
 //!
-//! If we resolve indirect calls, we would get more opportunities for
-//! optimization.
+//! The graph has an edge from each function to the functions each of its call
+//! sites may call.  A direct call (`$call_dir`) calls its callee.  An indirect
+//! call (`$call_idr`) through a function name calls that function, and one
+//! through a pointer calls any function whose address is taken and whose type
+//! is the type of the pointer, or, given a points-to solution, the functions
+//! the pointer points to.  External calls (`$call_ext`) are kept apart, since
+//! their callees have no body.
 //!
-//! You need to implement a simple call graph analysis here to use in inlining.
+//! The strongly connected components of the graph group mutually recursive
+//! functions, and [CallGraph::bottom_up] lists them in reverse topological
+//! order, callees first.  This is the order for transitive inlining: a function
+//! is done before any call to it is inlined.
+
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+
+use crate::middle_end::analysis::InstId;
+use crate::middle_end::lir::*;
+
+/// A call to internal functions, ending a basic block.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CallSite {
+    pub indirect: bool,
+    /// The functions the call may reach.
+    pub callees: Set<FuncId>,
+}
+
+pub struct CallGraph {
+    // the call sites of each function, keyed by the block the call ends.
+    calls: Map<FuncId, Map<BbId, CallSite>>,
+    // the external functions each function calls, keyed by instruction.
+    external: Map<FuncId, Map<InstId, FuncId>>,
+    // the strongly connected components, callees first.
+    sccs: Vec<Set<FuncId>>,
+}
+
+impl CallGraph {
+    /// The call graph resolving indirect calls by type.
+    pub fn new(program: &Program) -> CallGraph {
+        let addr_taken = addr_taken_functions(program);
+
+        Self::build(program, |callee| {
+            addr_taken
+                .iter()
+                .filter(|f| program.globals.iter().any(|g| g.name() == f.name() && g.typ() == callee.typ()))
+                .cloned()
+                .collect()
+        })
+    }
+
+    /// The call graph resolving indirect calls with a points-to solution, keyed
+    /// by [VarId::to_scope_string].  A pointer the solution has nothing for
+    /// calls nothing.
+    pub fn with_points_to(program: &Program, pts_to: &Map<String, Set<String>>) -> CallGraph {
+        Self::build(program, |callee| {
+            pts_to
+                .get(&callee.to_scope_string())
+                .into_iter()
+                .flatten()
+                .map(|name| func_id(name))
+                .filter(|f| program.functions.contains_key(f))
+                .collect()
+        })
+    }
+
+    fn build(program: &Program, resolve: impl Fn(&VarId) -> Set<FuncId>) -> CallGraph {
+        let mut calls = Map::new();
+        let mut external = Map::new();
+
+        for f in program.functions.values() {
+            let mut sites = Map::new();
+            let mut ext_sites = Map::new();
+
+            for bb in f.body.values() {
+                for (i, inst) in bb.insts.iter().enumerate() {
+                    if let Instruction::CallExt { ext_callee, .. } = inst {
+                        ext_sites.insert((bb.id.clone(), i), ext_callee.clone());
+                    }
+                }
+
+                let site = match &bb.term {
+                    Terminal::CallDirect { callee, .. } => CallSite {
+                        indirect: false,
+                        callees: Set::from([callee.clone()]),
+                    },
+                    // a function name is not a pointer that could change.
+                    Terminal::CallIndirect { callee, .. }
+                        if callee.is_global() && program.functions.contains_key(&func_id(callee.name())) =>
+                    {
+                        CallSite {
+                            indirect: true,
+                            callees: Set::from([func_id(callee.name())]),
+                        }
+                    }
+                    Terminal::CallIndirect { callee, .. } => CallSite {
+                        indirect: true,
+                        callees: resolve(callee),
+                    },
+                    _ => continue,
+                };
+                sites.insert(bb.id.clone(), site);
+            }
+
+            calls.insert(f.id.clone(), sites);
+            external.insert(f.id.clone(), ext_sites);
+        }
+
+        let sccs = Tarjan::run(&calls);
+        CallGraph { calls, external, sccs }
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = &FuncId> {
+        self.calls.keys()
+    }
+
+    /// The call sites of `f` calling internal functions.
+    pub fn call_sites(&self, f: &FuncId) -> &Map<BbId, CallSite> {
+        &self.calls[f]
+    }
+
+    /// The `$call_ext` instructions of `f`, with the functions they call.
+    pub fn external_call_sites(&self, f: &FuncId) -> &Map<InstId, FuncId> {
+        &self.external[f]
+    }
+
+    pub fn callees(&self, f: &FuncId) -> Set<&FuncId> {
+        self.calls[f].values().flat_map(|site| &site.callees).collect()
+    }
+
+    pub fn external_callees(&self, f: &FuncId) -> Set<&FuncId> {
+        self.external[f].values().collect()
+    }
+
+    pub fn callers(&self, f: &FuncId) -> Set<&FuncId> {
+        self.calls
+            .iter()
+            .filter(|(_, sites)| sites.values().any(|site| site.callees.contains(f)))
+            .map(|(caller, _)| caller)
+            .collect()
+    }
+
+    /// Whether `f` makes no internal calls.  A function with an indirect call
+    /// that may reach nothing is not a leaf either.
+    pub fn is_leaf(&self, f: &FuncId) -> bool {
+        self.calls[f].is_empty()
+    }
+
+    /// The strongly connected components, in reverse topological order: every
+    /// component comes after the ones it calls into.
+    pub fn bottom_up(&self) -> &[Set<FuncId>] {
+        &self.sccs
+    }
+
+    /// The strongly connected component containing `f`.
+    pub fn scc(&self, f: &FuncId) -> &Set<FuncId> {
+        self.sccs.iter().find(|scc| scc.contains(f)).unwrap()
+    }
+
+    /// Whether `f` can call itself, directly or through other functions.
+    pub fn is_recursive(&self, f: &FuncId) -> bool {
+        self.scc(f).len() > 1 || self.callees(f).contains(f)
+    }
+
+    /// The functions that can call themselves.
+    pub fn recursive_functions(&self) -> Set<&FuncId> {
+        self.functions().filter(|f| self.is_recursive(f)).collect()
+    }
+
+    /// A graphviz rendering of the graph.  Indirect calls are dashed, and
+    /// external functions are ellipses.
+    pub fn to_dot(&self) -> String {
+        let mut node_str = String::new();
+        let mut edge_str = String::new();
+
+        for (f, sites) in &self.calls {
+            node_str.push_str(&format!("\n\"{f}\" [shape=box];"));
+
+            let mut edges: Map<&FuncId, bool> = Map::new();
+            for site in sites.values() {
+                for g in &site.callees {
+                    // a direct call to the same function is drawn solid.
+                    *edges.entry(g).or_insert(true) &= site.indirect;
+                }
+            }
+            for (g, indirect) in edges {
+                let style = if indirect { "color=black style=dashed" } else { "color=black" };
+                edge_str.push_str(&format!("\n\"{f}\" -> \"{g}\" [{style}];"));
+            }
+            for g in self.external_callees(f) {
+                edge_str.push_str(&format!("\n\"{f}\" -> \"{g}\" [color=gray];"));
+            }
+        }
+
+        let externals: Set<&FuncId> = self.external.values().flat_map(|sites| sites.values()).collect();
+        for g in externals {
+            node_str.push_str(&format!("\n\"{g}\" [shape=ellipse];"));
+        }
+
+        format!(
+            r#"digraph callgraph {{
+{node_str}
+{edge_str}
+}}
+"#
+        )
+    }
+}
+
+// The functions whose name is used as a value somewhere in the program, so a
+// pointer may hold their address.
+fn addr_taken_functions(program: &Program) -> Set<FuncId> {
+    let mut names: Set<&VarId> = Set::new();
+    for f in program.functions.values() {
+        for bb in f.body.values() {
+            for inst in &bb.insts {
+                names.extend(inst.uses());
+                if let Instruction::AddrOf { op, .. } = inst {
+                    names.insert(op);
+                }
+            }
+            names.extend(bb.term.uses());
+        }
+    }
+
+    names
+        .into_iter()
+        .filter(|x| x.is_global())
+        .map(|x| func_id(x.name()))
+        .filter(|f| program.functions.contains_key(f))
+        .collect()
+}
+
+// Tarjan's algorithm.  A component is complete once its root is finished,
+// which happens after all the components it reaches, so they come out in
+// reverse topological order.
+struct Tarjan<'a> {
+    calls: &'a Map<FuncId, Map<BbId, CallSite>>,
+    index: Map<&'a FuncId, usize>,
+    low: Map<&'a FuncId, usize>,
+    stack: Vec<&'a FuncId>,
+    on_stack: Set<&'a FuncId>,
+    sccs: Vec<Set<FuncId>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn run(calls: &'a Map<FuncId, Map<BbId, CallSite>>) -> Vec<Set<FuncId>> {
+        let mut tarjan = Tarjan {
+            calls,
+            index: Map::new(),
+            low: Map::new(),
+            stack: vec![],
+            on_stack: Set::new(),
+            sccs: vec![],
+        };
+
+        for f in calls.keys() {
+            if !tarjan.index.contains_key(f) {
+                tarjan.visit(f);
+            }
+        }
+
+        tarjan.sccs
+    }
+
+    fn visit(&mut self, f: &'a FuncId) {
+        let index = self.index.len();
+        self.index.insert(f, index);
+        self.low.insert(f, index);
+        self.stack.push(f);
+        self.on_stack.insert(f);
+
+        // calls to functions the program does not define lead nowhere.
+        let calls = self.calls;
+        let callees: Set<&'a FuncId> = calls[f]
+            .values()
+            .flat_map(|site| &site.callees)
+            .filter(|g| calls.contains_key(*g))
+            .collect();
+        for g in callees {
+            if !self.index.contains_key(g) {
+                self.visit(g);
+                self.low.insert(f, self.low[f].min(self.low[g]));
+            } else if self.on_stack.contains(g) {
+                self.low.insert(f, self.low[f].min(self.index[g]));
+            }
+        }
+
+        if self.low[f] == self.index[f] {
+            let mut scc = Set::new();
+            loop {
+                let g = self.stack.pop().unwrap();
+                self.on_stack.remove(g);
+                scc.insert(g.clone());
+                if g == f {
+                    break;
+                }
+            }
+            self.sccs.push(scc);
+        }
+    }
+}
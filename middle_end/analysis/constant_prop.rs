@@ -17,6 +17,17 @@ pub enum Value {
     Bot,
 }
 
+impl Value {
+    // whether the concrete value `n` is one of the values this represents.
+    pub fn contains(&self, n: i64) -> bool {
+        match self {
+            Value::Top => true,
+            Value::Int(k) => *k == n,
+            Value::Bot => false,
+        }
+    }
+}
+
 // Abstract environment
 pub type Env = PointwiseEnv<Value>;
 
@@ -30,6 +41,29 @@ pub fn analyze(program: &Valid<Program>, func: FuncId) -> (Map<BbId, Env>, Map<I
     forward_analysis(f, &Cfg::new(f, program.globals.clone(), program.structs.clone()), &init_store, &bottom_store)
 }
 
+/// [analyze], but from the state the interpreter starts `func` in rather than
+/// with the locals at ⊥: the locals are zero-initialized, and so are the
+/// globals in `main`, while elsewhere they may hold anything.
+pub fn analyze_zero_init(program: &Valid<Program>, func: FuncId) -> (Map<BbId, Env>, Map<InstId, Env>) {
+    let program = &program.0;
+    let f = &program.functions[&func];
+    let mut init_store = create_init_store_no_ptrs(f, program);
+    let bottom_store = create_bottom_store_no_ptrs(f, program);
+
+    for local in &f.locals {
+        if let Some(value) = init_store.values.get_mut(local) {
+            *value = Value::Int(0);
+        }
+    }
+    for global in &program.globals {
+        if let Some(value) = init_store.values.get_mut(global) {
+            *value = if func == func_id("main") { Value::Int(0) } else { Value::Top };
+        }
+    }
+
+    forward_analysis(f, &Cfg::new(f, program.globals.clone(), program.structs.clone()), &init_store, &bottom_store)
+}
+
 // SECTION: helpers
 
 fn create_init_store(f: &Function, program: &Program) -> Env {
@@ -224,7 +258,7 @@ impl AbstractEnv for Env {
         changed
     }
 
-    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg) {
+    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg, _ctx: &mut ()) {
         use Instruction::*;
 
         fn arith(aop: ArithmeticOp, v1: Value, v2: Value) -> Value {
@@ -347,8 +381,8 @@ impl AbstractEnv for Env {
         }
 
         match inst.clone() {
-            AddrOf { lhs, op } => if lhs.typ().is_int() { self.insert(&lhs, &V::Top) },
-            Alloc { lhs, num: _, id: _ } => if lhs.typ().is_int() { self.insert(&lhs, &V::Top) },
+            AddrOf { lhs, op: _ } if lhs.typ().is_int() => self.insert(&lhs, &V::Top),
+            Alloc { lhs, num: _, id: _ } if lhs.typ().is_int() => self.insert(&lhs, &V::Top),
             Arith { lhs, aop, op1, op2 } => {
                 let v1 = self.value_from_op(op1);
                 let v2 = self.value_from_op(op2);
@@ -376,32 +410,25 @@ impl AbstractEnv for Env {
 
                 if lhs.typ().is_int() { self.insert(&lhs, &lhs_val) };
             }
-            Copy { lhs, op } => {
-                if lhs.typ().is_int() { 
-                    self.insert(&lhs, &self.value_from_op(op))
-                };
-            }
+            Copy { lhs, op } if lhs.typ().is_int() => self.insert(&lhs, &self.value_from_op(op)),
             Gep {
                 lhs,
                 src: _,
                 idx: _,
-            } =>  if lhs.typ().is_int() { self.insert(&lhs, &V::Top) },
+            } if lhs.typ().is_int() => self.insert(&lhs, &V::Top),
             Gfp {
                 lhs,
                 src: _,
                 field: _,
-            } =>  if lhs.typ().is_int() { self.insert(&lhs, &V::Top) },
-            Load { lhs, src: _ } =>  if lhs.typ().is_int() { self.insert(&lhs, &V::Top) },
-            Store { dst: _, op } => {
-                if op.typ().is_int() {
-                    let op_value = self.value_from_op(op);
-                    for var in &cfg.addr_taken_ints {
-                        
-                        let var_value = self.value_from_op(Operand::Var(var.clone()));
-                        let new_value = var_value.join(&op_value);
-
-                        self.insert(var, &new_value );
-                    }
+            } if lhs.typ().is_int() => self.insert(&lhs, &V::Top),
+            Load { lhs, src: _ } if lhs.typ().is_int() => self.insert(&lhs, &V::Top),
+            Store { dst: _, op } if op.typ().is_int() => {
+                let op_value = self.value_from_op(op);
+                for var in &cfg.addr_taken_ints {
+                    let var_value = self.value_from_op(Operand::Var(var.clone()));
+                    let new_value = var_value.join(&op_value);
+
+                    self.insert(var, &new_value);
                 }
             },
             _ => (),                       // phi is here
@@ -451,7 +478,7 @@ impl AbstractEnv for Env {
         }*/
     }
 
-    fn analyze_term(&mut self, term: &Terminal, cfg: &Cfg) -> Set<BbId> {
+    fn analyze_term(&mut self, term: &Terminal, cfg: &Cfg, _ctx: &mut ()) -> Set<BbId> {
         use Terminal::*;
         let mut skip_state = Set::new();
         match term {
@@ -499,13 +526,12 @@ impl AbstractEnv for Env {
             Ret(_) => {
                 // doesnt update value
             }
-            _ => (),
         }
 
         skip_state
     }
 
-    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg) -> (Vec<Self>, Set<BbId>) {
+    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg, ctx: &mut ()) -> (Vec<Self>, Set<BbId>) {
         // println!("analyze_bb: {:#?}", bb.id);
         let mut state_vec: Vec<Self> = vec![];
         let mut curr_state = self.clone();
@@ -515,12 +541,12 @@ impl AbstractEnv for Env {
         // loop through instructions
         // add all instructions' POST-STATE to vector
         for inst in &bb.insts {
-            curr_state.analyze_inst(inst, cfg);
+            curr_state.analyze_inst(inst, cfg, ctx);
             // println!("curr_state: {:#?}", curr_state);
             state_vec.push(curr_state.clone());
         }
 
-        let skip_state = curr_state.analyze_term(&bb.term, cfg);
+        let skip_state = curr_state.analyze_term(&bb.term, cfg, ctx);
         state_vec.push(curr_state.clone());
         // println!("state_vec: {:#?}", state_vec);
         (state_vec, skip_state)
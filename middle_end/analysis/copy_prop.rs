@@ -0,0 +1,112 @@
+//! Intraprocedural available copies analysis.
+//!
+//! A copy `x = $copy y` is available at a program point if on every path to
+//! it, the last definition of `x` is that copy and `y` has not been defined
+//! since.  Then `x` and `y` hold the same value there.
+//!
+//! This is a must analysis: the state is a [MustSet] of (x, y) pairs, joined by
+//! intersection, and blocks start from the top state until a path reaches
+//! them.  The entry starts with no available copies.
+//!
+//! Globals and address-taken variables can change without being named, so no
+//! copy involving one of them is tracked.
+
+use crate::commons::Valid;
+
+use super::*;
+
+// SECTION: analysis interface
+
+// The abstract environment.
+#[derive(Clone, Debug)]
+pub struct Env {
+    /// The available copies, as (lhs, rhs) pairs.
+    pub copies: MustSet<(VarId, VarId)>,
+    curr_inst: Option<InstId>,
+}
+
+impl Env {
+    /// The variable `x` is a copy of here, if any.  Only one copy of `x` can
+    /// be available, since a copy to `x` kills all the others.
+    pub fn copy_of(&self, x: &VarId) -> Option<&VarId> {
+        self.copies.facts()?.iter().find(|(lhs, _)| lhs == x).map(|(_, rhs)| rhs)
+    }
+}
+
+// Performs the analysis: use `forward_analysis` to implement this.
+pub fn analyze(program: &Valid<Program>, func: FuncId) -> (Map<BbId, Env>, Map<InstId, Env>) {
+    let program = &program.0;
+    let f = &program.functions[&func];
+
+    let entry_store = Env {
+        copies: MustSet::empty(),
+        curr_inst: None,
+    };
+    let top_store = Env {
+        copies: MustSet::Top,
+        curr_inst: None,
+    };
+
+    forward_analysis(
+        f,
+        &Cfg::new(f, program.globals.clone(), program.structs.clone()),
+        &entry_store,
+        &top_store,
+    )
+}
+
+// SECTION: analysis implementation
+
+impl Env {
+    // `x` is (re)defined: no copy from or to it holds anymore.
+    fn kill(&mut self, x: &VarId) {
+        self.copies.retain(|(lhs, rhs)| lhs != x && rhs != x);
+    }
+
+    fn tracked(x: &VarId, cfg: &Cfg) -> bool {
+        !x.is_global() && !cfg.addr_taken.contains(x)
+    }
+}
+
+impl AbstractEnv for Env {
+    fn join_with(&mut self, rhs: &Self, _block: &BbId, _join_type: i64) -> bool {
+        self.copies.intersect_with(&rhs.copies)
+    }
+
+    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg, _ctx: &mut ()) {
+        if let Some(lhs) = inst.def() {
+            self.kill(lhs);
+        }
+
+        if let Instruction::Copy { lhs, op: Operand::Var(rhs) } = inst {
+            if lhs != rhs && Self::tracked(lhs, cfg) && Self::tracked(rhs, cfg) {
+                self.copies.insert((lhs.clone(), rhs.clone()));
+            }
+        }
+    }
+
+    fn analyze_term(&mut self, term: &Terminal, _cfg: &Cfg, _ctx: &mut ()) -> Set<BbId> {
+        if let Some(lhs) = term.def() {
+            self.kill(lhs);
+        }
+
+        Set::new()
+    }
+
+    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg, ctx: &mut ()) -> (Vec<Self>, Set<BbId>) {
+        let mut v = vec![];
+        let mut s = self.clone();
+
+        for (i, inst) in bb.insts.iter().enumerate() {
+            s.curr_inst = Some((bb.id.clone(), i));
+            s.analyze_inst(inst, cfg, ctx);
+            v.push(s.clone());
+        }
+
+        s.curr_inst = Some((bb.id.clone(), bb.insts.len()));
+        s.analyze_term(&bb.term, cfg, ctx);
+        v.push(s);
+
+        (v, Set::new())
+    }
+}
@@ -0,0 +1,176 @@
+//! Dominator trees and dominance frontiers.
+//!
+//! The immediate dominators are computed with the iterative algorithm of
+//! Cooper, Harvey and Kennedy ("A Simple, Fast Dominance Algorithm"), which
+//! walks the blocks in reverse postorder until nothing changes.  Post-dominator
+//! trees are the dominator trees of the reversed cfg.
+
+use super::*;
+
+/// The dominator tree of the blocks reachable from the entry of a cfg.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DomTree {
+    root: BbId,
+    idoms: Map<BbId, BbId>,
+    children: Map<BbId, Set<BbId>>,
+    // the preorder number of every block, and the largest one in its subtree,
+    // so dominance is an interval check.
+    intervals: Map<BbId, (usize, usize)>,
+}
+
+impl DomTree {
+    pub fn new(cfg: &Cfg) -> Self {
+        let rpo = cfg.reverse_postorder();
+        let index: Map<&BbId, usize> = rpo.iter().enumerate().map(|(i, bb)| (bb, i)).collect();
+
+        let mut idom: Vec<Option<usize>> = vec![None; rpo.len()];
+        idom[0] = Some(0);
+
+        // walk up from both blocks until the paths meet.  Dominators come
+        // before the blocks they dominate in reverse postorder.
+        let intersect = |idom: &[Option<usize>], mut a: usize, mut b: usize| {
+            while a != b {
+                while a > b {
+                    a = idom[a].unwrap();
+                }
+                while b > a {
+                    b = idom[b].unwrap();
+                }
+            }
+            a
+        };
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for (i, bb) in rpo.iter().enumerate().skip(1) {
+                let new_idom = cfg
+                    .pred(bb)
+                    .filter_map(|pred| index.get(pred).copied())
+                    .filter(|&pred| idom[pred].is_some())
+                    .reduce(|a, b| intersect(&idom, a, b));
+
+                if new_idom != idom[i] {
+                    idom[i] = new_idom;
+                    changed = true;
+                }
+            }
+        }
+
+        let root = cfg.entry.clone();
+        let idoms: Map<BbId, BbId> = rpo
+            .iter()
+            .zip(idom)
+            .skip(1)
+            .map(|(bb, idom)| (bb.clone(), rpo[idom.unwrap()].clone()))
+            .collect();
+
+        let mut children: Map<BbId, Set<BbId>> = rpo.iter().map(|bb| (bb.clone(), Set::new())).collect();
+        for (bb, idom) in &idoms {
+            children.get_mut(idom).unwrap().insert(bb.clone());
+        }
+
+        // number the blocks in preorder; a block's interval is closed once its
+        // whole subtree is numbered.
+        let mut intervals: Map<BbId, (usize, usize)> = Map::new();
+        let mut n = 0;
+        let mut stack = vec![(root.clone(), false)];
+        while let Some((bb, subtree_done)) = stack.pop() {
+            if subtree_done {
+                intervals.get_mut(&bb).unwrap().1 = n - 1;
+                continue;
+            }
+
+            intervals.insert(bb.clone(), (n, n));
+            n += 1;
+            stack.push((bb.clone(), true));
+            stack.extend(children[&bb].iter().rev().map(|child| (child.clone(), false)));
+        }
+
+        DomTree {
+            root,
+            idoms,
+            children,
+            intervals,
+        }
+    }
+
+    pub fn root(&self) -> &BbId {
+        &self.root
+    }
+
+    // whether bb is in the tree, i.e. reachable from the root.
+    pub fn contains(&self, bb: &BbId) -> bool {
+        self.intervals.contains_key(bb)
+    }
+
+    // the immediate dominator of bb, if bb is in the tree and not the root.
+    pub fn idom(&self, bb: &BbId) -> Option<&BbId> {
+        self.idoms.get(bb)
+    }
+
+    // the blocks immediately dominated by bb.
+    pub fn children(&self, bb: &BbId) -> impl Iterator<Item = &BbId> {
+        self.children.get(bb).into_iter().flatten()
+    }
+
+    // whether a dominates b.  Every block dominates itself.
+    pub fn dominates(&self, a: &BbId, b: &BbId) -> bool {
+        match (self.intervals.get(a), self.intervals.get(b)) {
+            (Some((a_start, a_end)), Some((b_start, _))) => a_start <= b_start && b_start <= a_end,
+            _ => false,
+        }
+    }
+
+    pub fn strictly_dominates(&self, a: &BbId, b: &BbId) -> bool {
+        a != b && self.dominates(a, b)
+    }
+
+    // the dominators of bb, from bb itself up to the root.
+    pub fn dominators<'a>(&'a self, bb: &'a BbId) -> impl Iterator<Item = &'a BbId> {
+        std::iter::successors(self.contains(bb).then_some(bb), |bb| self.idom(bb))
+    }
+
+    // the blocks in the tree in preorder, so every block comes after its
+    // dominators.
+    pub fn preorder(&self) -> Vec<BbId> {
+        let mut order: Vec<(usize, BbId)> = self.intervals.iter().map(|(bb, (n, _))| (*n, bb.clone())).collect();
+        order.sort();
+        order.into_iter().map(|(_, bb)| bb).collect()
+    }
+
+    // the dominance frontier of every block in the tree: bb is in the
+    // frontier of a block that dominates a predecessor of bb without strictly
+    // dominating bb itself.  `cfg` must be the cfg the tree was built from.
+    pub fn frontiers(&self, cfg: &Cfg) -> Map<BbId, Set<BbId>> {
+        let mut frontiers: Map<BbId, Set<BbId>> = self.intervals.keys().map(|bb| (bb.clone(), Set::new())).collect();
+
+        for bb in self.intervals.keys() {
+            for pred in cfg.pred(bb).filter(|pred| self.contains(pred)) {
+                let mut runner = pred;
+                while Some(runner) != self.idom(bb) {
+                    frontiers.get_mut(runner).unwrap().insert(bb.clone());
+                    match self.idom(runner) {
+                        Some(idom) => runner = idom,
+                        None => break,
+                    }
+                }
+            }
+        }
+
+        frontiers
+    }
+}
+
+impl Cfg {
+    pub fn dom_tree(&self) -> DomTree {
+        DomTree::new(self)
+    }
+
+    // the post-dominator tree, rooted at the exit.  Blocks that cannot reach
+    // the exit are not in it.
+    pub fn post_dom_tree(&self) -> DomTree {
+        DomTree::new(&self.reversed())
+    }
+}
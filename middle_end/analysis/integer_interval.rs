@@ -1,7 +1,5 @@
 //! Intraprocedural integer constant propagation, with no pointer information.
 
-use derive_more::Display;
-
 use crate::commons::Valid;
 
 use super::*;
@@ -40,6 +38,14 @@ impl Value {
         V::R((Bound::Unbounded, Bound::Unbounded))
     }
 
+    // whether the concrete value `n` is one of the values this represents.
+    pub fn contains(&self, n: i64) -> bool {
+        match self {
+            V::Bot => false,
+            V::R(interval) => std::ops::RangeBounds::contains(interval, &n),
+        }
+    }
+
     fn widen(&self, widee: &Self) -> Self {
 
         match (self, widee) {
@@ -61,7 +67,6 @@ impl Value {
 
                 V::R((i3_low, i3_high))
             },
-            _ => panic!("unexpected case in widening"),
         }
     }
 }
@@ -113,6 +118,29 @@ pub fn analyze(program: &Valid<Program>, func: FuncId) -> (Map<BbId, Env>, Map<I
     forward_analysis(f, &Cfg::new(f, program.globals.clone(), program.structs.clone()), &init_store, &bottom_store)
 }
 
+/// [analyze], but from the state the interpreter starts `func` in rather than
+/// with the locals at ⊥: the locals are zero-initialized, and so are the
+/// globals in `main`, while elsewhere they may hold anything.
+pub fn analyze_zero_init(program: &Valid<Program>, func: FuncId) -> (Map<BbId, Env>, Map<InstId, Env>) {
+    let program = &program.0;
+    let f = &program.functions[&func];
+    let mut init_store = create_init_store_no_ptrs(f, program);
+    let bottom_store = create_bottom_store_no_ptrs(f, program);
+
+    for local in &f.locals {
+        if let Some(value) = init_store.values.get_mut(local) {
+            *value = Value::interval(0, 0);
+        }
+    }
+    for global in &program.globals {
+        if let Some(value) = init_store.values.get_mut(global) {
+            *value = if func == func_id("main") { Value::interval(0, 0) } else { Value::top() };
+        }
+    }
+
+    forward_analysis(f, &Cfg::new(f, program.globals.clone(), program.structs.clone()), &init_store, &bottom_store)
+}
+
 // SECTION: helpers
 
 fn create_init_store(f: &Function, program: &Program) -> Env {
@@ -320,7 +348,7 @@ impl AbstractEnv for Env {
         changed
     }
 
-    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg) {
+    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg, _ctx: &mut ()) {
         use Instruction::*;
 
         fn arith(aop: ArithmeticOp, v1: Value, v2: Value) -> Value {
@@ -437,14 +465,13 @@ impl AbstractEnv for Env {
                 
             }
 
-            fn interval_division((mut i1_low, mut i1_high): IntInterval, (mut i2_low, mut i2_high): IntInterval) -> Value {
+            fn interval_division((i1_low, i1_high): IntInterval, (mut i2_low, mut i2_high): IntInterval) -> Value {
                 fn b_div((b1, i1): (Bound<i64>, i64), (b2, i2): (Bound<i64>, i64)) -> (Bound<i64>, i64) {
 
                     match (b1, b2) {
                         // div by zero which shouldnt happen
                         (_, Included(0)) => {
                             panic!("panicking even though it should work, b_div div by 0");
-                            (Unbounded, i1)
                         },
                         // if both are unbounded, calculate which way it is
                         (Unbounded, Unbounded) => (Included(1), i1 / i2),
@@ -477,11 +504,9 @@ impl AbstractEnv for Env {
                         i2_high = Included(-1);
                     },
                     // interval crosses 0
-                    (_, _, Some(i2l), Some(i2h)) => {
-                        if i2l < 0 && i2h > 0 {
-                            i2_low = Included(-1);
-                            i2_high = Included(1);
-                        }
+                    (_, _, Some(i2l), Some(i2h)) if i2l < 0 && i2h > 0 => {
+                        i2_low = Included(-1);
+                        i2_high = Included(1);
                     },
                     // interval crosses 0 both ends unbounded, one end bounded should be checked earlier
                     (_, _, None, None) => {
@@ -680,8 +705,8 @@ impl AbstractEnv for Env {
         }
 
         match inst.clone() {
-            AddrOf { lhs, op } => if lhs.typ().is_int() { self.insert(&lhs, &V::top()) },
-            Alloc { lhs, num: _, id: _ } => if lhs.typ().is_int() { self.insert(&lhs, &V::top()) },
+            AddrOf { lhs, op: _ } if lhs.typ().is_int() => self.insert(&lhs, &V::top()),
+            Alloc { lhs, num: _, id: _ } if lhs.typ().is_int() => self.insert(&lhs, &V::top()),
             Arith { lhs, aop, op1, op2 } => {
                 let v1 = self.value_from_op(op1);
                 let v2 = self.value_from_op(op2);
@@ -709,32 +734,25 @@ impl AbstractEnv for Env {
                 
                 if lhs.typ().is_int() { self.insert(&lhs, &lhs_val) };
             }
-            Copy { lhs, op } => {
-                if lhs.typ().is_int() { 
-                    self.insert(&lhs, &self.value_from_op(op))
-                };
-            }
+            Copy { lhs, op } if lhs.typ().is_int() => self.insert(&lhs, &self.value_from_op(op)),
             Gep {
                 lhs,
                 src: _,
                 idx: _,
-            } =>  if lhs.typ().is_int() { self.insert(&lhs, &V::top()) },
+            } if lhs.typ().is_int() => self.insert(&lhs, &V::top()),
             Gfp {
                 lhs,
                 src: _,
                 field: _,
-            } =>  if lhs.typ().is_int() { self.insert(&lhs, &V::top()) },
-            Load { lhs, src: _ } =>  if lhs.typ().is_int() { self.insert(&lhs, &V::top()) },
-            Store { dst: _, op } => {
-                if op.typ().is_int() {
-                    let op_value = self.value_from_op(op);
-                    for var in &cfg.addr_taken_ints {
-                        
-                        let var_value = self.value_from_op(Operand::Var(var.clone()));
-                        let new_value = var_value.join(&op_value);
-
-                        self.insert(var, &new_value );
-                    }
+            } if lhs.typ().is_int() => self.insert(&lhs, &V::top()),
+            Load { lhs, src: _ } if lhs.typ().is_int() => self.insert(&lhs, &V::top()),
+            Store { dst: _, op } if op.typ().is_int() => {
+                let op_value = self.value_from_op(op);
+                for var in &cfg.addr_taken_ints {
+                    let var_value = self.value_from_op(Operand::Var(var.clone()));
+                    let new_value = var_value.join(&op_value);
+
+                    self.insert(var, &new_value);
                 }
             },
             _ => (),                       // phi is here
@@ -784,7 +802,7 @@ impl AbstractEnv for Env {
         }*/
     }
 
-    fn analyze_term(&mut self, term: &Terminal, cfg: &Cfg) -> Set<BbId> {
+    fn analyze_term(&mut self, term: &Terminal, cfg: &Cfg, _ctx: &mut ()) -> Set<BbId> {
         use Terminal::*;
         let mut skip_state = Set::new();
         match term {
@@ -796,20 +814,15 @@ impl AbstractEnv for Env {
                 let cv = self.value_from_op(cond.clone());
                 match cv {
                     Value::R((Included(0), Included(0))) => { skip_state.insert(tt.clone()); },
-                    Value::R((Included(low), Included(high))) => { 
-                        if (low < 0 && high < 0) || (low > 0 && high > 0) {
-                            skip_state.insert(ff.clone());
-                        }
+                    Value::R((Included(low), Included(high)))
+                        if (low < 0 && high < 0) || (low > 0 && high > 0) => {
+                        skip_state.insert(ff.clone());
                     },
-                    Value::R((Included(low), Unbounded)) => { 
-                        if low > 0 {
-                            skip_state.insert(ff.clone());
-                        }
+                    Value::R((Included(low), Unbounded)) if low > 0 => {
+                        skip_state.insert(ff.clone());
                     },
-                    Value::R((Unbounded, Included(high))) => {
-                        if high < 0 {
-                            skip_state.insert(ff.clone());
-                        }
+                    Value::R((Unbounded, Included(high))) if high < 0 => {
+                        skip_state.insert(ff.clone());
                     },
                     
                     Value::Bot => { skip_state.insert(tt.clone()); skip_state.insert(ff.clone()); },
@@ -847,13 +860,12 @@ impl AbstractEnv for Env {
             Ret(_) => {
                 // doesnt update value
             }
-            _ => (),
         }
 
         skip_state
     }
 
-    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg) -> (Vec<Self>, Set<BbId>) {
+    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg, ctx: &mut ()) -> (Vec<Self>, Set<BbId>) {
         // println!("analyze_bb: {:#?}", bb.id);
         let mut state_vec: Vec<Self> = vec![];
         let mut curr_state = self.clone();
@@ -863,12 +875,12 @@ impl AbstractEnv for Env {
         // loop through instructions
         // add all instructions' POST-STATE to vector
         for inst in &bb.insts {
-            curr_state.analyze_inst(inst, cfg);
+            curr_state.analyze_inst(inst, cfg, ctx);
             // println!("curr_state: {:#?}", curr_state);
             state_vec.push(curr_state.clone());
         }
 
-        let skip_state = curr_state.analyze_term(&bb.term, cfg);
+        let skip_state = curr_state.analyze_term(&bb.term, cfg, ctx);
         state_vec.push(curr_state.clone());
         // println!("state_vec: {:#?}", state_vec);
         (state_vec, skip_state)
@@ -1,6 +1,11 @@
 //! Intraprocedural liveness analysis.
 //!
 //! Remember that this is a backwards analysis!
+//!
+//! Besides the live variables, the analysis tracks which definitions are live:
+//! a definition is live at a program point if it reaches a use that is
+//! reachable from that point.  The reaching definitions analysis tells us which
+//! definitions each use reads.
 
 use std::rc::Rc;
 
@@ -19,34 +24,153 @@ pub struct Env {
     reaching_defs: Rc<Map<InstId, super::reaching_defs::Env>>,
     // result of this analysis
     pub live_defs: Set<InstId>,
+    pub live_vars: Set<VarId>,
+    curr_inst: Option<InstId>,
 }
 
 // Performs the analysis: use `backward_analysis` to implement this.
-pub fn analyze(_program: &Valid<Program>, _func: FuncId) -> (Map<BbId, Env>, Map<InstId, Env>) {
-    // @clippydodge let reaching_def_results = Rc::new(super::reaching_defs::analyze(program, func.clone()).0);
-    // @clippydodge let program = &program.0;
+pub fn analyze(program: &Valid<Program>, func: FuncId) -> (Map<BbId, Env>, Map<InstId, Env>) {
+    let reaching_defs = super::reaching_defs::analyze(program, func.clone());
+    analyze_with(program, func, &reaching_defs)
+}
+
+/// Performs the analysis given the result of the reaching definitions analysis
+/// of the function, e.g. one computed for another analysis already.
+pub fn analyze_with(
+    program: &Valid<Program>,
+    func: FuncId,
+    reaching_defs: &(Map<BbId, super::reaching_defs::Env>, Map<InstId, super::reaching_defs::Env>),
+) -> (Map<BbId, Env>, Map<InstId, Env>) {
+    let reaching_defs = Rc::new(reaching_defs_pre_states(program, func.clone(), reaching_defs));
+    let program = &program.0;
+    let f = &program.functions[&func];
+
+    let init_store = Env {
+        reaching_defs,
+        live_defs: Set::new(),
+        live_vars: Set::new(),
+        curr_inst: None,
+    };
+
+    backward_analysis(f, &Cfg::new(f, program.globals.clone(), program.structs.clone()), &init_store, &init_store)
+}
+
+// The reaching definitions before each instruction and terminal.
+fn reaching_defs_pre_states(
+    program: &Valid<Program>,
+    func: FuncId,
+    (pre_bb, post_inst): &(Map<BbId, super::reaching_defs::Env>, Map<InstId, super::reaching_defs::Env>),
+) -> Map<InstId, super::reaching_defs::Env> {
+    let f = &program.0.functions[&func];
 
-    // @clippydodge let init_store = todo!("map everything to empty set");
+    let mut pre_inst = Map::new();
+    for (bbid, bb) in &f.body {
+        let mut pre = pre_bb[bbid].clone();
+        for i in 0..=bb.insts.len() {
+            let id = (bbid.clone(), i);
+            let post = post_inst.get(&id).cloned();
+            pre_inst.insert(id, pre);
+            pre = post.unwrap_or_else(|| pre_bb[bbid].clone());
+        }
+    }
 
-    todo!("call backward_analysis");
+    pre_inst
 }
 
 // SECTION: analysis implementation
 
+impl Env {
+    // mark the variables in `vars` as used at `id`, along with the definitions
+    // of them reaching `id`.
+    fn use_vars<'a>(&mut self, id: &InstId, vars: impl IntoIterator<Item = &'a VarId>) {
+        let reaching = self.reaching_defs.get(id);
+        for var in vars {
+            self.live_vars.insert(var.clone());
+            if let Some(reaching) = reaching {
+                self.live_defs.extend(reaching.get(var).0);
+            }
+        }
+    }
+
+    // `lhs` is (re)defined at `id`: nothing before it can read this value.
+    fn define(&mut self, id: &InstId, lhs: &VarId) {
+        self.live_defs.remove(id);
+        self.live_vars.remove(lhs);
+    }
+
+    // the variables that a callee, a `$load` or the caller can read without
+    // naming them.
+    fn escaping(cfg: &Cfg) -> impl Iterator<Item = &VarId> {
+        cfg.globals.iter().chain(cfg.addr_taken.iter())
+    }
+}
+
 impl AbstractEnv for Env {
-    fn join_with(&mut self, _rhs: &Self, _block: &BbId, join_type: i64) -> bool {
-        todo!()
+    fn join_with(&mut self, rhs: &Self, _block: &BbId, _join_type: i64) -> bool {
+        let old = (self.live_defs.len(), self.live_vars.len());
+
+        self.live_defs.extend(rhs.live_defs.iter().cloned());
+        self.live_vars.extend(rhs.live_vars.iter().cloned());
+
+        old != (self.live_defs.len(), self.live_vars.len())
     }
 
-    fn analyze_inst(&mut self, _inst: &Instruction, cfg: &Cfg) {
-        todo!()
+    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg, _ctx: &mut ()) {
+        use Instruction::*;
+
+        let id = self.curr_inst.clone().unwrap();
+
+        let mut uses = inst.uses();
+        match inst {
+            // calls and loads may read memory, and callees any global.
+            CallExt { .. } | Load { .. } => uses.extend(Self::escaping(cfg)),
+            // a phi only reads the argument of the edge taken, but we don't
+            // know which one here.
+            _ => (),
+        }
+
+        if let Some(lhs) = inst.def() {
+            self.define(&id, lhs);
+        }
+        self.use_vars(&id, uses);
     }
 
-    fn analyze_term(&mut self, _term: &Terminal, cfg: &Cfg) -> Set<BbId> {
-        todo!()
+    fn analyze_term(&mut self, term: &Terminal, cfg: &Cfg, _ctx: &mut ()) -> Set<BbId> {
+        use Terminal::*;
+
+        let id = self.curr_inst.clone().unwrap();
+
+        let mut uses = term.uses();
+        match term {
+            CallDirect { .. } | CallIndirect { .. } => uses.extend(Self::escaping(cfg)),
+            // the caller may read any global after we return.
+            Ret(_) => uses.extend(cfg.globals.iter()),
+            _ => (),
+        }
+
+        if let Some(lhs) = term.def() {
+            self.define(&id, lhs);
+        }
+        self.use_vars(&id, uses);
+
+        Set::new()
     }
 
-    fn analyze_bb(&self, _bb: &BasicBlock, cfg: &Cfg) -> (Vec<Self>, Set<BbId>) {
-        todo!()
+    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg, ctx: &mut ()) -> (Vec<Self>, Set<BbId>) {
+        let mut v = vec![];
+        let mut s = self.clone();
+
+        s.curr_inst = Some((bb.id.clone(), bb.insts.len()));
+        s.analyze_term(&bb.term, cfg, ctx);
+        v.push(s.clone());
+
+        for (i, inst) in bb.insts.iter().enumerate().rev() {
+            s.curr_inst = Some((bb.id.clone(), i));
+            s.analyze_inst(inst, cfg, ctx);
+            v.push(s.clone());
+        }
+
+        v.reverse();
+        (v, Set::new())
     }
 }
@@ -0,0 +1,152 @@
+//! Loop-nest analysis.
+//!
+//! A back edge is an edge `latch -> header` where `header` dominates `latch`.
+//! The natural loop of a back edge is the header plus every block that can
+//! reach the latch without going through the header.  Natural loops with the
+//! same header are merged into one loop, and the loops of a function form a
+//! forest by nesting.
+//!
+//! Cycles in irreducible graphs have no back edge, so they are not loops here.
+
+use super::*;
+
+/// A natural loop.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Loop {
+    pub header: BbId,
+    /// The blocks in the loop, including the header and those of the loops
+    /// nested in it.
+    pub body: Set<BbId>,
+    /// The sources of the back edges to the header.
+    pub latches: Set<BbId>,
+    /// The edges leaving the loop, as (block inside, block outside).
+    pub exits: Set<(BbId, BbId)>,
+    /// The header of the innermost loop containing this one.
+    pub parent: Option<BbId>,
+    /// The number of loops containing this one, including itself.
+    pub depth: usize,
+    /// The only block outside the loop jumping to the header, if it has no
+    /// other successor.
+    pub preheader: Option<BbId>,
+}
+
+impl Loop {
+    pub fn contains(&self, bb: &BbId) -> bool {
+        self.body.contains(bb)
+    }
+
+    // the blocks outside the loop that the loop jumps to.
+    pub fn exit_blocks(&self) -> Set<BbId> {
+        self.exits.iter().map(|(_, to)| to.clone()).collect()
+    }
+}
+
+/// The loops of a function, keyed by their header.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LoopForest {
+    pub loops: Map<BbId, Loop>,
+}
+
+impl LoopForest {
+    pub fn new(cfg: &Cfg) -> Self {
+        let doms = cfg.dom_tree();
+        let mut loops: Map<BbId, Loop> = Map::new();
+
+        for latch in cfg.reverse_postorder() {
+            for header in cfg.succ(&latch).filter(|header| doms.dominates(header, &latch)) {
+                let l = loops.entry(header.clone()).or_insert_with(|| Loop {
+                    header: header.clone(),
+                    body: Set::from([header.clone()]),
+                    latches: Set::new(),
+                    exits: Set::new(),
+                    parent: None,
+                    depth: 0,
+                    preheader: None,
+                });
+                l.latches.insert(latch.clone());
+
+                // walk backwards from the latch; the header stops the walk.
+                let mut stack = vec![latch.clone()];
+                while let Some(bb) = stack.pop() {
+                    if l.body.insert(bb.clone()) {
+                        stack.extend(cfg.pred(&bb).filter(|pred| doms.contains(pred)).cloned());
+                    }
+                }
+            }
+        }
+
+        for l in loops.values_mut() {
+            l.exits = l
+                .body
+                .iter()
+                .flat_map(|bb| cfg.succ(bb).filter(|succ| !l.body.contains(*succ)).map(move |succ| (bb.clone(), succ.clone())))
+                .collect();
+
+            let mut outside_preds = cfg.pred(&l.header).filter(|pred| !l.body.contains(*pred));
+            l.preheader = match (outside_preds.next(), outside_preds.next()) {
+                (Some(pred), None) if cfg.succ(pred).count() == 1 => Some(pred.clone()),
+                _ => None,
+            };
+        }
+
+        // the innermost enclosing loop is the smallest one containing the
+        // header; loops are either nested or disjoint.
+        let parents: Map<BbId, Option<BbId>> = loops
+            .values()
+            .map(|l| {
+                let parent = loops
+                    .values()
+                    .filter(|outer| outer.header != l.header && outer.contains(&l.header))
+                    .min_by_key(|outer| outer.body.len())
+                    .map(|outer| outer.header.clone());
+                (l.header.clone(), parent)
+            })
+            .collect();
+
+        for (header, parent) in &parents {
+            let mut depth = 1;
+            let mut curr = parent;
+            while let Some(p) = curr {
+                depth += 1;
+                curr = &parents[p];
+            }
+
+            let l = loops.get_mut(header).unwrap();
+            l.parent = parent.clone();
+            l.depth = depth;
+        }
+
+        LoopForest { loops }
+    }
+
+    pub fn headers(&self) -> impl Iterator<Item = &BbId> {
+        self.loops.keys()
+    }
+
+    pub fn is_back_edge(&self, from: &BbId, to: &BbId) -> bool {
+        self.loops.get(to).is_some_and(|l| l.latches.contains(from))
+    }
+
+    // the innermost loop containing bb.
+    pub fn innermost(&self, bb: &BbId) -> Option<&Loop> {
+        self.loops
+            .values()
+            .filter(|l| l.contains(bb))
+            .max_by_key(|l| l.depth)
+    }
+
+    // the number of loops containing bb.
+    pub fn depth(&self, bb: &BbId) -> usize {
+        self.innermost(bb).map_or(0, |l| l.depth)
+    }
+
+    // the outermost loops.
+    pub fn roots(&self) -> impl Iterator<Item = &Loop> {
+        self.loops.values().filter(|l| l.parent.is_none())
+    }
+
+    // the loops immediately nested in the loop headed by `header`.
+    pub fn children<'a>(&'a self, header: &'a BbId) -> impl Iterator<Item = &'a Loop> {
+        self.loops.values().filter(move |l| l.parent.as_ref() == Some(header))
+    }
+}
@@ -0,0 +1,247 @@
+//! Intraprocedural flow-sensitive points-to analysis.
+//!
+//! The state at each program point maps the pointers, and the cells in memory
+//! that hold them, to the locations they may point to, by scoped names as
+//! [analysis_constraints::points_to] gives them.  A store through a pointer
+//! to a single cell overwrites what the cell points to; any other store adds
+//! to what every cell it may write points to.
+//!
+//! What the function is called with and what its callees do comes from a
+//! flow-insensitive solution for the whole program: the parameters, globals
+//! and cells start out pointing to what they do in it, and a call may make
+//! them point to anything they do in it.
+//!
+//! [analysis_constraints::points_to]: crate::middle_end::analysis_constraints::points_to
+
+use crate::commons::Valid;
+
+use super::*;
+
+// SECTION: analysis interface
+
+// The powerset lattice.  It represents the locations a pointer may point to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Value(pub Set<String>);
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let locs: Vec<&str> = self.0.iter().map(String::as_str).collect();
+        write!(f, "{{{}}}", locs.join(", "))
+    }
+}
+
+// Abstract environment, keyed by scoped names.
+pub type Env = PointwiseEnv<Value, String>;
+
+/// The points-to facts of a function.
+pub struct Solution {
+    /// The state before each instruction and terminal that was reached.
+    pub states: Map<InstId, Env>,
+    /// The locations that are a single cell, which a store through a pointer
+    /// to only one of them overwrites.
+    pub cells: Set<String>,
+}
+
+impl Solution {
+    /// The locations `x` may point to before `point`.  None if `x` points to
+    /// nothing there, or `point` is never reached.
+    pub fn get(&self, point: &InstId, x: &VarId) -> Option<&Set<String>> {
+        self.states.get(point)?.values.get(&x.to_scope_string()).map(|locs| &locs.0)
+    }
+
+    /// The cell a store through `x` at `point` overwrites, if it may write only
+    /// that one.
+    pub fn must(&self, point: &InstId, x: &VarId) -> Option<&String> {
+        match self.get(point, x)?.iter().collect::<Vec<_>>()[..] {
+            [loc] if self.cells.contains(loc) => Some(loc),
+            _ => None,
+        }
+    }
+}
+
+/// State shared by every block while the analysis runs.
+pub struct Context<'a> {
+    /// The flow-insensitive solution.
+    pub pts_to: &'a Map<String, Set<String>>,
+    /// The pointers and cells a callee may write.
+    pub outside: Set<String>,
+    /// The locals a pointer from outside the function may point to.  Such a
+    /// pointer may be to the local in another frame, so they are never
+    /// overwritten.
+    pub escaped: Set<String>,
+    /// See [Solution::cells].
+    pub cells: Set<String>,
+}
+
+// Performs the analysis, starting from the flow-insensitive solution `pts_to`.
+pub fn analyze(program: &Valid<Program>, func: FuncId, pts_to: &Map<String, Set<String>>) -> Solution {
+    let program = &program.0;
+    let f = &program.functions[&func];
+    let cfg = Cfg::new(f, program.globals.clone(), program.structs.clone());
+
+    let locals: Set<String> = f.locals.iter().chain(&f.params).map(VarId::to_scope_string).collect();
+    let globals: Set<String> = program.globals.iter().map(VarId::to_scope_string).collect();
+    let locations: Set<&String> = pts_to.values().flatten().collect();
+    let escaped: Set<String> = pts_to
+        .iter()
+        .filter(|(x, _)| !locals.contains(*x))
+        .flat_map(|(_, locs)| locs)
+        .filter(|loc| locals.contains(*loc))
+        .cloned()
+        .collect();
+
+    let outside: Set<String> = pts_to
+        .keys()
+        .filter(|x| globals.contains(*x) || locations.contains(x))
+        .filter(|x| !locals.contains(*x) || escaped.contains(*x))
+        .cloned()
+        .collect();
+    let cells = globals.iter().chain(locals.difference(&escaped)).cloned().collect();
+
+    let mut init_store = Env::new(Map::new());
+    for x in outside.iter().cloned().chain(f.params.iter().map(VarId::to_scope_string)) {
+        if let Some(locs) = pts_to.get(&x) {
+            init_store.insert(&x, &Value(locs.clone()));
+        }
+    }
+    let bottom_store = Env::new(Map::new());
+
+    let mut ctx = Context { pts_to, outside, escaped, cells };
+    let states = forward_analysis_with(f, &cfg, &init_store, &bottom_store, &mut ctx).1;
+    Solution { states, cells: ctx.cells }
+}
+
+// SECTION: analysis implementation
+
+impl AbstractValue for Value {
+    type Concrete = String;
+
+    const BOTTOM: Self = Value(Set::new());
+
+    fn alpha(loc: String) -> Self {
+        Value(Set::from([loc]))
+    }
+
+    fn join(&self, rhs: &Self) -> Value {
+        Value(self.0.union(&rhs.0).cloned().collect())
+    }
+}
+
+impl AbstractEnv<Context<'_>> for Env {
+    fn join_with(&mut self, rhs: &Self, _block: &BbId, _join_type: i64) -> bool {
+        self.pointwise_join(rhs)
+    }
+
+    fn analyze_inst(&mut self, inst: &Instruction, _cfg: &Cfg, ctx: &mut Context) {
+        use Instruction::*;
+
+        match inst {
+            AddrOf { lhs, op } => self.assign(lhs, Value::alpha(op.to_scope_string()), ctx),
+            Alloc { lhs, num: _, id } => self.assign(lhs, Value::alpha(id.to_scope_string()), ctx),
+            Arith { .. } | Cmp { .. } => {}
+            CallExt { lhs, .. } => self.call(lhs.as_ref(), ctx),
+            Copy { lhs, op } => {
+                let locs = self.operand(op);
+                self.assign(lhs, locs, ctx);
+            }
+            // fields and elements are merged with what they are in.
+            Gep { lhs, src, .. } | Gfp { lhs, src, .. } => {
+                let locs = self.get(&src.to_scope_string());
+                self.assign(lhs, locs, ctx);
+            }
+            Load { lhs, src } => {
+                let locs = self
+                    .get(&src.to_scope_string())
+                    .0
+                    .iter()
+                    .fold(Value::BOTTOM, |acc, loc| acc.join(&self.get(loc)));
+                self.assign(lhs, locs, ctx);
+            }
+            Store { dst, op } => {
+                if op.typ().is_ptr() {
+                    let locs = self.operand(op);
+                    let targets = self.get(&dst.to_scope_string()).0;
+                    match targets.iter().collect::<Vec<_>>()[..] {
+                        // σ[loc] ← σ[op]
+                        [loc] if ctx.cells.contains(loc) => self.insert(loc, &locs),
+                        // ∀loc ∈ σ[dst], σ[loc] ← σ[loc] ∪ σ[op]
+                        _ => {
+                            for loc in &targets {
+                                self.insert(loc, &self.get(loc).join(&locs));
+                            }
+                        }
+                    }
+                }
+            }
+            Phi { lhs, args } => {
+                let locs = args.iter().fold(Value::BOTTOM, |acc, op| acc.join(&self.operand(op)));
+                self.assign(lhs, locs, ctx);
+            }
+        }
+    }
+
+    fn analyze_term(&mut self, term: &Terminal, _cfg: &Cfg, ctx: &mut Context) -> Set<BbId> {
+        use Terminal::*;
+
+        match term {
+            CallDirect { lhs, .. } | CallIndirect { lhs, .. } => self.call(lhs.as_ref(), ctx),
+            _ => {}
+        }
+
+        Set::new()
+    }
+
+    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg, ctx: &mut Context) -> (Vec<Self>, Set<BbId>) {
+        // the pre state of each instruction and the terminal, then the post
+        // state of the block.
+        let mut states = vec![self.clone()];
+        let mut s = self.clone();
+
+        for inst in &bb.insts {
+            s.analyze_inst(inst, cfg, ctx);
+            states.push(s.clone());
+        }
+
+        let infeasible = s.analyze_term(&bb.term, cfg, ctx);
+        states.push(s);
+        (states, infeasible)
+    }
+}
+
+impl Env {
+    // the locations an operand points to: none for a constant.
+    fn operand(&self, op: &Operand) -> Value {
+        match op {
+            Operand::Var(x) => self.get(&x.to_scope_string()),
+            Operand::CInt(_) => Value::BOTTOM,
+        }
+    }
+
+    // σ[lhs] ← locs, for pointers.  A local that escaped may be another
+    // frame's too, so it keeps what it pointed to.
+    fn assign(&mut self, lhs: &VarId, locs: Value, ctx: &Context) {
+        if !lhs.typ().is_ptr() {
+            return;
+        }
+
+        let lhs = lhs.to_scope_string();
+        if ctx.escaped.contains(&lhs) {
+            self.insert(&lhs, &self.get(&lhs).join(&locs));
+        } else {
+            self.insert(&lhs, &locs);
+        }
+    }
+
+    // a call may make whatever it can write point to anything it does in the
+    // flow-insensitive solution, and returns any pointer `lhs` may point to.
+    fn call(&mut self, lhs: Option<&VarId>, ctx: &Context) {
+        for x in &ctx.outside {
+            self.insert(x, &self.get(x).join(&Value(ctx.pts_to[x].clone())));
+        }
+
+        if let Some(lhs) = lhs {
+            let locs = ctx.pts_to.get(&lhs.to_scope_string()).cloned().unwrap_or_default();
+            self.assign(lhs, Value(locs), ctx);
+        }
+    }
+}
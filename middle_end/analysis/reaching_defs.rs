@@ -60,32 +60,11 @@ fn join_sets(set1: Set<InstId>, set2: Set<InstId>) -> Set<InstId> {
 }
 
 impl AbstractEnv for Env {
-    fn join_with(&mut self, rhs: &Self, _block: &BbId, join_type: i64) -> bool {
-        let mut changed = false;
-
-        for (x, lhs) in self.values.iter_mut() {
-            if let Some(rhs) = rhs.values.get(x) {
-                let old = lhs.clone();
-                *lhs = lhs.join(rhs);
-
-                changed = changed || *lhs != old;
-            }
-        }
-
-        for (x, rhs) in &rhs.values {
-            let old = self.get(x);
-            let lhs = old.join(rhs);
-            self.insert(x, &lhs);
-
-            changed = changed || lhs != old;
-        }
-
-        changed
+    fn join_with(&mut self, rhs: &Self, _block: &BbId, _join_type: i64) -> bool {
+        self.pointwise_join(rhs)
     }
 
-    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg) {
-        let this_inst = &self.curr_inst.clone().unwrap();
-        
+    fn analyze_inst(&mut self, inst: &Instruction, _cfg: &Cfg, _ctx: &mut ()) {
         let get_vars = |opset: Vec<&Operand>| -> Vec<VarId> {
             let mut return_set: Vec<VarId> = Vec::new();
             for op in opset {
@@ -120,7 +99,7 @@ impl AbstractEnv for Env {
             Gfp { lhs, .. } => Some(lhs),
             Load { lhs, src: _ } => Some(lhs),
             Store { dst: _, op: _ } => None,
-            Phi { .. } => unreachable!(),
+            Phi { lhs, .. } => Some(lhs),
         };
 
         let mut used_lines: Set<InstId> = Set::new();
@@ -138,7 +117,7 @@ impl AbstractEnv for Env {
         }
     }
 
-    fn analyze_term(&mut self, term: &Terminal, cfg: &Cfg) -> Set<BbId> {
+    fn analyze_term(&mut self, term: &Terminal, _cfg: &Cfg, _ctx: &mut ()) -> Set<BbId> {
         use Terminal::*;
 
         let def = match term {
@@ -159,18 +138,18 @@ impl AbstractEnv for Env {
         Set::new()
     }
 
-    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg) -> (Vec<Self>, Set<BbId>) {
+    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg, ctx: &mut ()) -> (Vec<Self>, Set<BbId>) {
         let mut v = vec![];
         let mut s = self.clone();
 
         for (i, inst) in bb.insts.iter().enumerate() {
             s.curr_inst = Some((bb.id.clone(), i));
-            s.analyze_inst(inst, cfg);
+            s.analyze_inst(inst, cfg, ctx);
             v.push(s.clone());
         }
 
         s.curr_inst = Some((bb.id.clone(), bb.insts.len()));
-        s.analyze_term(&bb.term, cfg);
+        s.analyze_term(&bb.term, cfg, ctx);
         v.push(s.clone());
 
         (v, Set::new())
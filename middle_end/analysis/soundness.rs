@@ -0,0 +1,220 @@
+//! Checking analysis results against concrete executions.
+//!
+//! The program is run with the interpreter, and before each instruction the
+//! value of every variable is checked to be one of the values the analysis
+//! allows for it there.  A value that is not is a violation: the analysis
+//! missed a behavior of the program.  Like translation validation, this only
+//! checks one run, so it finds unsound results but does not prove that there
+//! are none.
+//!
+//! The integer analyses start locals at ⊥, but the interpreter zero-initializes
+//! them, and a ⊥ read taints everything computed from it.  So they are checked
+//! as run from the zero-initialized state (see
+//! [integer_interval::analyze_zero_init]).  A variable is checked only once it
+//! is assigned (see [Frame::values]).  A run that stops with a runtime error is
+//! checked up to the error.
+
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+use std::fmt::Display;
+
+use crate::commons::Valid;
+use crate::interpreter::{interpret_observed, Frame, Observed, Observer};
+use crate::middle_end::analysis_constraints;
+
+use super::*;
+
+/// The results of an analysis, as checked against concrete values.
+pub trait Facts {
+    /// The abstract value of `x` at `point` in `func`, if it does not allow
+    /// `witness`.  None for the variables the analysis does not track.
+    fn violated(&self, func: &FuncId, point: &InstId, x: &VarId, witness: &Observed) -> Option<String>;
+}
+
+/// A concrete value missing from the results of an analysis.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Violation {
+    pub func: FuncId,
+    pub point: InstId,
+    pub var: VarId,
+    /// The first value of `var` seen at `point` that the analysis misses.
+    pub witness: Observed,
+    /// What the analysis says about `var` at `point`.
+    pub fact: String,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (bb, i) = &self.point;
+        write!(
+            f,
+            "{}: before {bb}.{i}: {} = {} is not in {}",
+            self.func, self.var, self.witness, self.fact
+        )
+    }
+}
+
+/// Run the program and report the violations of the given facts, at most one
+/// for each variable at each program point.
+pub fn check(program: &Valid<Program>, facts: &impl Facts) -> Vec<Violation> {
+    let mut checker = Checker {
+        facts,
+        violations: Map::new(),
+    };
+    let _ = interpret_observed(program.0.clone(), &mut checker);
+    checker.violations.into_values().collect()
+}
+
+struct Checker<'a, F: Facts> {
+    facts: &'a F,
+    violations: Map<(FuncId, InstId, VarId), Violation>,
+}
+
+impl<F: Facts> Observer for Checker<'_, F> {
+    fn before(&mut self, point: &(BbId, usize), frame: &Frame) {
+        let func = frame.func();
+        for (x, witness) in frame.values() {
+            let key = (func.clone(), point.clone(), x);
+            if self.violations.contains_key(&key) {
+                continue;
+            }
+            if let Some(fact) = self.facts.violated(func, point, &key.2, &witness) {
+                let violation = Violation {
+                    func: func.clone(),
+                    point: point.clone(),
+                    var: key.2.clone(),
+                    witness,
+                    fact,
+                };
+                self.violations.insert(key, violation);
+            }
+        }
+    }
+}
+
+// SECTION: the checked analyses
+
+/// The integer intervals of each function, from
+/// [integer_interval::analyze_zero_init].
+pub struct Intervals(pub Map<FuncId, Map<InstId, integer_interval::Env>>);
+
+impl Intervals {
+    pub fn new(program: &Valid<Program>) -> Self {
+        Intervals(
+            program
+                .0
+                .functions
+                .keys()
+                .map(|f| (f.clone(), integer_interval::analyze_zero_init(program, f.clone()).1))
+                .collect(),
+        )
+    }
+}
+
+impl Facts for Intervals {
+    fn violated(&self, func: &FuncId, point: &InstId, x: &VarId, witness: &Observed) -> Option<String> {
+        int_violated(&self.0, func, point, x, witness, integer_interval::Value::contains)
+    }
+}
+
+/// The integer constants of each function, from
+/// [constant_prop::analyze_zero_init].
+pub struct Constants(pub Map<FuncId, Map<InstId, constant_prop::Env>>);
+
+impl Constants {
+    pub fn new(program: &Valid<Program>) -> Self {
+        Constants(
+            program
+                .0
+                .functions
+                .keys()
+                .map(|f| (f.clone(), constant_prop::analyze_zero_init(program, f.clone()).1))
+                .collect(),
+        )
+    }
+}
+
+impl Facts for Constants {
+    fn violated(&self, func: &FuncId, point: &InstId, x: &VarId, witness: &Observed) -> Option<String> {
+        int_violated(&self.0, func, point, x, witness, constant_prop::Value::contains)
+    }
+}
+
+// The states of these analyses at (bb, i) are the states before the ith
+// instruction.  A point missing from them was never reached by the analysis,
+// so every variable there is ⊥.
+fn int_violated<A: AbstractValue>(
+    states: &Map<FuncId, Map<InstId, PointwiseEnv<A>>>,
+    func: &FuncId,
+    point: &InstId,
+    x: &VarId,
+    witness: &Observed,
+    contains: fn(&A, i64) -> bool,
+) -> Option<String> {
+    let Observed::Int(n) = witness else {
+        return None;
+    };
+    let value = match states.get(func).and_then(|states| states.get(point)) {
+        Some(env) => env.values.get(x)?.clone(),
+        None => A::BOTTOM,
+    };
+
+    (!contains(&value, *n)).then(|| value.to_string())
+}
+
+/// A flow-insensitive points-to solution, keyed by [VarId::to_scope_string]
+/// like [analysis_constraints::points_to] returns it.  A pointer the solution
+/// has nothing for points to nothing.
+pub struct PointsTo(pub Map<String, Set<String>>);
+
+impl PointsTo {
+    /// The solution of the constraint solver.
+    pub fn new(program: &Valid<Program>) -> Self {
+        PointsTo(analysis_constraints::points_to(program))
+    }
+}
+
+impl Facts for PointsTo {
+    fn violated(&self, _func: &FuncId, _point: &InstId, x: &VarId, witness: &Observed) -> Option<String> {
+        loc_violated(self.0.get(&x.to_scope_string()), witness)
+    }
+}
+
+/// The flow-sensitive points-to facts of each function, from
+/// [points_to::analyze] starting from [analysis_constraints::points_to].
+pub struct FlowPointsTo(pub Map<FuncId, points_to::Solution>);
+
+impl FlowPointsTo {
+    pub fn new(program: &Valid<Program>) -> Self {
+        let pts_to = analysis_constraints::points_to(program);
+        FlowPointsTo(
+            program
+                .0
+                .functions
+                .keys()
+                .map(|f| (f.clone(), points_to::analyze(program, f.clone(), &pts_to)))
+                .collect(),
+        )
+    }
+}
+
+impl Facts for FlowPointsTo {
+    fn violated(&self, func: &FuncId, point: &InstId, x: &VarId, witness: &Observed) -> Option<String> {
+        loc_violated(self.0.get(func).and_then(|solution| solution.get(point, x)), witness)
+    }
+}
+
+// A pointer with no locations, or at a point that was never reached, points
+// to nothing.
+fn loc_violated(pts_to: Option<&Set<String>>, witness: &Observed) -> Option<String> {
+    let loc = match witness {
+        Observed::Loc(loc) => loc.clone(),
+        Observed::Fn(f) => f.name().to_string(),
+        _ => return None,
+    };
+
+    if pts_to.is_some_and(|pts_to| pts_to.contains(&loc)) {
+        return None;
+    }
+    let pts_to: Vec<&str> = pts_to.into_iter().flatten().map(String::as_str).collect();
+    Some(format!("{{{}}}", pts_to.join(", ")))
+}
@@ -52,3 +52,713 @@ fn run_test<Env: AbstractEnv, Printer: Fn(&Env) -> String>(
 
     collapsed_eq!(&actual, &expected);
 }
+
+fn parse(input: &str) -> Valid<Program> {
+    input.parse::<Program>().unwrap().validate().unwrap()
+}
+
+const DIAMOND: &str = r#"
+    fn main() -> int {
+    let x:int, y:int
+    entry:
+      x = $copy 1
+      $branch x bb1 bb2
+
+    bb1:
+      y = $copy x
+      $jump bb3
+
+    bb2:
+      y = $copy 2
+      $jump bb3
+
+    bb3:
+      $ret y
+    }
+"#;
+
+#[test]
+fn dominator_and_post_dominator_trees() {
+    let program = parse(DIAMOND);
+    let f = &program.0.functions[&func_id("main")];
+    let cfg = Cfg::new(f, program.0.globals.clone(), program.0.structs.clone());
+
+    let set = |bbs: &[&str]| bbs.iter().map(|bb| bb_id(bb)).collect::<Set<_>>();
+    let (entry, bb1, bb2, bb3) = (bb_id("entry"), bb_id("bb1"), bb_id("bb2"), bb_id("bb3"));
+
+    let doms = cfg.dom_tree();
+    assert_eq!(doms.root(), &entry);
+    assert_eq!(doms.idom(&entry), None);
+    assert_eq!(doms.idom(&bb1), Some(&entry));
+    assert_eq!(doms.idom(&bb3), Some(&entry));
+    assert_eq!(doms.children(&entry).cloned().collect::<Set<_>>(), set(&["bb1", "bb2", "bb3"]));
+    assert!(doms.dominates(&entry, &bb3) && doms.dominates(&bb3, &bb3));
+    assert!(!doms.dominates(&bb1, &bb3) && !doms.strictly_dominates(&bb3, &bb3));
+    assert_eq!(doms.dominators(&bb2).cloned().collect::<Vec<_>>(), vec![bb2.clone(), entry.clone()]);
+
+    let frontiers = doms.frontiers(&cfg);
+    assert_eq!(frontiers[&bb1], set(&["bb3"]));
+    assert_eq!(frontiers[&entry], set(&[]));
+
+    let postdoms = cfg.post_dom_tree();
+    assert_eq!(postdoms.root(), &bb3);
+    assert_eq!(postdoms.idom(&entry), Some(&bb3));
+    assert!(postdoms.dominates(&bb3, &bb2));
+
+    let post_frontiers = postdoms.frontiers(&cfg.reversed());
+    assert_eq!(post_frontiers[&bb1], set(&["entry"]));
+    assert_eq!(post_frontiers[&bb3], set(&[]));
+}
+
+// The frontiers of a loop header include the header itself.
+#[test]
+fn dominance_frontiers_of_nested_loops() {
+    let program = parse(NESTED_LOOPS);
+    let f = &program.0.functions[&func_id("main")];
+    let cfg = Cfg::new(f, program.0.globals.clone(), program.0.structs.clone());
+
+    let set = |bbs: &[&str]| bbs.iter().map(|bb| bb_id(bb)).collect::<Set<_>>();
+    let frontiers = cfg.dom_tree().frontiers(&cfg);
+
+    assert_eq!(frontiers[&bb_id("outer")], set(&["outer"]));
+    assert_eq!(frontiers[&bb_id("inner")], set(&["inner", "outer"]));
+    assert_eq!(frontiers[&bb_id("inner_body")], set(&["inner"]));
+    assert_eq!(frontiers[&bb_id("outer_latch")], set(&["outer"]));
+    assert_eq!(frontiers[&bb_id("exit")], set(&[]));
+}
+
+// Analyses that accumulate a solution do so through the context.
+#[test]
+fn reaching_defs_solution_is_collected_in_context() {
+    use crate::middle_end::analysis_rdef::reaching_defs;
+
+    let program = parse(DIAMOND);
+    let soln = reaching_defs::analyze(&program, func_id("main"));
+
+    let ret = ProgramPoint::from(bb_id("bb3"), None);
+    assert_eq!(
+        soln[&ret],
+        Set::from([
+            ProgramPoint::from(bb_id("bb1"), Some(0)),
+            ProgramPoint::from(bb_id("bb2"), Some(0)),
+        ])
+    );
+}
+
+const NESTED_LOOPS: &str = r#"
+    fn main() -> int {
+    let i:int, j:int, c:int
+    entry:
+      i = $copy 0
+      $jump outer
+
+    outer:
+      c = $cmp lt i 10
+      $branch c inner_pre exit
+
+    inner_pre:
+      j = $copy 0
+      $jump inner
+
+    inner:
+      c = $cmp lt j i
+      $branch c inner_body outer_latch
+
+    inner_body:
+      j = $arith add j 1
+      $jump inner
+
+    outer_latch:
+      i = $arith add i 1
+      $jump outer
+
+    exit:
+      $ret i
+    }
+"#;
+
+#[test]
+fn reverse_postorder_puts_blocks_before_their_forward_successors() {
+    let program = parse(NESTED_LOOPS);
+    let f = &program.0.functions[&func_id("main")];
+    let cfg = Cfg::new(f, program.0.globals.clone(), program.0.structs.clone());
+
+    let rpo = cfg.reverse_postorder();
+    let index = |bb: &str| rpo.iter().position(|x| *x == bb_id(bb)).unwrap();
+
+    assert_eq!(rpo.len(), f.body.len());
+    assert_eq!(index("entry"), 0);
+    assert!(index("outer") < index("inner_pre"));
+    assert!(index("inner_pre") < index("inner"));
+    assert!(index("inner") < index("inner_body"));
+    assert!(index("inner") < index("outer_latch"));
+}
+
+// The blocks on some path to a block.
+#[derive(Clone)]
+struct Visited(Set<BbId>);
+
+impl AbstractEnv for Visited {
+    fn join_with(&mut self, rhs: &Self, _block: &BbId, _join_type: i64) -> bool {
+        let len = self.0.len();
+        self.0.extend(rhs.0.iter().cloned());
+        len != self.0.len()
+    }
+
+    fn analyze_inst(&mut self, _inst: &Instruction, _cfg: &Cfg, _ctx: &mut ()) {}
+
+    fn analyze_term(&mut self, _term: &Terminal, _cfg: &Cfg, _ctx: &mut ()) -> Set<BbId> {
+        Set::new()
+    }
+
+    fn analyze_bb(&self, bb: &BasicBlock, _cfg: &Cfg, _ctx: &mut ()) -> (Vec<Self>, Set<BbId>) {
+        let mut s = self.clone();
+        s.0.insert(bb.id.clone());
+        (vec![s], Set::new())
+    }
+}
+
+// Each block of an acyclic cfg is analyzed exactly once, and the solver
+// records the joins it performs.
+#[test]
+fn forward_analysis_reports_stats() {
+    let program = parse(DIAMOND);
+    let f = &program.0.functions[&func_id("main")];
+    let cfg = Cfg::new(f, program.0.globals.clone(), program.0.structs.clone());
+
+    let empty = Visited(Set::new());
+    let (_, _, stats) = forward_analysis_with_stats(f, &cfg, &empty, &empty, &mut ());
+
+    assert_eq!(stats.block_visits, 4);
+    assert_eq!(stats.joins, 4);
+    assert_eq!(stats.widenings, 0);
+}
+
+#[test]
+fn loop_forest_of_nested_loops() {
+    let program = parse(NESTED_LOOPS);
+    let f = &program.0.functions[&func_id("main")];
+    let cfg = Cfg::new(f, program.0.globals.clone(), program.0.structs.clone());
+
+    let set = |bbs: &[&str]| bbs.iter().map(|bb| bb_id(bb)).collect::<Set<_>>();
+    let loops = &cfg.loops;
+
+    assert_eq!(loops.headers().cloned().collect::<Set<_>>(), set(&["outer", "inner"]));
+    assert_eq!(cfg.loop_headers, set(&["outer", "inner"]));
+
+    let outer = &loops.loops[&bb_id("outer")];
+    assert_eq!(outer.body, set(&["outer", "inner_pre", "inner", "inner_body", "outer_latch"]));
+    assert_eq!(outer.latches, set(&["outer_latch"]));
+    assert_eq!(outer.exit_blocks(), set(&["exit"]));
+    assert_eq!(outer.preheader, Some(bb_id("entry")));
+    assert_eq!((outer.parent.clone(), outer.depth), (None, 1));
+
+    let inner = &loops.loops[&bb_id("inner")];
+    assert_eq!(inner.body, set(&["inner", "inner_body"]));
+    assert_eq!(inner.exits, Set::from([(bb_id("inner"), bb_id("outer_latch"))]));
+    assert_eq!(inner.preheader, Some(bb_id("inner_pre")));
+    assert_eq!((inner.parent.clone(), inner.depth), (Some(bb_id("outer")), 2));
+
+    assert!(loops.is_back_edge(&bb_id("inner_body"), &bb_id("inner")));
+    assert!(!loops.is_back_edge(&bb_id("inner_pre"), &bb_id("inner")));
+    assert_eq!(loops.depth(&bb_id("inner_body")), 2);
+    assert_eq!(loops.depth(&bb_id("outer_latch")), 1);
+    assert_eq!(loops.depth(&bb_id("exit")), 0);
+    assert_eq!(loops.children(&bb_id("outer")).count(), 1);
+}
+
+// Neither block of an irreducible cycle dominates the other, so there is no
+// natural loop, but the analyses still need a widening point on the cycle.
+#[test]
+fn irreducible_cycle_gets_a_widening_point() {
+    let program = parse(
+        r#"
+    fn main() -> int {
+    let c:int
+    entry:
+      c = $copy 1
+      $branch c a b
+
+    a:
+      $branch c b exit
+
+    b:
+      $branch c a exit
+
+    exit:
+      $ret 0
+    }
+    "#,
+    );
+    let f = &program.0.functions[&func_id("main")];
+    let cfg = Cfg::new(f, program.0.globals.clone(), program.0.structs.clone());
+
+    assert!(cfg.loops.loops.is_empty());
+    assert_eq!(cfg.loop_headers.len(), 1);
+}
+
+const CALLS: &str = r#"
+    f:&(int) -> int
+    g:&(int) -> int
+    h:&() -> int
+    @fp:&(int) -> int
+
+    extern print:(int) -> _
+
+    fn f(n:int) -> int {
+    let c:int, m:int, r:int
+    entry:
+      c = $cmp gt n 0
+      $branch c more done
+    more:
+      m = $arith sub n 1
+      r = $call_dir g(m) then done
+    done:
+      $ret r
+    }
+
+    fn g(n:int) -> int {
+    let r:int
+    entry:
+      r = $call_dir f(n) then exit
+    exit:
+      $call_ext print(r)
+      $ret r
+    }
+
+    fn h() -> int {
+    entry:
+      $ret 0
+    }
+
+    fn main() -> int {
+    let p:&(int) -> int, x:int, y:int, z:int
+    entry:
+      p = $copy g
+      x = $call_idr p(1) then bb1
+    bb1:
+      y = $call_idr @fp(x) then bb2
+    bb2:
+      z = $call_idr h() then exit
+    exit:
+      $ret z
+    }
+"#;
+
+#[test]
+fn call_graph_resolves_indirect_calls_by_type() {
+    use call_graph::*;
+
+    let program = parse(CALLS).0;
+    let graph = CallGraph::new(&program);
+    let funcs = |fs: &[&str]| fs.iter().map(|f| func_id(f)).collect::<Set<_>>();
+
+    // only g has its address taken, and h is called by name.
+    assert_eq!(graph.call_sites(&func_id("main"))[&bb_id("entry")].callees, funcs(&["g"]));
+    assert_eq!(graph.call_sites(&func_id("main"))[&bb_id("bb1")].callees, funcs(&["g"]));
+    assert_eq!(graph.call_sites(&func_id("main"))[&bb_id("bb2")].callees, funcs(&["h"]));
+    assert!(graph.call_sites(&func_id("main")).values().all(|site| site.indirect));
+    assert_eq!(graph.callers(&func_id("f")), [&func_id("g")].into());
+    assert_eq!(graph.external_callees(&func_id("g")), [&func_id("print")].into());
+    assert!(graph.is_leaf(&func_id("h")));
+
+    // f and g are mutually recursive, and come before their caller.
+    assert_eq!(graph.recursive_functions(), [&func_id("f"), &func_id("g")].into());
+    let sccs = graph.bottom_up();
+    assert_eq!(sccs.len(), 3);
+    assert_eq!(sccs.last(), Some(&funcs(&["main"])));
+    assert!(sccs.contains(&funcs(&["f", "g"])));
+
+    let dot = graph.to_dot();
+    assert!(dot.contains(r#""main" -> "g" [color=black style=dashed];"#));
+    assert!(dot.contains(r#""f" -> "g" [color=black];"#));
+    assert!(dot.contains(r#""print" [shape=ellipse];"#));
+}
+
+#[test]
+fn call_graph_resolves_indirect_calls_with_points_to() {
+    use call_graph::*;
+
+    let program = parse(CALLS).0;
+    let pts_to = Map::from([("main.p".to_string(), Set::from(["g".to_string()]))]);
+    let graph = CallGraph::with_points_to(&program, &pts_to);
+
+    // nothing is known about @fp.
+    let sites = graph.call_sites(&func_id("main"));
+    assert_eq!(sites[&bb_id("entry")].callees, [func_id("g")].into());
+    assert!(sites[&bb_id("bb1")].callees.is_empty());
+    assert_eq!(sites[&bb_id("bb2")].callees, [func_id("h")].into());
+}
+
+const COUNTING: &str = r#"
+    fn main() -> int {
+    let i:int, c:int, p:&int, q:&int, r:&int
+    entry:
+      i = $copy 0
+      p = $alloc 1 [_a1]
+      q = $alloc 1 [_a2]
+      $jump loop
+    loop:
+      c = $cmp lt i 3
+      $branch c body exit
+    body:
+      i = $arith add i 1
+      r = $copy p
+      $jump loop
+    exit:
+      r = $copy q
+      $ret i
+    }
+"#;
+
+#[test]
+fn soundness_check_passes_sound_results() {
+    use soundness::*;
+
+    let program = parse(COUNTING);
+    assert_eq!(check(&program, &Intervals::new(&program)), vec![]);
+    assert_eq!(check(&program, &Constants::new(&program)), vec![]);
+    assert_eq!(check(&program, &PointsTo::new(&program)), vec![]);
+}
+
+// The analyses run from the locals the interpreter zero-initializes, so the
+// programs that read a local before assigning it have no violations either.
+#[test]
+fn soundness_check_passes_simple_inputs() {
+    use soundness::*;
+
+    let mut paths: Vec<_> = std::fs::read_dir("test-inputs/simple")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lir"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty());
+
+    for path in paths {
+        let program = parse(&std::fs::read_to_string(&path).unwrap());
+        let show = |violations: Vec<Violation>| violations.iter().map(Violation::to_string).collect::<Vec<_>>();
+
+        assert_eq!(show(check(&program, &Intervals::new(&program))), Vec::<String>::new(), "{}", path.display());
+        assert_eq!(show(check(&program, &Constants::new(&program))), Vec::<String>::new(), "{}", path.display());
+        assert_eq!(show(check(&program, &PointsTo::new(&program))), Vec::<String>::new(), "{}", path.display());
+    }
+}
+
+#[test]
+fn soundness_check_reports_first_witness() {
+    use crate::interpreter::Observed;
+    use soundness::*;
+
+    // claims that every int is 0.
+    struct Zero;
+    impl Facts for Zero {
+        fn violated(&self, _: &FuncId, _: &InstId, _: &VarId, witness: &Observed) -> Option<String> {
+            (*witness != Observed::Int(0)).then(|| "{0}".to_string())
+        }
+    }
+
+    let program = parse(COUNTING);
+    let violations = check(&program, &Zero);
+    let at = |bb: &str, i: usize| {
+        violations
+            .iter()
+            .filter(|v| v.point == (bb_id(bb), i) && v.var.name() == "i")
+            .map(|v| v.witness.clone())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(at("loop", 0), vec![Observed::Int(1)]);
+    assert_eq!(at("exit", 1), vec![Observed::Int(3)]);
+    assert_eq!(at("entry", 1), vec![]);
+    assert_eq!(
+        violations[0].to_string(),
+        "main: before body.0: c = 1 is not in {0}"
+    );
+
+    // r is seen pointing into both allocations, but may only point to one.
+    let pts_to = PointsTo(Map::from([
+        ("main.p".to_string(), Set::from(["_a1".to_string()])),
+        ("main.q".to_string(), Set::from(["_a2".to_string()])),
+        ("main.r".to_string(), Set::from(["_a1".to_string()])),
+    ]));
+    let violations = check(&program, &pts_to);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].to_string(), "main: before exit.1: r = &_a2 is not in {_a1}");
+}
+
+#[test]
+fn constraint_solve_matches_solutions() {
+    use crate::middle_end::constraints::constraint_solve::solve;
+
+    for name in ["no_proj", "proj1", "proj2", "proj3", "proj4", "lam", "lam2", "lam3"] {
+        let read = |ext: &str| std::fs::read_to_string(format!("test-inputs-03/solve/{name}.lir.{ext}")).unwrap();
+        assert_eq!(solve(&read("constraints")).trim_end(), read("solution").trim_end(), "{name}");
+    }
+}
+
+#[test]
+fn constraint_solve_agrees_with_reference() {
+    use crate::middle_end::analysis_constraints::{constraints_gen, Fields};
+    use crate::middle_end::constraints::{constraint_solve::*, Constraint};
+
+    for path in test_inputs() {
+        let program = parse(&std::fs::read_to_string(&path).unwrap());
+        for fields in [Fields::Merged, Fields::Separate] {
+            let constraints: Set<Constraint> = program
+                .0
+                .functions
+                .keys()
+                .flat_map(|f| constraints_gen::analyze_with(&program, f.clone(), fields))
+                .collect();
+
+            assert_eq!(
+                solve_constraints(&constraints),
+                reference::solve_constraints(&constraints),
+                "{} with {fields:?} fields",
+                path.display()
+            );
+        }
+    }
+}
+
+// The LIR programs in test-inputs.
+fn test_inputs() -> Vec<std::path::PathBuf> {
+    let mut files = vec![];
+    for dir in ["test-inputs/simple", "test-inputs/complex"] {
+        for file in std::fs::read_dir(dir).unwrap() {
+            let path = file.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "lir") {
+                files.push(path);
+            }
+        }
+    }
+    files
+}
+
+#[test]
+fn constraint_solve_merges_cycles() {
+    use crate::middle_end::constraints::constraint_solve::solve;
+
+    let constraints = "\
+main.p <= main.q
+main.q <= main.r
+main.r <= main.p
+proj(ref,1,main.r) <= main.s
+ref(_alloc1,_alloc1) <= main.p
+ref(main.x,main.x) <= _alloc1
+";
+    let solution = "\
+_alloc1 -> {main.x}
+main.p -> {_alloc1}
+main.q -> {_alloc1}
+main.r -> {_alloc1}
+main.s -> {main.x}
+";
+    assert_eq!(solve(constraints).trim_end(), solution.trim_end());
+}
+
+#[test]
+fn constraint_solve_offsets_fields() {
+    use crate::middle_end::constraints::constraint_solve::solve;
+
+    let constraints = "\
+field(main.p,left) <= main.l
+main.q <= main.p
+ref(_alloc1,_alloc1) <= main.p
+ref(_alloc2,_alloc2) <= main.q
+";
+    let solution = "\
+main.l -> {_alloc1.left, _alloc2.left}
+main.p -> {_alloc1, _alloc2}
+main.q -> {_alloc2}
+";
+    assert_eq!(solve(constraints).trim_end(), solution.trim_end());
+}
+
+const TREE: &str = r#"
+    struct node {
+      left:&node
+      right:&node
+    }
+
+    fn main() -> int {
+    let l:&&node, r:&&node, n:&node, t:&node, u:&node
+    entry:
+      t = $alloc 1 [_alloc1]
+      u = $alloc 1 [_alloc2]
+      l = $gfp t left
+      $store l u
+      r = $gfp t right
+      $store r t
+      n = $load l
+      $ret 0
+    }
+"#;
+
+#[test]
+fn points_to_separates_fields() {
+    use crate::middle_end::analysis_constraints::*;
+
+    let program = parse(TREE);
+    let locs = |locs: &[&str]| locs.iter().map(|loc| loc.to_string()).collect::<Set<String>>();
+
+    let merged = points_to_with(&program, Fields::Merged);
+    assert_eq!(merged["main.l"], locs(&["_alloc1"]));
+    assert_eq!(merged["main.n"], locs(&["_alloc1", "_alloc2"]));
+
+    let separate = points_to_with(&program, Fields::Separate);
+    assert_eq!(separate["main.l"], locs(&["_alloc1.left"]));
+    assert_eq!(separate["main.r"], locs(&["_alloc1.right"]));
+    assert_eq!(separate["_alloc1.left"], locs(&["_alloc2"]));
+    assert_eq!(separate["main.n"], locs(&["_alloc2"]));
+}
+
+#[test]
+fn points_to_fields_refine_merged_fields() {
+    use crate::middle_end::analysis_constraints::*;
+
+    for path in test_inputs() {
+        let program = parse(&std::fs::read_to_string(&path).unwrap());
+        let merged = points_to_with(&program, Fields::Merged);
+        let separate = points_to_with(&program, Fields::Separate);
+
+        // every field location is a field of a location of the merged solution.
+        for (x, locs) in separate.iter().filter(|(x, _)| merged.contains_key(*x)) {
+            for loc in locs {
+                assert!(
+                    merged[x].iter().any(|base| loc == base || loc.starts_with(&format!("{base}."))),
+                    "{}: {x} -> {loc} is not in {:?}",
+                    path.display(),
+                    merged[x]
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn steensgaard_unifies_what_is_copied() {
+    use crate::middle_end::constraints::{constraint_solve::steensgaard, Constraints};
+    use crate::middle_end::slice::slice_solve::parse_pts_to;
+
+    let constraints = "\
+main.p <= main.r
+main.q <= main.r
+ref(main.a,main.a) <= main.p
+ref(main.b,main.b) <= main.q
+";
+    // unlike the inclusion-based solution, p and q point to b and a too.
+    let solution = "\
+main.p -> {main.a, main.b}
+main.q -> {main.a, main.b}
+main.r -> {main.a, main.b}
+";
+    let output = steensgaard::solve(constraints);
+    assert_eq!(output.trim_end(), solution.trim_end());
+
+    // the output reads back like that of the inclusion-based solver.
+    let constraints = constraints.parse::<Constraints>().unwrap();
+    assert_eq!(parse_pts_to(&output), steensgaard::solve_constraints(&constraints.0));
+}
+
+#[test]
+fn steensgaard_contains_inclusion_based_solution() {
+    use crate::middle_end::analysis_constraints::*;
+
+    for path in test_inputs() {
+        let program = parse(&std::fs::read_to_string(&path).unwrap());
+        let unified = points_to_steensgaard(&program);
+
+        for (x, locs) in points_to(&program) {
+            assert!(
+                unified.get(&x).is_some_and(|unified| unified.is_superset(&locs)),
+                "{}: {x} -> {locs:?} is not in {:?}",
+                path.display(),
+                unified.get(&x)
+            );
+        }
+    }
+}
+
+const STORES: &str = r#"
+    fn main() -> int {
+    let a:&int, b:&int, c:&int, h:&&int, p:&&int, r:&int, s:&int
+    entry:
+      a = $alloc 1 [_a1]
+      b = $alloc 1 [_a2]
+      p = $addrof c
+      $store p a
+      $store p b
+      r = $load p
+      h = $alloc 1 [_a3]
+      $store h a
+      $store h b
+      s = $load h
+      $ret 0
+    }
+"#;
+
+#[test]
+fn flow_points_to_overwrites_single_cells() {
+    use crate::middle_end::analysis_constraints::points_to;
+    use points_to::Value;
+
+    let program = parse(STORES);
+    let locs = |locs: &[&str]| Value(locs.iter().map(|loc| loc.to_string()).collect());
+    let pts_to = points_to(&program);
+    let solution = points_to::analyze(&program, func_id("main"), &pts_to);
+    let before_ret = &solution.states[&(bb_id("entry"), 10)];
+
+    // c is a single cell, but an allocation may be many.
+    assert_eq!(before_ret.get(&"main.c".to_string()), locs(&["_a2"]));
+    assert_eq!(before_ret.get(&"main.r".to_string()), locs(&["_a2"]));
+    assert_eq!(before_ret.get(&"_a3".to_string()), locs(&["_a1", "_a2"]));
+    assert_eq!(before_ret.get(&"main.s".to_string()), locs(&["_a1", "_a2"]));
+    assert_eq!(pts_to["main.r"], locs(&["_a1", "_a2"]).0);
+}
+
+#[test]
+fn flow_points_to_refines_points_to() {
+    use crate::middle_end::analysis_constraints::points_to;
+
+    for path in test_inputs() {
+        let program = parse(&std::fs::read_to_string(&path).unwrap());
+        let pts_to = points_to(&program);
+
+        for f in program.0.functions.keys() {
+            let solution = points_to::analyze(&program, f.clone(), &pts_to);
+            for (point, state) in &solution.states {
+                for (x, locs) in &state.values {
+                    assert!(
+                        pts_to.get(x).is_some_and(|all| all.is_superset(&locs.0)) || locs.0.is_empty(),
+                        "{}: before {point:?}: {x} -> {locs} is not in {:?}",
+                        path.display(),
+                        pts_to.get(x)
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn reaching_defs_ptrs_kills_overwritten_cells() {
+    use crate::middle_end::analysis_constraints::points_to;
+    use crate::middle_end::analysis_rdef_ptrs::reaching_defs_ptrs;
+
+    let program = parse(STORES);
+    let main = func_id("main");
+    let pts_to = points_to(&program);
+    let at = |i| ProgramPoint::from(bb_id("entry"), Some(i));
+    let defs = |pps: &[usize]| pps.iter().map(|&i| at(i)).collect::<Set<_>>();
+
+    // the first store to c reaches the load only if the second may miss it.
+    let flat = reaching_defs_ptrs::analyze(&program, main.clone(), pts_to.clone());
+    assert_eq!(flat[&at(5)], defs(&[2, 3, 4]));
+    assert_eq!(flat[&at(9)], defs(&[6, 7, 8]));
+
+    let flow = reaching_defs_ptrs::analyze(&program, main.clone(), points_to::analyze(&program, main, &pts_to));
+    assert_eq!(flow[&at(5)], defs(&[2, 4]));
+    assert_eq!(flow[&at(9)], defs(&[6, 7, 8]));
+}
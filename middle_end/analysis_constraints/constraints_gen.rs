@@ -1,4 +1,4 @@
-//! Intraprocedural reaching definitions analysis.
+//! Intraprocedural generation of points-to constraints.
 
 use crate::commons::Valid;
 
@@ -14,8 +14,7 @@ pub struct Value(pub Set<ProgramPoint>);
 impl Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{{")?;
-        let len = self.0.len();
-        for (bb, pp) in self.0.iter().enumerate() {
+        for pp in &self.0 {
             let (bb, n) = match pp {
                 ProgramPoint::Instruction { bb, i } => (bb, i.to_string()),
                 ProgramPoint::Terminal { bb } => (bb, "term".to_owned()),
@@ -34,8 +33,25 @@ impl Display for Value {
 // Abstract environment
 pub type Env = PointwiseEnv<Value>;
 
+/// State shared by every block while the constraints are generated.
+pub struct Context<'a> {
+    pub program: &'a Program,
+    /// The operand returned by each function, if any.
+    pub func_rets: Map<FuncId, Option<Operand>>,
+    /// The constraints generated so far.
+    pub soln: Set<Constraint>,
+    /// Whether a field pointer points to the struct or to the field.
+    pub fields: Fields,
+}
+
 // Performs the analysis: use `forward_analysis` to implement this.
 pub fn analyze(program: &Valid<Program>, func: FuncId) -> Set<Constraint> {
+    analyze_with(program, func, Fields::Merged)
+}
+
+/// The constraints of a function, with `field` constraints for the field
+/// pointers if the fields are told apart.
+pub fn analyze_with(program: &Valid<Program>, func: FuncId, fields: Fields) -> Set<Constraint> {
     fn get_func_rets(functions: &Map<FuncId, Function>) -> Map<FuncId, Option<Operand>> {
         let mut func_rets: Map<FuncId, Option<Operand>> = Map::new();
         for (fid, f) in functions {
@@ -58,8 +74,11 @@ pub fn analyze(program: &Valid<Program>, func: FuncId) -> Set<Constraint> {
 
     let func_rets = get_func_rets(&program.functions);
     for global in &program.globals {
+        // globals holding a function pointer are not functions themselves.
         if global.typ().base_typ().is_function() {
-            let function = &program.functions[&func_id(&global.0.name)];
+            let Some(function) = program.functions.get(&func_id(&global.0.name)) else {
+                continue;
+            };
 
             soln.insert(Constraint(
                 ConstraintExp::Lam {
@@ -73,7 +92,11 @@ pub fn analyze(program: &Valid<Program>, func: FuncId) -> Set<Constraint> {
             ));
         }
     }
-    forward_analysis(f, &Cfg::new(f, program.globals.clone(), program.structs.clone(), program, func_rets), &init_store, &init_store, &mut soln)
+
+    let cfg = Cfg::new(f, program.globals.clone(), program.structs.clone());
+    let mut ctx = Context { program, func_rets, soln, fields };
+    forward_analysis_with(f, &cfg, &init_store, &init_store, &mut ctx);
+    ctx.soln
 }
 
 // SECTION: analysis implementation
@@ -92,50 +115,15 @@ impl AbstractValue for Value {
     }
 }
 
-fn join_sets(set1: Set<ProgramPoint>, set2: Set<ProgramPoint>) -> Set<ProgramPoint> {
-    set1.union(&set2).cloned().collect()
-}
-
-fn get_vars(opset: Vec<&Operand>) -> Set<VarId> {
-    let mut return_set: Set<VarId> = Set::new();
-    for op in opset {
-        if let Operand::Var(v) = op.clone() {
-            return_set.insert(v);
-        }
+impl<'a> AbstractEnv<Context<'a>> for Env {
+    fn join_with(&mut self, rhs: &Self, _block: &BbId, _join_type: i64) -> bool {
+        self.pointwise_join(rhs)
     }
 
-    return_set
-}
-
-impl AbstractEnv for Env {
-    fn join_with(&mut self, rhs: &Self, _block: &BbId, join_type: i64) -> bool {
-        let mut changed = false;
-
-        for (x, lhs) in self.values.iter_mut() {
-            if let Some(rhs) = rhs.values.get(x) {
-                let old = lhs.clone();
-                *lhs = lhs.join(rhs);
-
-                changed = changed || *lhs != old;
-            }
-        }
-
-        for (x, rhs) in &rhs.values {
-            let old = self.get(x);
-            let lhs = old.join(rhs);
-            self.insert(x, &lhs);
-
-            changed = changed || lhs != old;
-        }
-
-        changed
-    }
-
-    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg, soln: &mut Set<Constraint>, store: &mut Map<VarId, Set<InstId>>) {
+    fn analyze_inst(&mut self, inst: &Instruction, _cfg: &Cfg, ctx: &mut Context<'a>) {
+        let soln = &mut ctx.soln;
         use Instruction::*;
 
-        let this_inst = &self.curr_inst.clone().unwrap();
-        let this_pp = ProgramPoint::from_instid(this_inst.clone());
         match inst {
             AddrOf { lhs, op } => {
                 if lhs.typ().is_ptr() {
@@ -145,7 +133,7 @@ impl AbstractEnv for Env {
                     ));
                 }
             },
-            Alloc { lhs, num, id } => {
+            Alloc { lhs, num: _, id } => {
                 if lhs.typ().is_ptr() {
                     soln.insert(Constraint(
                         ConstraintExp::Ref(id.clone(), id.clone()),
@@ -153,13 +141,13 @@ impl AbstractEnv for Env {
                     ));
                 }
             },
-            Arith { lhs, aop:_, op1, op2 } => {
+            Arith { .. } => {
                 // Can be ignored, does not affect pointers.
             },
-            Cmp { lhs, rop:_, op1, op2 } => {
+            Cmp { .. } => {
                 // Can be ignored, does not affect pointers.
             },
-            CallExt { lhs, ext_callee, args } => {
+            CallExt { .. } => {
                 // IGNORE
             },
             Copy { lhs, op } => {
@@ -176,7 +164,7 @@ impl AbstractEnv for Env {
             Gep {
                 lhs,
                 src,
-                idx,
+                idx: _,
             } => {
                 if lhs.typ().is_ptr() {
                     soln.insert(Constraint(
@@ -187,12 +175,13 @@ impl AbstractEnv for Env {
             },
             Gfp { lhs, src, field } => {
                 if lhs.typ().is_ptr() {
-                    soln.insert(Constraint(
-                        ConstraintExp::Var(src.clone()),
-                        ConstraintExp::Var(lhs.clone())
-                    ));
+                    let src = match ctx.fields {
+                        Fields::Merged => ConstraintExp::Var(src.clone()),
+                        Fields::Separate => ConstraintExp::Field(src.clone(), field.name.to_string()),
+                    };
+                    soln.insert(Constraint(src, ConstraintExp::Var(lhs.clone())));
                 }
-            }, 
+            },
             Load { lhs, src } => {
                 if lhs.typ().is_ptr() {
                     soln.insert(Constraint(
@@ -211,20 +200,30 @@ impl AbstractEnv for Env {
                     }
                 };
             },
-            Phi { .. } => unreachable!(),
+            // a copy from each argument.
+            Phi { lhs, args } => {
+                if lhs.typ().is_ptr() {
+                    for var in args.iter().filter_map(Operand::as_var) {
+                        soln.insert(Constraint(
+                            ConstraintExp::Var(var.clone()),
+                            ConstraintExp::Var(lhs.clone())
+                        ));
+                    }
+                }
+            },
         };
     }
 
-    fn analyze_term(&mut self, term: &Terminal, cfg: &Cfg, soln: &mut Set<Constraint>, store: &mut Map<VarId, Set<InstId>>) -> Set<BbId> {
+    fn analyze_term(&mut self, term: &Terminal, _cfg: &Cfg, ctx: &mut Context<'a>) -> Set<BbId> {
+        let soln = &mut ctx.soln;
         use Terminal::*;
-        let this_pp = ProgramPoint::from(self.curr_inst.clone().unwrap().0, None);
         match term {
-            CallDirect { lhs, callee, args, next_bb } => {
+            CallDirect { lhs, callee, args, next_bb: _ } => {
 
                 // function return is subset of lhs
                 if let Some(lhs_var) = lhs {
                     if lhs_var.typ().is_ptr() {
-                        if let Some(Operand::Var(ret_var)) = &cfg.func_rets[callee] {
+                        if let Some(Operand::Var(ret_var)) = &ctx.func_rets[callee] {
                             soln.insert(Constraint(
                                 ConstraintExp::Var(ret_var.clone()),
                                 ConstraintExp::Var(lhs_var.clone())
@@ -236,7 +235,7 @@ impl AbstractEnv for Env {
                 }
 
                 // each arg is subset of function param
-                let callee_args = &cfg.program.functions[callee].params;
+                let callee_args = &ctx.program.functions[callee].params;
                 for (param, arg) in callee_args.iter().zip(args) {
                     if param.typ().is_ptr() {
                         if let Operand::Var(arg_var) = arg {
@@ -248,7 +247,7 @@ impl AbstractEnv for Env {
                     }
                 }
             },
-            CallIndirect { lhs, callee, args, next_bb } => {
+            CallIndirect { lhs, callee, args, next_bb: _ } => {
                 if let LirType::Function{ ret_ty, param_ty} = callee.typ().base_typ().0.get().clone() {
                     soln.insert(Constraint(
                         ConstraintExp::Var(callee.clone()),
@@ -260,7 +259,7 @@ impl AbstractEnv for Env {
                                 if let Some(ret_var) = lhs {
                                     Some(Operand::Var(ret_var.clone()))
                                 } else {
-                                    Some(Operand::Var(var_id("_DUMMY", ret_ty.clone().unwrap(), None)))
+                                    ret_ty.clone().map(|ret_ty| Operand::Var(var_id("_DUMMY", ret_ty, None)))
                                 }
                             },
                             args: args.iter().filter_map(|x| if let Operand::Var(arg_var) = x {
@@ -274,10 +273,10 @@ impl AbstractEnv for Env {
 
                 
             },
-            Branch { cond, .. } => {
+            Branch { .. } => {
                 // Can be ignored, flow-insensitive.
             },
-            Ret(Some(op)) => {
+            Ret(Some(_)) => {
                 // IGNORE
             },
             _ => (), // IGNORE
@@ -286,18 +285,18 @@ impl AbstractEnv for Env {
         Set::new() // relic from flow analysis
     }
 
-    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg, soln: &mut Set<Constraint>, store: &mut Map<VarId, Set<InstId>>) -> (Vec<Self>, Set<BbId>) {
+    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg, ctx: &mut Context<'a>) -> (Vec<Self>, Set<BbId>) {
         let mut v = vec![];
         let mut s = self.clone();
 
         for (i, inst) in bb.insts.iter().enumerate() {
             s.curr_inst = Some((bb.id.clone(), i));
-            s.analyze_inst(inst, cfg, soln, store);
+            s.analyze_inst(inst, cfg, ctx);
             v.push(s.clone());
         }
 
         s.curr_inst = Some((bb.id.clone(), bb.insts.len()));
-        s.analyze_term(&bb.term, cfg, soln, store);
+        s.analyze_term(&bb.term, cfg, ctx);
         v.push(s.clone());
         (v, Set::new())
     }
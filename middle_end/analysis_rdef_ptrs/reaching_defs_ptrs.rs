@@ -1,132 +1,105 @@
 //! Intraprocedural reaching definitions analysis.
 
 use crate::commons::Valid;
+use crate::middle_end::analysis::points_to;
 
 use super::*;
 
 // SECTION: analysis interface
 
-// The powerset lattice.  It represents the definitions
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct Value(pub Set<ProgramPoint>);
-
-impl Display for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{{")?;
-        let len = self.0.len();
-        for (bb, pp) in self.0.iter().enumerate() {
-            let (bb, n) = match pp {
-                ProgramPoint::Instruction { bb, i } => (bb, i.to_string()),
-                ProgramPoint::Terminal { bb } => (bb, "term".to_owned()),
-            };
-
-            if n == "term" {
-                write!(f, "{bb}.{n}")?;
-            } else {
-                write!(f, "{bb}.{n}, ")?;
-            }
-        }
-        write!(f, "}}")
+/// What the pointers of a function point to, by scoped names.
+pub trait PtsTo {
+    /// The locations `x` may point to before `point`.
+    fn pts_to(&self, point: &InstId, x: &VarId) -> Option<&Set<String>>;
+
+    /// The location a store through `x` at `point` surely overwrites, if any.
+    fn must_pts_to(&self, _point: &InstId, _x: &VarId) -> Option<&String> {
+        None
     }
 }
 
-// Abstract environment
-pub type Env = PointwiseEnv<Value>;
-
-// Performs the analysis: use `forward_analysis` to implement this.
-pub fn analyze(program: &Valid<Program>, func: FuncId, pts_to: Map<String, Set<String>>) -> (Map<ProgramPoint, Set<ProgramPoint>>) {
-    let program = &program.0;
-    let f = &program.functions[&func];
-
-    let init_store = Env::new(Map::new());
-    let mut soln: Map<ProgramPoint, Set<ProgramPoint>> = Map::new();
-    forward_analysis(f, &Cfg::new(f, program.globals.clone(), program.structs.clone(), pts_to), &init_store, &init_store, &mut soln)
+/// A flow-insensitive solution, which holds at every point.  A store through a
+/// pointer never overwrites a location.
+impl PtsTo for Map<String, Set<String>> {
+    fn pts_to(&self, _point: &InstId, x: &VarId) -> Option<&Set<String>> {
+        self.get(&x.to_scope_string())
+    }
 }
 
-// SECTION: analysis implementation
-
-impl AbstractValue for Value {
-    type Concrete = ProgramPoint;
-
-    const BOTTOM: Self = Value(Set::new());
-
-    fn alpha(def: ProgramPoint) -> Self {
-        Value(Set::from([def]))
+impl PtsTo for points_to::Solution {
+    fn pts_to(&self, point: &InstId, x: &VarId) -> Option<&Set<String>> {
+        self.get(point, x)
     }
 
-    fn join(&self, rhs: &Self) -> Value {
-        Value(self.0.union(&rhs.0).cloned().collect())
+    fn must_pts_to(&self, point: &InstId, x: &VarId) -> Option<&String> {
+        self.must(point, x)
     }
 }
 
-fn join_sets(set1: Set<ProgramPoint>, set2: Set<ProgramPoint>) -> Set<ProgramPoint> {
-    set1.union(&set2).cloned().collect()
+/// State shared by every block while the analysis runs.
+pub struct Context<P> {
+    /// The address-taken variables grouped by type, see [addr_taken_by_type].
+    pub addr_taken: Map<Type, Set<VarId>>,
+    /// The address-taken variables and globals by scoped name.  The other
+    /// locations are not variables, and stand for a dummy one.
+    pub locs: Map<String, VarId>,
+    /// The points-to solution.
+    pub pts_to: P,
+    /// The definitions reaching each program point.
+    pub soln: Map<ProgramPoint, Set<ProgramPoint>>,
 }
 
-fn get_vars(opset: Vec<&Operand>) -> Set<VarId> {
-    let mut return_set: Set<VarId> = Set::new();
-    for op in opset {
-        if let Operand::Var(v) = op.clone() {
-            return_set.insert(v);
-        }
+impl<P: PtsTo> Context<P> {
+    // the variables for the locations `x` may point to before `point`.
+    fn locations(&self, point: &InstId, x: &VarId) -> Set<VarId> {
+        self.pts_to.pts_to(point, x).into_iter().flatten().map(|loc| self.location(loc)).collect()
     }
 
-    return_set
+    fn location(&self, loc: &str) -> VarId {
+        self.locs.get(loc).cloned().unwrap_or_else(|| var_id_dummy_from_str(loc))
+    }
 }
 
-impl AbstractEnv for Env {
-    fn join_with(&mut self, rhs: &Self, _block: &BbId, join_type: i64) -> bool {
-        let mut changed = false;
-
-        for (x, lhs) in self.values.iter_mut() {
-            if let Some(rhs) = rhs.values.get(x) {
-                let old = lhs.clone();
-                *lhs = lhs.join(rhs);
+// Performs the analysis: use `forward_analysis` to implement this.
+//
+// `pts_to` is either a flow-insensitive solution keyed by scoped variable
+// names, or the [points_to::Solution] of `func`.
+pub fn analyze<P: PtsTo>(program: &Valid<Program>, func: FuncId, pts_to: P) -> Map<ProgramPoint, Set<ProgramPoint>> {
+    let program = &program.0;
+    let f = &program.functions[&func];
+    let cfg = Cfg::new(f, program.globals.clone(), program.structs.clone());
 
-                changed = changed || *lhs != old;
-            }
-        }
+    let mut ctx = Context {
+        addr_taken: addr_taken_by_type(&cfg, f),
+        locs: cfg.addr_taken.iter().chain(&cfg.globals).map(|x| (x.to_scope_string(), x.clone())).collect(),
+        pts_to,
+        soln: Map::new(),
+    };
 
-        for (x, rhs) in &rhs.values {
-            let old = self.get(x);
-            let lhs = old.join(rhs);
-            self.insert(x, &lhs);
+    let init_store = Env::new(Map::new());
+    forward_analysis_with(f, &cfg, &init_store, &init_store, &mut ctx);
+    ctx.soln
+}
 
-            changed = changed || lhs != old;
-        }
+// SECTION: analysis implementation
 
-        changed
+impl<P: PtsTo> AbstractEnv<Context<P>> for Env {
+    fn join_with(&mut self, rhs: &Self, _block: &BbId, _join_type: i64) -> bool {
+        self.pointwise_join(rhs)
     }
 
-    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg, soln: &mut Map<ProgramPoint, Set<ProgramPoint>>, store: &mut Map<VarId, Set<InstId>>) {
-
-        /*
-            ReachViaArgs = ReachableTypes(type(<arg1>)) ∪ . . . ∪ ReachableTypes(type(<argN>))
-            ReachViaGlobals = ReachableTypes(type(<global1>)) ∪ . . .
-            WDEF = ⋃ {addr_taken[τ] | τ ∈ ReachViaArgs ∪ReachViaGlobals} ∪ Globals
-        */
-        fn calculate_call_wdef(cfg: &Cfg, ops: Set<VarId>) -> Set<VarId> {
-            ops.iter()
-                .map(|x| cfg.reachable_types(&x.typ()))
-                .chain(cfg.globals.iter().map(|x| cfg.reachable_types(&x.typ())))
-                .fold(Set::new(), |acc, x| acc.union(&x).cloned().collect()).iter()
-                .filter_map(|a| cfg.addr_taken.get(a))
-                .flatten()
-                .chain(cfg.globals.iter())
-                .cloned()
-                .collect()
-        }
+    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg, ctx: &mut Context<P>) {
+        use Instruction::*;
 
         let this_inst = &self.curr_inst.clone().unwrap();
-        
-        use Instruction::*;
 
         let mut used_vars: Set<VarId> = Set::new();
         let mut wdef: Option<Set<VarId>> = None;
         let this_pp = ProgramPoint::from_instid(this_inst.clone());
+        let stored;
         let def = match inst {
             AddrOf { lhs, op: _ } => Some(lhs),
-            Alloc { lhs, num, id } => {
+            Alloc { lhs, num, id: _ } => {
                 used_vars = get_vars(vec![num]);
                 Some(lhs)
             },
@@ -138,14 +111,14 @@ impl AbstractEnv for Env {
                 used_vars = get_vars(vec![op1, op2]);
                 Some(lhs)
             },
-            CallExt { lhs, ext_callee, args } => {
-                let call_wdef = calculate_call_wdef(cfg, get_vars(args.iter().collect()));
+            CallExt { lhs, ext_callee: _, args } => {
+                let call_wdef = calculate_call_wdef(cfg, &ctx.addr_taken, get_vars(args.iter().collect()));
                 // {<arg>|<arg> is a variable}
                 used_vars.extend(get_vars(args.iter().collect()).iter().cloned());
                 // CALL_WDEF
                 used_vars.extend(call_wdef.iter().cloned());
 
-                wdef = Some(call_wdef.clone());
+                wdef = Some(call_wdef);
 
                 lhs.as_ref()
             },
@@ -162,140 +135,59 @@ impl AbstractEnv for Env {
                 used_vars.insert(src.clone());
                 Some(lhs)
             },
-            Gfp { lhs, src, field } => {
+            Gfp { lhs, src, field: _ } => {
                 used_vars.insert(src.clone());
                 Some(lhs)
-            }, 
+            },
             Load { lhs, src } => {
-                let src_name = src.to_scope_string();
-
-                if let Some(pts_to_set) = cfg.pts_to.get(&src_name) {
-                    for pts_to_var in pts_to_set {
-                        used_vars.insert(var_id_dummy_from_str(pts_to_var));
-                    }
-                }
-                /* 
-                if this_pp == ProgramPoint::from(bb_id("bb10"), Some(2)) {
-                    println!("========================");
-                    dbg!(&used_vars);
-                    for used_var in &used_vars {
-                        dbg!(self.get(used_var));
-                    }
-                }
-                */
+                // USE = {src} ∪ the locations it may point to
+                used_vars = ctx.locations(this_inst, src);
+                used_vars.insert(src.clone());
                 Some(lhs)
             },
             Store { dst, op } => {
-                if let Operand::Var(var) = op {
-                    if let Some(pts_to_set) = cfg.pts_to.get(var.name()) {
-                        for pts_to_var in pts_to_set {
-                            used_vars.insert(var_id_dummy_from_str(pts_to_var));
-                        }
-                    }
+                // USE = {dst} ∪ {<op> | <op> is a variable}
+                used_vars = get_vars(vec![op]);
+                used_vars.insert(dst.clone());
+                // DEF = the location dst must point to, or else
+                // WDEF = the locations it may point to
+                match ctx.pts_to.must_pts_to(this_inst, dst) {
+                    Some(loc) => {
+                        stored = ctx.location(loc);
+                        Some(&stored)
+                    },
+                    None => {
+                        wdef = Some(ctx.locations(this_inst, dst));
+                        None
+                    },
                 }
-                Some(dst)
             },
             Phi { .. } => unreachable!(),
         };
 
-        // ppvalue
-        let pp_value = &Value(Set::from([this_pp.clone()]));
-        // ∀v ∈ USE, soln[pp] ← soln[pp] ∪ σ[v]
-        soln.entry(this_pp.clone())
-            .or_default()
-            .extend(
-                used_vars.iter()
-                    .map(|used| self.get(used))
-                    .map(|Value(set)| set)
-                    .fold(Set::new(), |acc, set| acc.union(&set).cloned().collect())
-            );
-
-        // σ[x] ← {pp}
-        if let Some(lhs) = def {
-            self.insert(
-                lhs,
-                pp_value,
-            );
-        }
-
-        // ∀x ∈ WDEF, σ[x] ← σ[x] ∪ {pp}
-        if let Some(wdefs) = wdef {
-            for w in wdefs {
-                self.insert(&w, &self.get(&w).join(pp_value));
-            }
-        }
+        self.record_defs(this_pp, used_vars, def, wdef, &mut ctx.soln);
     }
 
-    fn analyze_term(&mut self, term: &Terminal, cfg: &Cfg, soln: &mut Map<ProgramPoint, Set<ProgramPoint>>, store: &mut Map<VarId, Set<InstId>>) -> Set<BbId> {
-
-        /*
-            ReachViaArgs = ReachableTypes(type(<arg1>)) ∪ . . . ∪ ReachableTypes(type(<argN>))
-            ReachViaGlobals = ReachableTypes(type(<global1>)) ∪ . . .
-            WDEF = ⋃ {addr_taken[τ] | τ ∈ ReachViaArgs ∪ReachViaGlobals} ∪ Globals
-        */
-        fn calculate_call_wdef(cfg: &Cfg, ops: Set<VarId>) -> Set<VarId> {
-            ops.iter()
-                .map(|x| cfg.reachable_types(&x.typ()))
-                .chain(cfg.globals.iter().map(|x| cfg.reachable_types(&x.typ())))
-                .fold(Set::new(), |acc, x| acc.union(&x).cloned().collect()).iter()
-                .filter_map(|a| cfg.addr_taken.get(a))
-                .flatten()
-                .chain(cfg.globals.iter())
-                .cloned()
-                .collect()
-        }
-
-        fn calculate_call_wdef_debug(cfg: &Cfg, ops: Set<VarId>) -> Set<VarId> {
-            let ops_reachable_types: Vec<Set<Type>> = ops.iter()
-                .map(|x| cfg.reachable_types(&x.typ()))
-                .collect();
-        
-            let globals_reachable_types: Vec<Set<Type>> = cfg.globals.iter()
-                .map(|x| cfg.reachable_types(&x.typ()))
-                .collect();
-        
-            let combined_reachable_types: Vec<Set<Type>> = ops_reachable_types.into_iter()
-                .chain(globals_reachable_types)
-                .collect();
-
-            dbg!(&combined_reachable_types);
-        
-            let folded_reachable_types: Set<Type> = combined_reachable_types.into_iter()
-                .fold(Set::new(), |acc, x| acc.union(&x).cloned().collect());
-
-            dbg!(&folded_reachable_types);
-
-            let filtered_addr_taken: Vec<&VarId> = folded_reachable_types.iter()
-                .filter_map(|a| cfg.addr_taken.get(a))
-                .flatten()
-                .collect();
-        
-            let final_set: Set<VarId> = filtered_addr_taken.into_iter()
-                .chain(cfg.globals.iter())
-                .cloned()
-                .collect();
-        
-            final_set
-        }
+    fn analyze_term(&mut self, term: &Terminal, cfg: &Cfg, ctx: &mut Context<P>) -> Set<BbId> {
         use Terminal::*;
 
         let mut used_vars: Set<VarId> = Set::new();
         let mut wdef: Option<Set<VarId>> = None;
         let this_pp = ProgramPoint::from(self.curr_inst.clone().unwrap().0, None);
         let def = match term {
-            CallDirect { lhs, callee, args, next_bb } => {
-                let call_wdef = calculate_call_wdef(cfg, get_vars(args.iter().collect()));
+            CallDirect { lhs, callee: _, args, next_bb: _ } => {
+                let call_wdef = calculate_call_wdef(cfg, &ctx.addr_taken, get_vars(args.iter().collect()));
                 // {<arg>|<arg> is a variable}
                 used_vars.extend(get_vars(args.iter().collect()).iter().cloned());
                 // CALL_WDEF
                 used_vars.extend(call_wdef.iter().cloned());
 
-                wdef = Some(call_wdef.clone());
+                wdef = Some(call_wdef);
 
                 lhs.as_ref()
             },
-            CallIndirect { lhs, callee, args, next_bb } => {
-                let call_wdef = calculate_call_wdef(cfg, get_vars(args.iter().collect()));
+            CallIndirect { lhs, callee, args, next_bb: _ } => {
+                let call_wdef = calculate_call_wdef(cfg, &ctx.addr_taken, get_vars(args.iter().collect()));
                 // {fp}
                 used_vars.insert(callee.clone());
                 // {<arg>|<arg> is a variable}
@@ -303,7 +195,7 @@ impl AbstractEnv for Env {
                 // CALL_WDEF
                 used_vars.extend(call_wdef.iter().cloned());
 
-                wdef = Some(call_wdef.clone());
+                wdef = Some(call_wdef);
 
                 lhs.as_ref()
             },
@@ -318,52 +210,27 @@ impl AbstractEnv for Env {
             _ => None,
         };
 
-        // ppvalue
-        let pp_value = &Value(Set::from([this_pp.clone()]));
-        // ∀v ∈ USE, soln[pp] ← soln[pp] ∪ σ[v]
-        soln.entry(this_pp)
-            .or_default()
-            .extend(
-                used_vars.iter()
-                    .map(|used| self.get(used))
-                    .map(|Value(set)| set)
-                    .fold(Set::new(), |acc, set| acc.union(&set).cloned().collect())
-            );
-
-        // σ[x] ← {pp}
-        if let Some(lhs) = def {
-            self.insert(
-                lhs,
-                pp_value,
-            );
-        }
-        // ∀x ∈ WDEF, σ[x] ← σ[x] ∪ {pp}
-        if let Some(wdefs) = wdef {
-            for w in wdefs {
-                self.insert(&w, &self.get(&w).join(pp_value));
-            }
-        }
+        self.record_defs(this_pp, used_vars, def, wdef, &mut ctx.soln);
 
         // skip branch
         // remnants from value analysis
         // and i quote, "Because we don’t do a value analysis, we consider both branches as viable when analyzing a $branch instruction."
-        Set::new() 
+        Set::new()
     }
 
-    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg, soln: &mut Map<ProgramPoint, Set<ProgramPoint>>, store: &mut Map<VarId, Set<InstId>>) -> (Vec<Self>, Set<BbId>) {
+    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg, ctx: &mut Context<P>) -> (Vec<Self>, Set<BbId>) {
         let mut v = vec![];
         let mut s = self.clone();
 
         for (i, inst) in bb.insts.iter().enumerate() {
             s.curr_inst = Some((bb.id.clone(), i));
-            s.analyze_inst(inst, cfg, soln, store);
+            s.analyze_inst(inst, cfg, ctx);
             v.push(s.clone());
         }
 
         s.curr_inst = Some((bb.id.clone(), bb.insts.len()));
-        s.analyze_term(&bb.term, cfg, soln, store);
+        s.analyze_term(&bb.term, cfg, ctx);
         v.push(s.clone());
         (v, Set::new())
     }
-    
-}
\ No newline at end of file
+}
@@ -6,13 +6,15 @@ use super::lir::*;
 use std::cmp::Ordering;
 use std::fmt;
 
-use std::collections::{BTreeMap as Map, BTreeSet as Set};
+use std::collections::BTreeSet as Set;
 
 #[derive(Clone)]
 pub enum ConstraintExp {
     Var(VarId),
     Ref(VarId, VarId),
     Proj(VarId),
+    // the field of that name of each location the variable points to.
+    Field(VarId, String),
     Lam {
        name: String,
        param_ty: Vec<Type>,
@@ -27,21 +29,6 @@ pub enum ConstraintExp {
     }
 }
 
-impl ConstraintExp {
-    fn dummy_var() -> ConstraintExp {
-        ConstraintExp::Var(var_id("_GENERATED_DUMMY_PTR_TO_INT", ptr_ty(int_ty()), None))
-    }
-
-    fn get_name(&self) -> &str {
-        match self {
-            Self::Var(v) => v.name(),
-            Self::Ref(v, _) => v.name(),
-            Self::Proj(v) => v.name(),
-            _ => panic!("LAMBDA THING DONT ASK"),
-        }
-    }
-}
-
 impl PartialOrd for ConstraintExp {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
@@ -104,6 +91,7 @@ impl fmt::Display for ConstraintExp {
                 write!(f, "ref({},{})", var1.with_funcid(), var2.with_funcid())
             },
             ConstraintExp::Proj(var) => write!(f, "proj(ref,1,{})", var.with_funcid()),
+            ConstraintExp::Field(var, field) => write!(f, "field({},{})", var.with_funcid(), field),
             ConstraintExp::Lam{
                 name,
                 param_ty,
@@ -152,14 +140,11 @@ impl fmt::Display for ConstraintExp {
             } => {
                 write!(f, "lam_[({})->{}]({})", params, ret_ty, args)
             },
-            _ => unreachable!(),
         }
         
     }
 }
 
-pub struct ConstraintVar(Option<FuncId>, VarId);
-
 impl fmt::Debug for ConstraintExp {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self)
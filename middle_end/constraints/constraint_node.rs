@@ -1,2 +1 @@
-use super::*;
 
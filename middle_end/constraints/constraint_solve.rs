@@ -1,172 +1,563 @@
-use super::*;
-use std::collections::{BTreeMap as Map, BTreeSet as Set, VecDeque};
-use pretty_assertions::assert_eq;
-use std::env;
-
-pub fn solve(constraint_string: &str) -> String {
-    use ConstraintExp::*;
-
-    let pairs = constraint_string.parse::<Constraints>().unwrap();
-
-    assert_eq!(pairs.to_string(), constraint_string);
-    
-    let mut pred_edges: Map<ConstraintExp, Set<ConstraintExp>> = Map::new();
-    let mut succ_edges: Map<ConstraintExp, Set<ConstraintExp>> = Map::new();
-
-    let mut worklist: VecDeque<ConstraintExp> = VecDeque::new();
-
-    for constraint in pairs.0 {
-        let (e1, e2) = constraint.as_tuple();
-        add_edge(&mut worklist, &mut pred_edges, &mut succ_edges, e1.clone(), e2.clone());
-    }
-    dbg!(&worklist);
-    while let Some(node) = worklist.pop_front() {
-
-        preds_to_succs(&node, &mut worklist, &mut pred_edges, &mut succ_edges);
-        match node.clone() {
-            Var(var) => {
-                let projected = Proj(var.clone());
-                let preds = pred_edges.entry(node.clone()).or_default().clone();
-                let succs = succ_edges.entry(projected.clone()).or_default().clone();
-
-                for succ in succs.clone() {
-                    for pred in preds.clone() {
-                        if let Ref(v1, v2) = pred {
-                            add_edge(&mut worklist, &mut pred_edges, &mut succ_edges, Var(v1), succ.clone());
-
-                        }
-                    }
-                }
-            },
-            Proj(var) => {
-                let varred = Var(var.clone());
-                let preds = pred_edges.entry(node.clone()).or_default().clone();
-                let succs = succ_edges.entry(varred.clone()).or_default().clone();
-
-                for succ in succs.clone() {
-                    for pred in preds.clone() {
-                        if let Ref(v1, v2) = pred {
-                            add_edge(&mut worklist, &mut pred_edges, &mut succ_edges, Var(v1), succ.clone());
-
-                        }
-                    }
-                }
-            },
-            _ => {
-                ()
-            }
-        }
-    }
-
-    preds_to_string(&pred_edges)
-}
-
-fn preds_to_succs(
-    node: &ConstraintExp,
-    worklist: &mut VecDeque<ConstraintExp>,
-    pred_edges: &mut Map<ConstraintExp, Set<ConstraintExp>>,
-    succ_edges: &mut Map<ConstraintExp, Set<ConstraintExp>>
-) {
-    let preds = pred_edges.entry(node.clone()).or_default().clone();
-    let succs = succ_edges.entry(node.clone()).or_default().clone();
-    for succ in succs.clone() {
-        for pred in preds.clone() {
-            add_edge(worklist, pred_edges, succ_edges, pred, succ.clone());
-        }
-    }
-}
-
-fn read_from(path: &str) -> String {
-    String::from_utf8(
-        std::fs::read(path)
-            .unwrap_or_else(|_| panic!("Could not read the input file {}", path)),
-    )
-    .expect("The input file does not contain valid utf-8 text")
-}
-
-fn add_edge(
-    worklist: &mut VecDeque<ConstraintExp>,
-    pred_edges: &mut Map<ConstraintExp, Set<ConstraintExp>>,
-    succ_edges: &mut Map<ConstraintExp, Set<ConstraintExp>>,
-    e1: ConstraintExp, e2: ConstraintExp
-) {
-    use ConstraintExp::*;
-    if e1.get_name() == "main.id16" {
-        dbg!(&e1, &e2);
-    }
-    match (&e1, &e2) {
-        // both var
-        (Var(v1), Var(v2)) => {
-            if succ_edges.entry(e1.clone()).or_default().insert(e2.clone()) && !worklist.contains(&e1) {
-                worklist.push_back(e1);
-            }
-        },
-        // one proj
-        (Proj(v1), Var(v2)) => {
-            if succ_edges.entry(e1.clone()).or_default().insert(e2.clone()) && !worklist.contains(&e1) {
-                pred_edges.entry(e2.clone()).or_default().insert(e1.clone());
-                worklist.push_back(e1);
-            }
-        },
-        (Var(v1), Proj(v2)) => {
-            if succ_edges.entry(e1.clone()).or_default().insert(e2.clone()) && !worklist.contains(&e1) {
-                pred_edges.entry(e2.clone()).or_default().insert(e1.clone());
-                worklist.push_back(e1);
-            }
-        },
-        (Ref(v1, v2), Var(v3)) => {
-            if pred_edges.entry(e2.clone()).or_default().insert(e1.clone()) && !worklist.contains(&e2) {
-                succ_edges.entry(Var(v1.clone())).or_default().insert(Proj(v3.clone()));
-                worklist.push_back(e2);
-            }
-        },
-        (Proj(v1), Proj(v2)) => {
-            if succ_edges.entry(e1.clone()).or_default().insert(e2.clone()) && !worklist.contains(&e1) {
-                pred_edges.entry(e2.clone()).or_default().insert(e1.clone());
-                worklist.push_back(e1.clone());
-            }
-        },
-        (Ref(v1, v2), Proj(v3)) => {
-            
-
-            /* 
-            let var = Var(v3.clone());
-            let var_pred = pred_edges.entry(var).or_default().clone();
-            for pred in var_pred {
-                if let Ref(b1, b2) = pred {
-                    if pred_edges.entry(Var(b1.clone())).or_default().insert(e1.clone()) && !worklist.contains(&Var(b1.clone())) {
-                        worklist.push_back(Var(b1.clone()));
-                    }
-                }
-            }*/
-        },
-        _ => { println!("============================================skipping for now e1: {e1} | e2: {e2}") }
-    }
-}
-
-fn preds_to_string(pred_edges: &Map<ConstraintExp, Set<ConstraintExp>>) -> String {
-    /*
-    for x in var node in the graph in lexicographical ordering:
-    if pred(x) contains a ref:
-       ptsto = {c.args[0] for c in x.pred if c is a ref constructor}
-	print("$x -> $ptsto")
-    */
-    use ConstraintExp::*;
-
-    let mut return_string = String::from("");
-
-    for (node, pred_set) in pred_edges {
-        let mut refs: Vec<String> = vec![];
-        for pred in pred_set.clone() {
-            if let Ref(v1, v2) = pred {
-                refs.push(v1.to_string());
-            }
-        }
-
-        if !refs.is_empty() {
-            return_string = format!("{}{node} -> {{{}}}\n", return_string, refs.join(", "));
-        }
-    }
-    return_string += "\n";
-    return_string
-}
\ No newline at end of file
+use super::*;
+use std::collections::{BTreeMap as Map, BTreeSet as Set, HashMap, VecDeque};
+use std::mem;
+use pretty_assertions::assert_eq;
+
+pub mod reference;
+pub mod steensgaard;
+
+pub fn solve(constraint_string: &str) -> String {
+    let pairs = constraint_string.parse::<Constraints>().unwrap();
+
+    assert_eq!(pairs.to_string(), constraint_string);
+
+    pts_to_string(&solve_constraints(&pairs.0))
+}
+
+/// Solve the given constraints.  The result maps each variable (by its scoped
+/// name) that points to something to the allocation sites, variables and
+/// functions it may point to.
+///
+/// Besides `ref` and `proj`, this supports the `lam` constructor of function
+/// pointers: a lambda `lam_[t](f, r, a1, ..., an)` flowing into a call
+/// `x <= lam_[t](_DUMMY, r', b1, ..., bn)` through `x` makes the call return
+/// what `f` returns (r <= r') and pass its arguments to the parameters of `f`
+/// (bi <= ai).  The return is missing from a lambda when it is not a pointer
+/// variable, so it is matched only when both sides have one.
+///
+/// A field constraint `field(x, f) <= y` makes y point to the field `f` of
+/// each location x points to, as a location named like `_alloc1.f`.
+///
+/// The solver numbers the variables and terms, and propagates only the terms
+/// that are new to a variable since it was last visited.  Variables on a cycle
+/// of inclusions end up with the same points-to set, so they are merged into
+/// one node.  Cycles are looked for lazily: when propagating along x <= y
+/// leaves x and y with equal sets, which a cycle through the edge would
+/// cause, and that edge was not checked before.
+pub fn solve_constraints(constraints: &Set<Constraint>) -> Map<String, Set<String>> {
+    let mut solver = Solver::default();
+
+    for Constraint(lhs, rhs) in constraints {
+        let (lhs, rhs) = (solver.intern(Exp::from(lhs)), solver.intern(Exp::from(rhs)));
+        solver.add(lhs, rhs);
+    }
+    solver.solve();
+
+    solver.solution()
+}
+
+fn pts_to_string(pts_to: &Map<String, Set<String>>) -> String {
+    let mut return_string = String::from("");
+
+    for (x, locs) in pts_to {
+        if !locs.is_empty() {
+            let locs: Vec<&str> = locs.iter().map(String::as_str).collect();
+            return_string = format!("{return_string}{x} -> {{{}}}\n", locs.join(", "));
+        }
+    }
+    return_string += "\n";
+    return_string
+}
+
+// A constructed value: what the points-to sets hold.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Term {
+    Ref(String),
+    Lam(Lambda),
+}
+
+impl Term {
+    // the name of the location or function this points to.
+    fn name(&self) -> String {
+        match self {
+            Term::Ref(x) => x.clone(),
+            Term::Lam(lam) => lam.name.clone(),
+        }
+    }
+}
+
+// A `lam` constructor.  Only lambdas of the same type match.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+struct Lambda {
+    typ: String,
+    name: String,
+    ret: Option<String>,
+    args: Vec<String>,
+}
+
+// A constraint expression, with the variables named by their scoped names.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Exp {
+    Var(String),
+    Proj(String),
+    Field(String, String),
+    Term(Term),
+}
+
+impl From<&ConstraintExp> for Exp {
+    fn from(exp: &ConstraintExp) -> Self {
+        use ConstraintExp::*;
+
+        match exp {
+            Var(x) => Exp::Var(x.with_funcid()),
+            Proj(x) => Exp::Proj(x.with_funcid()),
+            Field(x, field) => Exp::Field(x.with_funcid(), field.clone()),
+            Ref(x, _) => Exp::Term(Term::Ref(x.with_funcid())),
+            Lam { name, param_ty, ret_ty, ret_op, args } => {
+                let params: Vec<String> = param_ty.iter().map(|t| t.to_string()).collect();
+                let ret_ty = ret_ty.as_ref().map_or("_".to_string(), |t| t.to_string());
+                let ret = match ret_op {
+                    Some(Operand::Var(x)) if x.typ().is_ptr() => Some(x.with_funcid()),
+                    _ => None,
+                };
+                Exp::Term(Term::Lam(Lambda {
+                    typ: format!("({})->{ret_ty}", params.join(",")),
+                    name: name.clone(),
+                    ret,
+                    args: args.iter().filter(|x| x.typ().is_ptr()).map(|x| x.with_funcid()).collect(),
+                }))
+            }
+            LamSimple { params, ret_ty, args } => {
+                let num_ptr_params = params.split(',').filter(|t| t.starts_with('&')).count();
+                let mut args = args.split(',').map(str::to_string).filter(|x| !x.is_empty());
+                let name = args.next().unwrap_or_default();
+                let mut args: Vec<String> = args.collect();
+                // the return comes first, if there is one.
+                let ret = (args.len() > num_ptr_params).then(|| args.remove(0));
+                Exp::Term(Term::Lam(Lambda {
+                    typ: format!("({params})->{ret_ty}"),
+                    name,
+                    ret,
+                    args,
+                }))
+            }
+        }
+    }
+}
+
+
+// A variable of the constraint graph.  Variables on a cycle are merged, with
+// one node standing for all of them (see [Solver::find]).
+type Node = usize;
+
+// The index of an interned term.
+type TermId = usize;
+
+// A side of a constraint, interned.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Side {
+    Var(Node),
+    Proj(Node),
+    Field(Node, usize),
+    Term(TermId),
+}
+
+// A term, with its variables interned.
+#[derive(Clone, Debug)]
+enum Value {
+    Loc(Node),
+    Fn(Fun),
+}
+
+// A lambda, or a call when its name is `_DUMMY`.  The return of a call nobody
+// reads is dropped.
+#[derive(Clone, Debug)]
+struct Fun {
+    name: String,
+    typ: usize,
+    ret: Option<Node>,
+    args: Vec<Node>,
+}
+
+// An inclusion-constraint solver over numbered variables and terms.
+#[derive(Default)]
+struct Solver {
+    names: Vec<String>,
+    nodes: HashMap<String, Node>,
+    // indexed by node, in one vector so that a new node allocates once.
+    data: Vec<NodeData>,
+    values: Vec<Value>,
+    // the term referring to each lambda.
+    lambdas: HashMap<Lambda, TermId>,
+    types: HashMap<String, usize>,
+    field_names: Vec<String>,
+    fields: HashMap<String, usize>,
+    // the nodes with a nonempty delta, each once.
+    worklist: VecDeque<Node>,
+    // the edges already checked for a cycle.
+    checked: Set<(Node, Node)>,
+    // the visit number of each node and the lowest one it reaches while
+    // looking for a cycle, or usize::MAX for the nodes not visited.  They are
+    // kept between searches, each resetting only the nodes it visited.
+    index: Vec<usize>,
+    low: Vec<usize>,
+    on_stack: Vec<bool>,
+}
+
+// What the solver keeps of a node.  But for `parent`, it is only meaningful
+// for the nodes that were not merged into others.
+struct NodeData {
+    // the merged node this belongs to, and the size of a merged node.
+    parent: Node,
+    size: usize,
+    // the term referring to this node.
+    reference: Option<TermId>,
+    pts_to: Set<TermId>,
+    // the terms of pts_to not yet propagated.
+    delta: Set<TermId>,
+    // x <= y
+    succs: Set<Node>,
+    // proj(x) <= e
+    loads: Set<Side>,
+    // e <= proj(x)
+    stores: Set<Side>,
+    // field(x, f) <= y
+    offsets: Set<(usize, Node)>,
+    // x <= lam(...)
+    calls: Set<TermId>,
+    queued: bool,
+}
+
+impl Solver {
+    fn node(&mut self, x: String) -> Node {
+        if let Some(&n) = self.nodes.get(&x) {
+            return n;
+        }
+
+        let n = self.names.len();
+        self.nodes.insert(x.clone(), n);
+        self.names.push(x);
+        self.data.push(NodeData {
+            parent: n,
+            size: 1,
+            reference: None,
+            pts_to: Set::new(),
+            delta: Set::new(),
+            succs: Set::new(),
+            loads: Set::new(),
+            stores: Set::new(),
+            offsets: Set::new(),
+            calls: Set::new(),
+            queued: false,
+        });
+        n
+    }
+
+    // The term referring to the location x.
+    fn reference(&mut self, x: String) -> TermId {
+        let x = self.node(x);
+        if let Some(t) = self.data[x].reference {
+            return t;
+        }
+
+        self.values.push(Value::Loc(x));
+        self.data[x].reference = Some(self.values.len() - 1);
+        self.values.len() - 1
+    }
+
+    fn lambda(&mut self, lam: Lambda) -> TermId {
+        if let Some(&t) = self.lambdas.get(&lam) {
+            return t;
+        }
+
+        let types = self.types.len();
+        let typ = *self.types.entry(lam.typ.clone()).or_insert(types);
+        let fun = Fun {
+            name: lam.name.clone(),
+            typ,
+            ret: lam.ret.clone().filter(|ret| ret != "_DUMMY").map(|ret| self.node(ret)),
+            args: lam.args.iter().map(|x| self.node(x.clone())).collect(),
+        };
+        self.values.push(Value::Fn(fun));
+        self.lambdas.insert(lam, self.values.len() - 1);
+        self.values.len() - 1
+    }
+
+    fn intern(&mut self, exp: Exp) -> Side {
+        match exp {
+            Exp::Var(x) => Side::Var(self.node(x)),
+            Exp::Proj(x) => Side::Proj(self.node(x)),
+            Exp::Field(x, field) => {
+                let f = match self.fields.get(&field) {
+                    Some(&f) => f,
+                    None => {
+                        self.fields.insert(field.clone(), self.field_names.len());
+                        self.field_names.push(field);
+                        self.field_names.len() - 1
+                    }
+                };
+                Side::Field(self.node(x), f)
+            }
+            Exp::Term(Term::Ref(loc)) => Side::Term(self.reference(loc)),
+            Exp::Term(Term::Lam(lam)) => Side::Term(self.lambda(lam)),
+        }
+    }
+
+    // The node x is merged into.
+    fn find(&self, mut x: Node) -> Node {
+        while self.data[x].parent != x {
+            x = self.data[x].parent;
+        }
+        x
+    }
+
+    fn add(&mut self, lhs: Side, rhs: Side) {
+        use Side::*;
+
+        match (lhs, rhs) {
+            (Var(x), Var(y)) => self.add_edge(x, y),
+            (Term(t), Var(y)) => self.add_terms(self.find(y), [t]),
+            (Proj(x), rhs) => {
+                let x = self.find(x);
+                if self.data[x].loads.insert(rhs) {
+                    for loc in self.locations(x) {
+                        self.add(Var(loc), rhs);
+                    }
+                }
+            }
+            (lhs, Proj(x)) => {
+                let x = self.find(x);
+                if self.data[x].stores.insert(lhs) {
+                    for loc in self.locations(x) {
+                        self.add(lhs, Var(loc));
+                    }
+                }
+            }
+            (Field(x, f), Var(y)) => {
+                let x = self.find(x);
+                if self.data[x].offsets.insert((f, y)) {
+                    for loc in self.locations(x) {
+                        let t = self.field(loc, f);
+                        self.add_terms(self.find(y), [t]);
+                    }
+                }
+            }
+            (Var(x), Term(call)) => {
+                let x = self.find(x);
+                if self.data[x].calls.insert(call) {
+                    for lam in self.data[x].pts_to.clone() {
+                        self.call(lam, call);
+                    }
+                }
+            }
+            (Term(lam), Term(call)) => self.call(lam, call),
+            // the generator never bounds a field, or a lambda by one.
+            (_, Field(..)) | (Field(..), Term(_)) => {}
+        }
+    }
+
+    fn add_edge(&mut self, x: Node, y: Node) {
+        let (x, y) = (self.find(x), self.find(y));
+        if x != y && self.data[x].succs.insert(y) {
+            self.add_terms(y, self.data[x].pts_to.clone());
+        }
+    }
+
+    fn add_terms(&mut self, x: Node, terms: impl IntoIterator<Item = TermId>) {
+        for t in terms {
+            if self.data[x].pts_to.insert(t) {
+                self.data[x].delta.insert(t);
+            }
+        }
+        if !self.data[x].delta.is_empty() {
+            self.queue(x);
+        }
+    }
+
+    fn queue(&mut self, x: Node) {
+        if !self.data[x].queued {
+            self.data[x].queued = true;
+            self.worklist.push_back(x);
+        }
+    }
+
+    fn solve(&mut self) {
+        while let Some(x) = self.worklist.pop_front() {
+            self.data[x].queued = false;
+            // a node merged into another left its terms to it.
+            if self.find(x) != x {
+                continue;
+            }
+            let delta = mem::take(&mut self.data[x].delta);
+            self.propagate(x, &delta);
+        }
+    }
+
+    // Propagate the terms newly added to the points-to set of x.
+    fn propagate(&mut self, x: Node, delta: &Set<TermId>) {
+        let (loads, stores, calls) = (self.data[x].loads.clone(), self.data[x].stores.clone(), self.data[x].calls.clone());
+        let offsets = self.data[x].offsets.clone();
+        for &t in delta {
+            match self.values[t] {
+                Value::Loc(loc) => {
+                    for &rhs in &loads {
+                        self.add(Side::Var(loc), rhs);
+                    }
+                    for &lhs in &stores {
+                        self.add(lhs, Side::Var(loc));
+                    }
+                    for &(f, y) in &offsets {
+                        let t = self.field(loc, f);
+                        self.add_terms(self.find(y), [t]);
+                    }
+                }
+                Value::Fn(_) => {
+                    for &call in &calls {
+                        self.call(t, call);
+                    }
+                }
+            }
+        }
+
+        let mut cycle = false;
+        let succs: Vec<Node> = self.data[x].succs.iter().map(|&y| self.find(y)).collect();
+        for y in succs.into_iter().filter(|&y| y != x) {
+            self.add_terms(y, delta.iter().copied());
+            // a node with no successors is on no cycle.
+            cycle |= !self.data[y].succs.is_empty() && self.data[x].pts_to == self.data[y].pts_to && self.checked.insert((x, y));
+        }
+        if cycle {
+            for y in self.scc(x) {
+                self.merge(self.find(x), y);
+            }
+        }
+    }
+
+    // The locations the points-to set of x holds.
+    fn locations(&self, x: Node) -> Vec<Node> {
+        self.data[x].pts_to
+            .iter()
+            .filter_map(|&t| match self.values[t] {
+                Value::Loc(loc) => Some(loc),
+                Value::Fn(_) => None,
+            })
+            .collect()
+    }
+
+    // A reference to the field f of a location.
+    fn field(&mut self, loc: Node, f: usize) -> TermId {
+        self.reference(format!("{}.{}", self.names[loc], self.field_names[f]))
+    }
+
+    // The function `lam` is called at `call`.
+    fn call(&mut self, lam: TermId, call: TermId) {
+        let (Value::Fn(lam), Value::Fn(call)) = (&self.values[lam], &self.values[call]) else {
+            return;
+        };
+        if lam.typ != call.typ {
+            return;
+        }
+
+        let mut edges: Vec<(Node, Node)> = lam.args.iter().zip(&call.args).map(|(&param, &arg)| (arg, param)).collect();
+        if let (Some(ret), Some(dst)) = (lam.ret, call.ret) {
+            edges.push((ret, dst));
+        }
+        for (x, y) in edges {
+            self.add_edge(x, y);
+        }
+    }
+
+    // The nodes on a cycle with the given one, found with Tarjan's algorithm,
+    // iteratively since the graph may have long paths.
+    fn scc(&mut self, root: Node) -> Vec<Node> {
+        let mut index = mem::take(&mut self.index);
+        let mut low = mem::take(&mut self.low);
+        let mut on_stack = mem::take(&mut self.on_stack);
+        index.resize(self.names.len(), usize::MAX);
+        low.resize(self.names.len(), usize::MAX);
+        on_stack.resize(self.names.len(), false);
+
+        // the nodes visited, to reset them when done.
+        let mut seen = vec![];
+        let mut stack = vec![];
+        // the nodes being visited, with their successors left to visit.
+        let mut visits: Vec<(Node, Vec<Node>)> = vec![];
+        let mut next = Some(root);
+        let mut visited = 0;
+
+        loop {
+            if let Some(x) = next.take() {
+                (index[x], low[x], on_stack[x]) = (visited, visited, true);
+                visited += 1;
+                stack.push(x);
+                seen.push(x);
+                visits.push((x, self.data[x].succs.iter().map(|&y| self.find(y)).collect()));
+            }
+            let (x, succs) = visits.last_mut().expect("the root is the last node visited");
+            let x = *x;
+            if let Some(y) = succs.pop() {
+                if index[y] == usize::MAX {
+                    next = Some(y);
+                } else if on_stack[y] {
+                    low[x] = low[x].min(index[y]);
+                }
+                continue;
+            }
+
+            visits.pop();
+            if let Some(&(parent, _)) = visits.last() {
+                low[parent] = low[parent].min(low[x]);
+            }
+            if low[x] == index[x] {
+                let at = stack.iter().rposition(|&y| y == x).unwrap();
+                let scc = stack.split_off(at);
+                for &y in &scc {
+                    on_stack[y] = false;
+                }
+                if x == root {
+                    for y in seen {
+                        (index[y], low[y]) = (usize::MAX, usize::MAX);
+                    }
+                    (self.index, self.low, self.on_stack) = (index, low, on_stack);
+                    return scc;
+                }
+            }
+        }
+    }
+
+    // Merge the nodes x and y, both their own representatives.
+    fn merge(&mut self, x: Node, y: Node) {
+        if x == y {
+            return;
+        }
+        let (x, y) = if self.data[x].size < self.data[y].size { (y, x) } else { (x, y) };
+        self.data[y].parent = x;
+        self.data[x].size += self.data[y].size;
+
+        let pts_to = mem::take(&mut self.data[y].pts_to);
+        self.data[x].pts_to.extend(pts_to);
+        self.data[y].delta.clear();
+        let succs = mem::take(&mut self.data[y].succs);
+        self.data[x].succs.extend(succs);
+        let loads = mem::take(&mut self.data[y].loads);
+        self.data[x].loads.extend(loads);
+        let stores = mem::take(&mut self.data[y].stores);
+        self.data[x].stores.extend(stores);
+        let offsets = mem::take(&mut self.data[y].offsets);
+        self.data[x].offsets.extend(offsets);
+        let calls = mem::take(&mut self.data[y].calls);
+        self.data[x].calls.extend(calls);
+
+        // the terms of either node may meet constraints of the other.
+        self.data[x].delta = self.data[x].pts_to.clone();
+        self.queue(x);
+    }
+
+    fn solution(&self) -> Map<String, Set<String>> {
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(x, name)| (name.clone(), &self.data[self.find(x)].pts_to))
+            .filter(|(_, pts_to)| !pts_to.is_empty())
+            .map(|(x, pts_to)| (x, pts_to.iter().map(|&t| self.term_name(t).to_string()).collect()))
+            .collect()
+    }
+
+    // the name of the location or function a term refers to.
+    fn term_name(&self, t: TermId) -> &str {
+        match &self.values[t] {
+            Value::Loc(x) => &self.names[*x],
+            Value::Fn(fun) => &fun.name,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! A straightforward solver keyed by variable names, which recomputes nothing
+//! cleverly and never merges variables.  It gives the same solutions as
+//! [super::solve_constraints], which is checked and benchmarked against it.
+
+use std::collections::{BTreeMap as Map, BTreeSet as Set, VecDeque};
+
+use super::*;
+
+/// Solve the given constraints, like [super::solve_constraints].
+pub fn solve_constraints(constraints: &Set<Constraint>) -> Map<String, Set<String>> {
+    let mut solver = Solver::default();
+
+    for Constraint(lhs, rhs) in constraints {
+        solver.add(Exp::from(lhs), Exp::from(rhs));
+    }
+    while let Some((x, term)) = solver.worklist.pop_front() {
+        solver.propagate(&x, &term);
+    }
+
+    solver
+        .pts_to
+        .into_iter()
+        .map(|(x, terms)| (x, terms.iter().map(Term::name).collect()))
+        .collect()
+}
+
+// An inclusion-constraint solver that keeps the points-to set of each variable
+// and the constraints waiting on it.
+#[derive(Default)]
+struct Solver {
+    pts_to: Map<String, Set<Term>>,
+    // x <= y
+    succs: Map<String, Set<String>>,
+    // proj(x) <= e
+    loads: Map<String, Set<Exp>>,
+    // e <= proj(x)
+    stores: Map<String, Set<Exp>>,
+    // field(x, f) <= y
+    offsets: Map<String, Set<(String, String)>>,
+    // x <= lam(...)
+    calls: Map<String, Set<Lambda>>,
+    // terms newly added to the points-to set of a variable.
+    worklist: VecDeque<(String, Term)>,
+}
+
+impl Solver {
+    fn add(&mut self, lhs: Exp, rhs: Exp) {
+        use Exp::*;
+
+        match (lhs, rhs) {
+            (Var(x), Var(y)) => {
+                if x != y && self.succs.entry(x.clone()).or_default().insert(y.clone()) {
+                    for term in self.pts_to.get(&x).cloned().unwrap_or_default() {
+                        self.add_term(&y, term);
+                    }
+                }
+            }
+            (Term(term), Var(y)) => self.add_term(&y, term),
+            (Proj(x), rhs) => {
+                if self.loads.entry(x.clone()).or_default().insert(rhs.clone()) {
+                    for loc in self.locations(&x) {
+                        self.add(Var(loc), rhs.clone());
+                    }
+                }
+            }
+            (lhs, Proj(x)) => {
+                if self.stores.entry(x.clone()).or_default().insert(lhs.clone()) {
+                    for loc in self.locations(&x) {
+                        self.add(lhs.clone(), Var(loc));
+                    }
+                }
+            }
+            (Field(x, field), Var(y)) => {
+                if self.offsets.entry(x.clone()).or_default().insert((field.clone(), y.clone())) {
+                    for loc in self.locations(&x) {
+                        self.add_term(&y, super::Term::Ref(format!("{loc}.{field}")));
+                    }
+                }
+            }
+            (Var(x), Term(super::Term::Lam(call))) => {
+                if self.calls.entry(x.clone()).or_default().insert(call.clone()) {
+                    for term in self.pts_to.get(&x).cloned().unwrap_or_default() {
+                        if let super::Term::Lam(lam) = term {
+                            self.call(&lam, &call);
+                        }
+                    }
+                }
+            }
+            (Term(super::Term::Lam(lam)), Term(super::Term::Lam(call))) => self.call(&lam, &call),
+            // the generator never bounds a variable or lambda by a ref, or
+            // anything by a field.
+            (_, Term(_)) | (_, Field(..)) => {}
+        }
+    }
+
+    fn add_term(&mut self, x: &str, term: Term) {
+        if self.pts_to.entry(x.to_string()).or_default().insert(term.clone()) {
+            self.worklist.push_back((x.to_string(), term));
+        }
+    }
+
+    // Propagate a term newly added to the points-to set of x.
+    fn propagate(&mut self, x: &str, term: &Term) {
+        for y in self.succs.get(x).cloned().unwrap_or_default() {
+            self.add_term(&y, term.clone());
+        }
+
+        match term {
+            Term::Ref(loc) => {
+                for rhs in self.loads.get(x).cloned().unwrap_or_default() {
+                    self.add(Exp::Var(loc.clone()), rhs);
+                }
+                for lhs in self.stores.get(x).cloned().unwrap_or_default() {
+                    self.add(lhs, Exp::Var(loc.clone()));
+                }
+                for (field, y) in self.offsets.get(x).cloned().unwrap_or_default() {
+                    self.add_term(&y, Term::Ref(format!("{loc}.{field}")));
+                }
+            }
+            Term::Lam(lam) => {
+                for call in self.calls.get(x).cloned().unwrap_or_default() {
+                    self.call(lam, &call);
+                }
+            }
+        }
+    }
+
+    // the locations x points to.
+    fn locations(&self, x: &str) -> Vec<String> {
+        self.pts_to
+            .get(x)
+            .into_iter()
+            .flatten()
+            .filter_map(|term| match term {
+                Term::Ref(loc) => Some(loc.clone()),
+                Term::Lam(_) => None,
+            })
+            .collect()
+    }
+
+    // The function `lam` is called at `call`.
+    fn call(&mut self, lam: &Lambda, call: &Lambda) {
+        if lam.typ != call.typ {
+            return;
+        }
+
+        // `_DUMMY` stands for the result of a call that nobody reads.
+        if let (Some(ret), Some(dst)) = (&lam.ret, &call.ret) {
+            if dst != "_DUMMY" {
+                self.add(Exp::Var(ret.clone()), Exp::Var(dst.clone()));
+            }
+        }
+        for (param, arg) in lam.args.iter().zip(&call.args) {
+            self.add(Exp::Var(arg.clone()), Exp::Var(param.clone()));
+        }
+    }
+}
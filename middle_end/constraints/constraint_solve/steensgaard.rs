@@ -0,0 +1,254 @@
+//! Steensgaard's unification-based points-to analysis, over the same
+//! constraints as [super::solve_constraints].
+//!
+//! Each variable points to at most one class of locations, and a constraint
+//! unifies what its two sides point to instead of including one in the other.
+//! With union-find this takes near-linear time, but where the inclusion-based
+//! solver finds that x <= y makes y point to a location that x does not, this
+//! makes both point to it.  The classes pointed to hold function signatures
+//! too: a lambda and a call through the same class unify their returns and
+//! arguments.  Fields are merged, so a `field(x, f)` constraint is a copy.
+
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
+
+use pretty_assertions::assert_eq;
+
+use super::*;
+
+/// Solve the constraints in the same format as [super::solve], and print the
+/// solution the same way.
+pub fn solve(constraint_string: &str) -> String {
+    let pairs = constraint_string.parse::<Constraints>().unwrap();
+
+    assert_eq!(pairs.to_string(), constraint_string);
+
+    pts_to_string(&solve_constraints(&pairs.0))
+}
+
+/// Solve the given constraints, in the format of [super::solve_constraints].
+/// Every variable points to at least the locations it does in that solution.
+pub fn solve_constraints(constraints: &Set<Constraint>) -> Map<String, Set<String>> {
+    let mut solver = Solver::default();
+
+    for Constraint(lhs, rhs) in constraints {
+        solver.add(&Exp::from(lhs), &Exp::from(rhs));
+    }
+
+    solver.solution()
+}
+
+// A class of variables.  Classes are merged, with one node standing for all
+// of them (see [Solver::find]).
+type Node = usize;
+
+// The returns and parameters of the functions in a class.
+#[derive(Clone, Debug, Default)]
+struct Sig {
+    ret: Option<Node>,
+    args: Vec<Node>,
+}
+
+// A union-find of variables.  All vectors are indexed by node, and but for
+// `parent` and `names` are only meaningful for the nodes that are their own
+// representatives.
+#[derive(Default)]
+struct Solver {
+    // the name of each variable, or None for a class made up to point to.
+    names: Vec<Option<String>>,
+    nodes: Map<String, Node>,
+    parent: Vec<Node>,
+    size: Vec<usize>,
+    // the class the variables of a class point to.
+    pointee: Vec<Option<Node>>,
+    // the functions in a class, and their signature.
+    funcs: Vec<Set<String>>,
+    sig: Vec<Option<Sig>>,
+    // the variables that are pointed to.
+    is_loc: Vec<bool>,
+}
+
+impl Solver {
+    fn node(&mut self, name: Option<&str>) -> Node {
+        if let Some(&x) = name.and_then(|name| self.nodes.get(name)) {
+            return x;
+        }
+
+        let x = self.names.len();
+        if let Some(name) = name {
+            self.nodes.insert(name.to_string(), x);
+        }
+        self.names.push(name.map(str::to_string));
+        self.parent.push(x);
+        self.size.push(1);
+        self.pointee.push(None);
+        self.funcs.push(Set::new());
+        self.sig.push(None);
+        self.is_loc.push(false);
+        x
+    }
+
+    fn var(&mut self, name: &str) -> Node {
+        self.node(Some(name))
+    }
+
+    // The class x is merged into.
+    fn find(&mut self, x: Node) -> Node {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        let mut x = x;
+        while self.parent[x] != root {
+            (x, self.parent[x]) = (self.parent[x], root);
+        }
+        root
+    }
+
+    // The class the variables of x's class point to, made up if there is none.
+    fn pointee(&mut self, x: Node) -> Node {
+        let x = self.find(x);
+        match self.pointee[x] {
+            Some(y) => self.find(y),
+            None => {
+                let y = self.node(None);
+                self.pointee[x] = Some(y);
+                y
+            }
+        }
+    }
+
+    // The class of the values an expression stands for: what x points to for
+    // x, what *x points to for proj(x), the location for ref(loc) and the
+    // function for a lambda.
+    fn values(&mut self, exp: &Exp) -> Node {
+        match exp {
+            Exp::Var(x) | Exp::Field(x, _) => {
+                let x = self.var(x);
+                self.pointee(x)
+            }
+            Exp::Proj(x) => {
+                let x = self.var(x);
+                let y = self.pointee(x);
+                self.pointee(y)
+            }
+            Exp::Term(Term::Ref(loc)) => {
+                let loc = self.var(loc);
+                self.is_loc[loc] = true;
+                loc
+            }
+            Exp::Term(Term::Lam(lam)) => {
+                let sig = self.sig(lam);
+                let x = self.node(None);
+                if lam.name != "_DUMMY" {
+                    self.funcs[x].insert(lam.name.clone());
+                }
+                self.sig[x] = Some(sig);
+                x
+            }
+        }
+    }
+
+    fn sig(&mut self, lam: &Lambda) -> Sig {
+        Sig {
+            ret: lam.ret.as_ref().filter(|ret| *ret != "_DUMMY").map(|ret| self.var(ret)),
+            args: lam.args.iter().map(|x| self.var(x)).collect(),
+        }
+    }
+
+    fn add(&mut self, lhs: &Exp, rhs: &Exp) {
+        match (lhs, rhs) {
+            // a call through x.
+            (Exp::Var(x), Exp::Term(Term::Lam(call))) => {
+                let x = self.var(x);
+                let lam = self.pointee(x);
+                let call = self.values(&Exp::Term(Term::Lam(call.clone())));
+                self.join(lam, call);
+            }
+            (Exp::Term(Term::Lam(lam)), Exp::Term(Term::Lam(call))) => {
+                let (lam, call) = (self.sig(lam), self.sig(call));
+                self.join_sigs(lam, call);
+            }
+            // the generator never bounds a variable by a ref, or anything by
+            // a field.
+            (_, Exp::Term(_)) | (_, Exp::Field(..)) => {}
+            (lhs, rhs) => {
+                let (lhs, rhs) = (self.values(lhs), self.values(rhs));
+                self.join(lhs, rhs);
+            }
+        }
+    }
+
+    // Unify two classes, and then what they point to and their signatures.
+    fn join(&mut self, x: Node, y: Node) {
+        let mut pending = vec![(x, y)];
+        while let Some((x, y)) = pending.pop() {
+            let (x, y) = (self.find(x), self.find(y));
+            if x == y {
+                continue;
+            }
+            let (x, y) = if self.size[x] < self.size[y] { (y, x) } else { (x, y) };
+            self.parent[y] = x;
+            self.size[x] += self.size[y];
+
+            let funcs = std::mem::take(&mut self.funcs[y]);
+            self.funcs[x].extend(funcs);
+            match (self.pointee[x], self.pointee[y]) {
+                (Some(px), Some(py)) => pending.push((px, py)),
+                (None, py) => self.pointee[x] = py,
+                (_, None) => {}
+            }
+            match (self.sig[x].take(), self.sig[y].take()) {
+                (Some(sx), Some(sy)) => {
+                    let args = if sx.args.len() < sy.args.len() { &sy.args } else { &sx.args };
+                    self.sig[x] = Some(Sig { ret: sx.ret.or(sy.ret), args: args.clone() });
+                    pending.extend(self.sig_pairs(sx, sy));
+                }
+                (sx, sy) => self.sig[x] = sx.or(sy),
+            }
+        }
+    }
+
+    fn join_sigs(&mut self, sx: Sig, sy: Sig) {
+        for (x, y) in self.sig_pairs(sx, sy) {
+            self.join(x, y);
+        }
+    }
+
+    // The classes to unify for two signatures to agree: what their returns
+    // point to, and what their arguments do.
+    fn sig_pairs(&mut self, sx: Sig, sy: Sig) -> Vec<(Node, Node)> {
+        let mut pairs: Vec<(Node, Node)> = sx.args.iter().zip(&sy.args).map(|(&x, &y)| (x, y)).collect();
+        pairs.extend(sx.ret.zip(sy.ret));
+        pairs.into_iter().map(|(x, y)| (self.pointee(x), self.pointee(y))).collect()
+    }
+
+    fn solution(&mut self) -> Map<String, Set<String>> {
+        // the locations and functions in each class.
+        let mut members: Map<Node, Set<String>> = Map::new();
+        for x in 0..self.names.len() {
+            let class = self.find(x);
+            if let Some(name) = self.names[x].clone().filter(|_| self.is_loc[x]) {
+                members.entry(class).or_default().insert(name);
+            }
+            if class == x {
+                members.entry(class).or_default().extend(self.funcs[x].iter().cloned());
+            }
+        }
+
+        let mut solution = Map::new();
+        for x in 0..self.names.len() {
+            let class = self.find(x);
+            let Some(pointee) = self.pointee[class] else {
+                continue;
+            };
+            let pointee = self.find(pointee);
+            match (&self.names[x], members.get(&pointee)) {
+                (Some(name), Some(locs)) if !locs.is_empty() => {
+                    solution.insert(name.clone(), locs.clone());
+                }
+                _ => {}
+            }
+        }
+        solution
+    }
+}
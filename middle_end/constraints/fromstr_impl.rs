@@ -13,11 +13,13 @@ constraints = { SOI ~ (constraint ~ NEWLINE)+ ~ EOI }
 
 constraint = { constraint_expr ~ "<=" ~ constraint_expr }
 
-constraint_expr = { reff | lam | proj | var }
+constraint_expr = { reff | lam | proj | field | var }
 
-var = { ( ASCII_ALPHANUMERIC+ ~ "." )? ~ "_"? ~ ASCII_ALPHANUMERIC+ }
+var = { ( ASCII_ALPHANUMERIC+ ~ "." )? ~ "_"? ~ ASCII_ALPHANUMERIC+ ~ ( "." ~ ASCII_DIGIT+ )* }
 reff = { "ref(" ~ var ~ "," ~ var ~ ")" }
 proj = { "proj(ref,1," ~ var ~ ")" }
+field = { "field(" ~ var ~ "," ~ field_name ~ ")" }
+field_name = { ("_" | ASCII_ALPHANUMERIC)+ }
 lam = { "lam_[(" ~ type_arr? ~ ")->" ~ ret_type ~ "](" ~ var_arr? ~ ")" }
 
 ret_type = { type | "_" }
@@ -30,6 +32,7 @@ struct ConstraintParser;
 
 use derive_more::Display;
 #[derive(Clone, Debug, Display, Eq, PartialEq)]
+#[allow(clippy::large_enum_variant)]
 pub enum Errors {
     Parse(Error<Rule>),
     ContextSensitive(String),
@@ -46,6 +49,7 @@ impl std::str::FromStr for Constraints {
     }
 }
 
+#[allow(clippy::result_large_err)]
 fn create_constraints(parse_tree: Pair<Rule>) -> Result<Constraints, Errors> {
     let mut constraints_set: Set<Constraint> = Set::new();
 
@@ -75,6 +79,7 @@ fn parse_expr(expr: Pair<Rule>) -> ConstraintExp {
     match expr.as_rule() {
         Rule::var => parse_var(expr),
         Rule::proj => parse_proj(expr),
+        Rule::field => parse_field(expr),
         Rule::reff => parse_ref(expr),
         Rule::lam => parse_lam(expr),
         _ => unreachable!("not a constraintexpr: {:#?}", expr),
@@ -95,23 +100,32 @@ fn parse_proj(expr: Pair<Rule>) -> ConstraintExp {
     ConstraintExp::Proj(dummy_var(expr.as_str()))
 }
 
+fn parse_field(expr: Pair<Rule>) -> ConstraintExp {
+    let mut inner = expr.into_inner();
+    let (var, field) = (inner.next().unwrap(), inner.next().unwrap());
+    ConstraintExp::Field(dummy_var(var.as_str()), field.as_str().trim().to_owned())
+}
+
 fn parse_ref(expr: Pair<Rule>) -> ConstraintExp {
     let expr = expr.into_inner().next().clone().unwrap();
     ConstraintExp::Ref(dummy_var(expr.as_str()), dummy_var(expr.as_str()))
 }
 
 fn parse_lam(expr: Pair<Rule>) -> ConstraintExp {
-    let mut expr = expr.into_inner();
-    let type_arr = expr.next().clone().unwrap();
-    let ret_ty = expr.next().clone().unwrap();
-    let var_arr = expr.next().clone().unwrap();
-
-    //let mut param_ty: Vec<Type> = todo!();
-    //let mut args: Vec<VarId> = todo!();
+    // the parameter types and the arguments may be missing.
+    let (mut params, mut ret_ty, mut args) = ("", "", "");
+    for part in expr.into_inner() {
+        match part.as_rule() {
+            Rule::type_arr => params = part.as_str(),
+            Rule::ret_type => ret_ty = part.as_str(),
+            Rule::var_arr => args = part.as_str(),
+            _ => unreachable!("not a part of a lambda: {:#?}", part),
+        }
+    }
 
     ConstraintExp::LamSimple {
-        params: type_arr.as_str().trim().to_owned(),
-        ret_ty: ret_ty.as_str().trim().to_owned(),
-        args: var_arr.as_str().trim().to_owned(),
+        params: params.trim().to_owned(),
+        ret_ty: ret_ty.trim().to_owned(),
+        args: args.trim().to_owned(),
     }
-}
\ No newline at end of file
+}
@@ -18,12 +18,9 @@ mod id_type_factories;
 mod misc_impl;
 mod validate;
 
-pub use self::associated_impl::*;
 pub use self::cfg_dump_impl::*;
-pub use self::display_impl::*;
 pub use self::fromstr_impl::*;
 pub use self::id_type_factories::*;
-pub use self::misc_impl::*;
 pub use self::validate::*;
 
 // SECTION: lir identifiers
@@ -158,6 +155,8 @@ pub enum Instruction {
         src: VarId,
     },
     Phi {
+        // phis come first in their block.  There is one argument per
+        // predecessor of the block, in the order of the predecessors' names.
         lhs: VarId,
         args: Vec<Operand>,
     },
@@ -197,13 +196,13 @@ pub enum Terminal {
     Ret(Option<Operand>),
 }
 
-#[derive(Clone, Debug, Deserialize, Display, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Display, Eq, Hash, PartialEq, Serialize)]
 pub enum Operand {
     CInt(i32),
     Var(VarId),
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, Display, Eq, PartialEq, Serialize)]
+#[derive(Copy, Clone, Debug, Deserialize, Display, Eq, Hash, PartialEq, Serialize)]
 pub enum ArithmeticOp {
     #[display(fmt = "add")]
     Add,
@@ -215,7 +214,7 @@ pub enum ArithmeticOp {
     Divide,
 }
 
-#[derive(Copy, Clone, Debug, Deserialize, Display, Eq, PartialEq, Serialize)]
+#[derive(Copy, Clone, Debug, Deserialize, Display, Eq, Hash, PartialEq, Serialize)]
 pub enum ComparisonOp {
     #[display(fmt = "eq")]
     Eq,
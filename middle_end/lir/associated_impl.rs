@@ -91,7 +91,7 @@ impl Type {
     pub fn base_typ(&self) -> &Self {
         match &*self.0 {
             LirType::Pointer(deref_ty) => deref_ty.base_typ(),
-            _ => &self,
+            _ => self,
         }
     }
 
@@ -115,6 +115,185 @@ impl Operand {
     }
 }
 
+impl Operand {
+    pub fn as_var(&self) -> Option<&VarId> {
+        match self {
+            Operand::Var(v) => Some(v),
+            Operand::CInt(_) => None,
+        }
+    }
+
+    pub fn as_var_mut(&mut self) -> Option<&mut VarId> {
+        match self {
+            Operand::Var(v) => Some(v),
+            Operand::CInt(_) => None,
+        }
+    }
+}
+
+impl Instruction {
+    /// The variable this instruction assigns, if any.
+    pub fn def(&self) -> Option<&VarId> {
+        use Instruction::*;
+        match self {
+            AddrOf { lhs, .. }
+            | Alloc { lhs, .. }
+            | Arith { lhs, .. }
+            | Cmp { lhs, .. }
+            | Copy { lhs, .. }
+            | Gep { lhs, .. }
+            | Gfp { lhs, .. }
+            | Load { lhs, .. }
+            | Phi { lhs, .. } => Some(lhs),
+            CallExt { lhs, .. } => lhs.as_ref(),
+            Store { .. } => None,
+        }
+    }
+
+    /// The variables this instruction reads by name.  The operand of `$addrof`
+    /// is not read, and neither is memory.
+    pub fn uses(&self) -> Vec<&VarId> {
+        use Instruction::*;
+        match self {
+            AddrOf { .. } => vec![],
+            Alloc { num, .. } => num.as_var().into_iter().collect(),
+            Arith { op1, op2, .. } | Cmp { op1, op2, .. } => {
+                [op1, op2].into_iter().filter_map(Operand::as_var).collect()
+            }
+            CallExt { args, .. } | Phi { args, .. } => args.iter().filter_map(Operand::as_var).collect(),
+            Copy { op, .. } => op.as_var().into_iter().collect(),
+            Gep { src, idx, .. } => [src].into_iter().chain(idx.as_var()).collect(),
+            Gfp { src, .. } | Load { src, .. } => vec![src],
+            Store { dst, op } => [dst].into_iter().chain(op.as_var()).collect(),
+        }
+    }
+
+    /// [Instruction::def], for renaming.
+    pub fn def_mut(&mut self) -> Option<&mut VarId> {
+        use Instruction::*;
+        match self {
+            AddrOf { lhs, .. }
+            | Alloc { lhs, .. }
+            | Arith { lhs, .. }
+            | Cmp { lhs, .. }
+            | Copy { lhs, .. }
+            | Gep { lhs, .. }
+            | Gfp { lhs, .. }
+            | Load { lhs, .. }
+            | Phi { lhs, .. } => Some(lhs),
+            CallExt { lhs, .. } => lhs.as_mut(),
+            Store { .. } => None,
+        }
+    }
+
+    /// [Instruction::uses], for renaming.
+    pub fn uses_mut(&mut self) -> Vec<&mut VarId> {
+        use Instruction::*;
+        match self {
+            AddrOf { .. } => vec![],
+            Alloc { num, .. } => num.as_var_mut().into_iter().collect(),
+            Arith { op1, op2, .. } | Cmp { op1, op2, .. } => {
+                [op1, op2].into_iter().filter_map(Operand::as_var_mut).collect()
+            }
+            CallExt { args, .. } | Phi { args, .. } => args.iter_mut().filter_map(Operand::as_var_mut).collect(),
+            Copy { op, .. } => op.as_var_mut().into_iter().collect(),
+            Gep { src, idx, .. } => [src].into_iter().chain(idx.as_var_mut()).collect(),
+            Gfp { src, .. } | Load { src, .. } => vec![src],
+            Store { dst, op } => [dst].into_iter().chain(op.as_var_mut()).collect(),
+        }
+    }
+
+    /// The operands of this instruction, which can be variables or constants.
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        use Instruction::*;
+        match self {
+            AddrOf { .. } | Gfp { .. } | Load { .. } => vec![],
+            Alloc { num, .. } => vec![num],
+            Arith { op1, op2, .. } | Cmp { op1, op2, .. } => vec![op1, op2],
+            CallExt { args, .. } | Phi { args, .. } => args.iter_mut().collect(),
+            Copy { op, .. } | Store { op, .. } => vec![op],
+            Gep { idx, .. } => vec![idx],
+        }
+    }
+}
+
+impl Terminal {
+    /// The variable this terminal assigns, if any.
+    pub fn def(&self) -> Option<&VarId> {
+        match self {
+            Terminal::CallDirect { lhs, .. } | Terminal::CallIndirect { lhs, .. } => lhs.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The variables this terminal reads by name.
+    pub fn uses(&self) -> Vec<&VarId> {
+        use Terminal::*;
+        match self {
+            Branch { cond, .. } => cond.as_var().into_iter().collect(),
+            CallDirect { args, .. } => args.iter().filter_map(Operand::as_var).collect(),
+            CallIndirect { callee, args, .. } => {
+                [callee].into_iter().chain(args.iter().filter_map(Operand::as_var)).collect()
+            }
+            Jump(_) => vec![],
+            Ret(op) => op.iter().filter_map(Operand::as_var).collect(),
+        }
+    }
+
+    /// [Terminal::def], for renaming.
+    pub fn def_mut(&mut self) -> Option<&mut VarId> {
+        match self {
+            Terminal::CallDirect { lhs, .. } | Terminal::CallIndirect { lhs, .. } => lhs.as_mut(),
+            _ => None,
+        }
+    }
+
+    /// [Terminal::uses], for renaming.
+    pub fn uses_mut(&mut self) -> Vec<&mut VarId> {
+        use Terminal::*;
+        match self {
+            Branch { cond, .. } => cond.as_var_mut().into_iter().collect(),
+            CallDirect { args, .. } => args.iter_mut().filter_map(Operand::as_var_mut).collect(),
+            CallIndirect { callee, args, .. } => {
+                [callee].into_iter().chain(args.iter_mut().filter_map(Operand::as_var_mut)).collect()
+            }
+            Jump(_) => vec![],
+            Ret(op) => op.iter_mut().filter_map(Operand::as_var_mut).collect(),
+        }
+    }
+
+    /// The operands of this terminal, which can be variables or constants.
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        use Terminal::*;
+        match self {
+            Branch { cond, .. } => vec![cond],
+            CallDirect { args, .. } | CallIndirect { args, .. } => args.iter_mut().collect(),
+            Jump(_) => vec![],
+            Ret(op) => op.iter_mut().collect(),
+        }
+    }
+
+    /// The blocks this terminal can transfer control to.
+    pub fn successors(&self) -> Vec<&BbId> {
+        use Terminal::*;
+        match self {
+            Branch { tt, ff, .. } => vec![tt, ff],
+            CallDirect { next_bb, .. } | CallIndirect { next_bb, .. } | Jump(next_bb) => vec![next_bb],
+            Ret(_) => vec![],
+        }
+    }
+
+    /// [Terminal::successors], for retargeting.
+    pub fn successors_mut(&mut self) -> Vec<&mut BbId> {
+        use Terminal::*;
+        match self {
+            Branch { tt, ff, .. } => vec![tt, ff],
+            CallDirect { next_bb, .. } | CallIndirect { next_bb, .. } | Jump(next_bb) => vec![next_bb],
+            Ret(_) => vec![],
+        }
+    }
+}
+
 impl Program {
     pub fn validate(self) -> Result<Valid<Self>, ValidationError> {
         validate::validate(&self)?;
@@ -124,4 +303,9 @@ impl Program {
     pub fn check_valid(&self) -> Result<(), ValidationError> {
         validate::validate(self)
     }
+
+    // whether this program is in SSA form.  Assumes the program is valid.
+    pub fn check_ssa(&self) -> Result<(), ValidationError> {
+        validate::check_ssa(self)
+    }
 }
@@ -74,6 +74,7 @@ struct ProgramParser;
 // the context-sensitive syntactic rules encountered when processing the parse
 // tree resulting from pest.
 #[derive(Clone, Debug, Display, Eq, PartialEq)]
+#[allow(clippy::large_enum_variant)]
 pub enum Errors {
     Parse(Error<Rule>),
     ContextSensitive(String),
@@ -20,6 +20,8 @@
 //   Function type.
 // - all instructions are well-typed.
 // - every basic block is reachable from entry and reaches the exit block.
+// - phis come before the other instructions of a block, and have one argument
+//   per predecessor of the block.
 // - if a global variable has the same name as a function then that variable is
 //   a function pointer to a function with the same type as the named function;
 //   there cannot be a global variable named 'main'.
@@ -43,6 +45,7 @@ pub fn validate(program: &Program) -> Result<(), ValidationError> {
     errors += check_declared(program);
     errors += check_no_func_type(program);
     errors += check_reachability(program);
+    errors += check_phis(program);
     errors += check_func_and_extern_names(program);
     errors += check_global_func_ptrs(program);
 
@@ -453,7 +456,7 @@ fn check_types(program: &Program) -> ValidationError {
 
                 match inst {
                     // anything Goes 
-                    I::AddrOf { lhs, op } => {}
+                    I::AddrOf { .. } => {}
                     // lhs must be a pointer, and not a function pointer.
                     // num must be a integer.
                     I::Alloc { lhs, num, .. } => match lhs.typ().get_deref_type() {
@@ -587,11 +590,13 @@ fn check_types(program: &Program) -> ValidationError {
                     Some(callee) => {
                         // lhs and return types should match.
                         if let Some(lhs) = lhs {
-                            if callee.ret_ty.is_none() {
-                                report_err();
-                            } else if &lhs.typ() != callee.ret_ty.as_ref().unwrap() {
-                                report_err();
-                                continue;
+                            match &callee.ret_ty {
+                                None => report_err(),
+                                Some(ret_ty) if &lhs.typ() != ret_ty => {
+                                    report_err();
+                                    continue;
+                                }
+                                Some(_) => {}
                             }
                         }
 
@@ -793,6 +798,45 @@ fn check_reachability(program: &Program) -> ValidationError {
     err
 }
 
+// - phis come before the other instructions of a block, and have one argument
+//   per predecessor of the block.
+fn check_phis(program: &Program) -> ValidationError {
+    let mut err = ValidationError::new();
+
+    for (name, func) in &program.functions {
+        let mut num_preds: Map<&BbId, usize> = Map::new();
+        for bb in func.body.values() {
+            for succ in bb.term.successors().into_iter().collect::<Set<_>>() {
+                *num_preds.entry(succ).or_default() += 1;
+            }
+        }
+
+        for (label, bb) in &func.body {
+            let num_phis = bb
+                .insts
+                .iter()
+                .take_while(|inst| matches!(inst, Instruction::Phi { .. }))
+                .count();
+
+            for (pos, inst) in bb.insts.iter().enumerate() {
+                if let Instruction::Phi { args, .. } = inst {
+                    let preds = num_preds.get(label).copied().unwrap_or(0);
+                    if pos >= num_phis {
+                        err.add_error(format!("phi at {name}.{label}.{pos} is not at the start of its block"));
+                    } else if args.len() != preds {
+                        err.add_error(format!(
+                            "phi at {name}.{label}.{pos} has {} arguments but its block has {preds} predecessors",
+                            args.len()
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    err
+}
+
 // - if a global variable has the same name as a function then that variable is
 //   a function pointer to a function with the same type as the named function;
 //   there cannot be a global variable named 'main'.
@@ -823,3 +867,88 @@ fn check_global_func_ptrs(program: &Program) -> ValidationError {
 
     err
 }
+
+// SECTION: ssa validation
+
+// check whether a valid Program is in SSA form:
+//
+// - every variable is defined at most once, and parameters are not defined.
+// - every use of a variable defined in the function is dominated by its
+//   definition.  A phi uses its i-th argument at the end of the i-th
+//   predecessor of its block.
+//
+// globals and address-taken locals live in memory, so they are exempt.
+pub fn check_ssa(program: &Program) -> Result<(), ValidationError> {
+    use crate::middle_end::analysis::Cfg;
+
+    let mut err = ValidationError::new();
+
+    for (name, func) in &program.functions {
+        let cfg = Cfg::new(func, program.globals.clone(), program.structs.clone());
+        let doms = cfg.dom_tree();
+        let in_ssa = |x: &VarId| !x.is_global() && !cfg.addr_taken.contains(x);
+
+        // where each variable is defined; the terminal is at index insts.len().
+        let mut defs: Map<&VarId, (&BbId, usize)> = Map::new();
+        for (label, bb) in &func.body {
+            let bb_defs = bb.insts.iter().map(|inst| inst.def()).chain([bb.term.def()]);
+            for (pos, def) in bb_defs.enumerate() {
+                match def.filter(|x| in_ssa(x)) {
+                    Some(x) if func.params.contains(x) => {
+                        err.add_error(format!("parameter {x} in function {name} is redefined"))
+                    }
+                    Some(x) if defs.insert(x, (label, pos)).is_some() => {
+                        err.add_error(format!("variable {x} in function {name} is defined more than once"))
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        let dominated = |x: &VarId, label: &BbId, pos: usize| match defs.get(x) {
+            Some((def_label, def_pos)) => {
+                (*def_label == label && *def_pos < pos) || doms.strictly_dominates(def_label, label)
+            }
+            None => true,
+        };
+
+        for (label, bb) in &func.body {
+            let mut report_err = |x: &VarId, pos: usize| {
+                err.add_error(format!(
+                    "use of {x} at {name}.{label}.{pos} is not dominated by its definition"
+                ))
+            };
+
+            for (pos, inst) in bb.insts.iter().enumerate() {
+                match inst {
+                    Instruction::Phi { args, .. } => {
+                        for (arg, pred) in args.iter().zip(cfg.pred(label)) {
+                            match arg.as_var() {
+                                Some(x) if !dominated(x, pred, usize::MAX) => report_err(x, pos),
+                                _ => (),
+                            }
+                        }
+                    }
+                    _ => inst
+                        .uses()
+                        .into_iter()
+                        .filter(|x| !dominated(x, label, pos))
+                        .for_each(|x| report_err(x, pos)),
+                }
+            }
+
+            let pos = bb.insts.len();
+            bb.term
+                .uses()
+                .into_iter()
+                .filter(|x| !dominated(x, label, pos))
+                .for_each(|x| report_err(x, pos));
+        }
+    }
+
+    if err.is_empty() {
+        Ok(())
+    } else {
+        Err(err)
+    }
+}
@@ -1,11 +1,18 @@
 //! Optimization passes.
 
-use std::collections::BTreeMap as Map;
+use std::collections::{BTreeMap as Map, BTreeSet as Set};
 
 pub mod constant_prop;
 pub mod copy_prop;
 pub mod dead_store_elimination;
+pub mod gvn;
 pub mod inlining;
+pub mod licm;
+pub mod pass_manager;
+pub mod sccp;
+pub mod simplify_cfg;
+pub mod ssa;
+pub mod translation_validation;
 
 #[cfg(test)]
 mod tests;
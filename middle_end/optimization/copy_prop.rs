@@ -2,23 +2,21 @@
 
 Copy propagation.
 
-This optimization uses the result of a reaching definitions-like analysis to
-figure out whether propagating a copy is valid.
+This optimization uses the result of the available copies analysis (see
+[crate::middle_end::analysis::copy_prop]) to figure out whether propagating a
+copy is valid.
 
 The steps of the optimization are:
 
 For each instruction i:
   For x ∈ use(i):
-    If x has only one reaching definition, and
-      that definition is a copy instruction `x = $copy y`, and
-      `y` is not modified since the `$copy` instruction:
+    If on every path to i, the last definition of x is a copy instruction
+      `x = $copy y`, and `y` is not modified since the `$copy` instruction:
     Then, replace x with y in i.
 
-You could use reaching definitions for the first 2 conditions, but it cannot
-answer the third question (whether `y` is modified since).  So, you need to
-implement another forward analysis that keeps track of modifications since last
-copy (there is no starter code for that analysis).  This analysis is called the
-_copy propagation analysis_.
+Reaching definitions could answer the first part, but not whether `y` is
+modified since.  So the pass uses another forward analysis that keeps track of
+modifications since the last copy, the _copy propagation analysis_.
 
 The parameter for this analysis are:
 
@@ -49,26 +47,55 @@ So, we take the intersection of all predecessor nodes.
 
  */
 
-// @clippydodge use super::*;
+use super::*;
 use crate::commons::*;
+use crate::middle_end::analysis::{copy_prop::*, *};
 use crate::middle_end::lir::*;
 
 /// The actual optimization pass.
-pub fn copy_prop(program: Valid<Program>) -> Valid<Program> {
-    let program = program.0;
-
-    /* @clippydodge program.functions = program
-    .functions
-    .iter()
-    .map(|(id, f)| -> (FuncId, Function) {
-        (
-            id.clone(),
-            todo!("define your per-function optimization routine and call it here"),
-        )
-    })
-    .collect();*/
+pub fn copy_prop(valid_program: Valid<Program>) -> Valid<Program> {
+    let mut program = valid_program.0.clone();
+
+    program.functions = program
+        .functions
+        .iter()
+        .map(|(id, f)| {
+            let (pre_bb, post_inst) = analyze(&valid_program, id.clone());
+            (id.clone(), copy_prop_func(pre_bb, post_inst, f))
+        })
+        .collect();
 
     // Do not remove this validation check.  It is there to help you catch the
     // bugs early on.  The autograder uses an internal final validation check.
     program.validate().unwrap()
 }
+
+fn copy_prop_func(pre_bb: Map<BbId, Env>, post_inst: Map<InstId, Env>, func: &Function) -> Function {
+    let mut opt_func = func.clone();
+
+    for (bbid, bb) in &mut opt_func.body {
+        // the state before an instruction is the state after the previous one.
+        let pre = |i: usize| match i {
+            0 => &pre_bb[bbid],
+            _ => &post_inst[&(bbid.clone(), i - 1)],
+        };
+
+        for (i, inst) in bb.insts.iter_mut().enumerate() {
+            propagate(inst.uses_mut(), pre(i));
+        }
+        propagate(bb.term.uses_mut(), pre(bb.insts.len()));
+    }
+
+    opt_func
+}
+
+// Replace each variable with the variable it is a copy of.  Copies of copies
+// are followed to the original, which ends: a copy to `x` kills every copy
+// from `x`, so available copies never form a cycle.
+fn propagate(uses: Vec<&mut VarId>, env: &Env) {
+    for x in uses {
+        while let Some(y) = env.copy_of(x) {
+            *x = y.clone();
+        }
+    }
+}
@@ -4,27 +4,92 @@ use super::*;
 use crate::commons::*;
 use crate::middle_end::analysis::{liveness::*, *};
 use crate::middle_end::lir::*;
+use crate::middle_end::optimization::pass_manager::{Analyses, Analysis};
 
-/// The actual optimization pass.
+/// The actual optimization pass.  Removing a dead instruction can make the
+/// definitions it reads dead, so this runs until nothing changes.
 pub fn dead_store_elim(valid_program: Valid<Program>) -> Valid<Program> {
-    let mut program = valid_program.0.clone();
-
-    program.functions = program
-        .functions
-        .iter()
-        .map(|(id, f)| {
-            let (pre_bb, pre_inst) = analyze(&valid_program, id.clone());
-            (id.clone(), dse_func(pre_bb, pre_inst, f))
+    dead_store_elim_with(valid_program, &mut Analyses::default())
+}
+
+/// The pass, reusing the liveness results already computed.
+pub fn dead_store_elim_with(valid_program: Valid<Program>, analyses: &mut Analyses) -> Valid<Program> {
+    let mut valid_program = valid_program;
+
+    loop {
+        let mut program = valid_program.0.clone();
+
+        program.functions = program
+            .functions
+            .iter()
+            .map(|(id, f)| {
+                let live = analyses.liveness(&valid_program, id);
+                (id.clone(), dse_func(&live.0, &live.1, f))
+            })
+            .collect();
+
+        if program == valid_program.0 {
+            return valid_program;
+        }
+
+        analyses.invalidate_changes(&valid_program.0, &program, &[Analysis::Cfg, Analysis::Dominators, Analysis::PointsTo]);
+
+        // Do not remove this validation check.  It is there to help you catch the
+        // bugs early on.  The autograder uses an internal final validation check.
+        valid_program = program.validate().unwrap();
+    }
+}
+
+/// Dead store elimination for a single function
+fn dse_func(pre_bb: &Map<BbId, Env>, pre_inst: &Map<InstId, Env>, func: &Function) -> Function {
+    let mut opt_func = func.clone();
+
+    for (bbid, bb) in &mut opt_func.body {
+        let mut idx = 0;
+        bb.insts.retain(|inst| {
+            // the state after an instruction is the state before the next one
+            // (or before the terminal).
+            let post = pre_inst.get(&(bbid.clone(), idx + 1)).unwrap_or(&pre_bb[bbid]);
+            let keep = !removable(inst) || post.live_defs.contains(&(bbid.clone(), idx));
+            idx += 1;
+            keep
+        });
+    }
+
+    // remove the locals no instruction mentions anymore.
+    let used: Set<VarId> = opt_func
+        .body
+        .values()
+        .flat_map(|bb| {
+            bb.insts
+                .iter()
+                .flat_map(|inst| inst.def().into_iter().chain(inst.uses()).chain(addr_of_operand(inst)))
+                .chain(bb.term.def())
+                .chain(bb.term.uses())
+                .cloned()
+                .collect::<Vec<_>>()
         })
         .collect();
+    opt_func.locals.retain(|x| used.contains(x));
 
-    // Do not remove this validation check.  It is there to help you catch the
-    // bugs early on.  The autograder uses an internal final validation check.
-    program.validate().unwrap()
+    opt_func
 }
 
-/// Dead store elimination for a single function
-fn dse_func(_pre_bb: Map<BbId, Env>, _pre_inst: Map<InstId, Env>, _func: &Function) -> Function {
-    todo!("remove unused stores");
-    // @clippydodge todo!("remove unused variables")
+// Whether the instruction only assigns its left-hand side: it has no other
+// effect and cannot fail at runtime.
+pub(super) fn removable(inst: &Instruction) -> bool {
+    use Instruction::*;
+    match inst {
+        AddrOf { .. } | Cmp { .. } | Copy { .. } => true,
+        Arith { aop: ArithmeticOp::Divide, op2, .. } => matches!(op2, Operand::CInt(n) if *n != 0),
+        Arith { .. } => true,
+        Alloc { .. } | CallExt { .. } | Gep { .. } | Gfp { .. } | Load { .. } | Phi { .. } | Store { .. } => false,
+    }
+}
+
+fn addr_of_operand(inst: &Instruction) -> Option<&VarId> {
+    match inst {
+        Instruction::AddrOf { op, .. } => Some(op),
+        _ => None,
+    }
 }
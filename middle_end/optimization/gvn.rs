@@ -0,0 +1,186 @@
+//! Global value numbering.
+//!
+//! Walks the dominator tree keeping a scoped hash table from the pure
+//! expressions computed so far to the variable holding their value.  A
+//! computation whose expression is in the table when it is reached is
+//! redundant: some block dominating it already computed the same value, so it
+//! is replaced with a copy.  The expressions of a block leave the table once
+//! its dominator subtree is done.
+//!
+//! Arithmetic, comparisons and address computations (`$gfp`, `$gep`) are pure.
+//! Loads and calls are not, since memory can change between two of them.
+//!
+//! Only variables in SSA form (see [ssa_vars]) have a value number, so the
+//! pass does the most on SSA programs, but it is correct on any program: an
+//! expression reading a variable that is assigned more than once is never
+//! reused.  Operands are numbered through copies, and the operands of
+//! commutative operations are matched in either order.
+
+use std::collections::HashMap;
+
+use super::*;
+use crate::commons::*;
+use crate::middle_end::analysis::{dominators::DomTree, Cfg};
+use crate::middle_end::lir::*;
+use crate::middle_end::optimization::{pass_manager::Analyses, ssa::ssa_vars};
+
+/// The actual optimization pass.
+pub fn gvn(valid_program: Valid<Program>) -> Valid<Program> {
+    gvn_with(valid_program, &mut Analyses::default())
+}
+
+/// The pass, reusing the CFGs and dominator trees already computed.
+pub fn gvn_with(valid_program: Valid<Program>, analyses: &mut Analyses) -> Valid<Program> {
+    let functions = valid_program
+        .0
+        .functions
+        .iter()
+        .map(|(id, f)| {
+            let cfg = analyses.cfg(&valid_program, id);
+            let doms = analyses.dominators(&valid_program, id);
+            (id.clone(), gvn_func(f, &cfg, &doms))
+        })
+        .collect();
+
+    let mut program = valid_program.0;
+    program.functions = functions;
+
+    // Do not remove this validation check.  It is there to help you catch the
+    // bugs early on.  The autograder uses an internal final validation check.
+    program.validate().unwrap()
+}
+
+// A pure expression, over the value numbers of its operands.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+enum Expr {
+    Arith(ArithmeticOp, Operand, Operand),
+    Cmp(ComparisonOp, Operand, Operand),
+    Gep(Operand, Operand),
+    Gfp(Operand, FieldId),
+}
+
+impl Expr {
+    // the same expression with its operands swapped, if that has the same
+    // value.
+    fn swapped(&self) -> Option<Expr> {
+        use ArithmeticOp::*;
+        use ComparisonOp::*;
+
+        match self {
+            Expr::Arith(aop @ (Add | Multiply), op1, op2) => Some(Expr::Arith(*aop, op2.clone(), op1.clone())),
+            Expr::Cmp(rop @ (Eq | Neq), op1, op2) => Some(Expr::Cmp(*rop, op2.clone(), op1.clone())),
+            _ => None,
+        }
+    }
+}
+
+fn gvn_func(func: &Function, cfg: &Cfg, doms: &DomTree) -> Function {
+    let mut numbering = Numbering {
+        ssa_vars: ssa_vars(func, cfg),
+        numbers: Map::new(),
+        table: HashMap::new(),
+    };
+    let mut out = func.clone();
+
+    // a block's expressions are removed once its whole subtree is done.
+    let mut stack = vec![(doms.root().clone(), None)];
+    while let Some((bbid, added)) = stack.pop() {
+        if let Some(added) = added {
+            for expr in added {
+                numbering.table.remove(&expr);
+            }
+            continue;
+        }
+
+        let mut added = vec![];
+        for inst in &mut out.body.get_mut(&bbid).unwrap().insts {
+            if let Some(expr) = numbering.value_number(inst, &mut added) {
+                let lhs = inst.def().unwrap().clone();
+                *inst = Instruction::Copy { lhs, op: Operand::Var(expr) };
+            }
+        }
+
+        stack.push((bbid.clone(), Some(added)));
+        stack.extend(doms.children(&bbid).map(|child| (child.clone(), None)));
+    }
+
+    out
+}
+
+struct Numbering {
+    ssa_vars: Set<VarId>,
+    // the value number of the SSA variables known to hold the same value as
+    // an earlier one, or a constant.
+    numbers: Map<VarId, Operand>,
+    // the variable holding the value of each expression available here.
+    table: HashMap<Expr, VarId>,
+}
+
+impl Numbering {
+    // the value number of an operand, if it has one.
+    fn number(&self, op: &Operand) -> Option<Operand> {
+        match op {
+            Operand::CInt(_) => Some(op.clone()),
+            Operand::Var(x) if self.ssa_vars.contains(x) => Some(self.numbers.get(x).cloned().unwrap_or_else(|| op.clone())),
+            Operand::Var(_) => None,
+        }
+    }
+
+    fn expr(&self, inst: &Instruction) -> Option<Expr> {
+        use Instruction::*;
+
+        let expr = match inst {
+            Arith { aop, op1, op2, .. } => Expr::Arith(*aop, self.number(op1)?, self.number(op2)?),
+            // `a > b` is `b < a`.
+            Cmp { rop: ComparisonOp::Greater, op1, op2, .. } => {
+                Expr::Cmp(ComparisonOp::Less, self.number(op2)?, self.number(op1)?)
+            }
+            Cmp { rop: ComparisonOp::GreaterEq, op1, op2, .. } => {
+                Expr::Cmp(ComparisonOp::LessEq, self.number(op2)?, self.number(op1)?)
+            }
+            Cmp { rop, op1, op2, .. } => Expr::Cmp(*rop, self.number(op1)?, self.number(op2)?),
+            Gep { src, idx, .. } => Expr::Gep(self.number(&Operand::Var(src.clone()))?, self.number(idx)?),
+            Gfp { src, field, .. } => Expr::Gfp(self.number(&Operand::Var(src.clone()))?, field.clone()),
+            _ => return None,
+        };
+        Some(expr)
+    }
+
+    // number the value `inst` defines.  Returns the variable already holding
+    // it if the instruction is redundant; otherwise the expression it computes
+    // becomes available, and is recorded in `added`.
+    fn value_number(&mut self, inst: &Instruction, added: &mut Vec<Expr>) -> Option<VarId> {
+        let lhs = inst.def()?;
+
+        if let Instruction::Copy { op, .. } = inst {
+            if let Some(n) = self.number(op).filter(|_| self.ssa_vars.contains(lhs)) {
+                self.numbers.insert(lhs.clone(), n);
+            }
+            return None;
+        }
+
+        let expr = self.expr(inst)?;
+        let available = self
+            .table
+            .get(&expr)
+            .or_else(|| expr.swapped().and_then(|swapped| self.table.get(&swapped)))
+            .cloned();
+
+        match available {
+            Some(x) => {
+                if self.ssa_vars.contains(lhs) {
+                    self.numbers.insert(lhs.clone(), self.number(&Operand::Var(x.clone())).unwrap());
+                }
+                Some(x)
+            }
+            // the expression can only be reused if its variable keeps the
+            // value.
+            None if self.ssa_vars.contains(lhs) => {
+                self.table.insert(expr.clone(), lhs.clone());
+                added.push(expr);
+                None
+            }
+            None => None,
+        }
+    }
+}
@@ -6,24 +6,30 @@
 //! inliner are as follows:
 //!
 //! - [NameGenerator] maintains a set of used names in the caller's body, and
-//! generates mangled names when needed.
+//!   generates mangled names when needed.
 //!
 //! - [gen_inlined_code] contains the core of the inlining functionality.  It
-//! creates a copy of the callee's control flow graph, using [NameGenerator] to
-//! generate mangled names for basic blocks and variables.
+//!   creates a copy of the callee's control flow graph, using [NameGenerator] to
+//!   generate mangled names for basic blocks and variables.
 //!
 //! - `inline_call` inlines given call site by inserting the inlined code into
-//! the caller's CFG.
+//!   the caller's CFG.
 //!
 //! - [inline_call_sites] inlines all given call sites.  This function can be
-//! used for implementing different inlining strategies.
+//!   used for implementing different inlining strategies.
 //!
 //! - [inline_leaf_functions] implements a simple inlining strategy: it inlines
-//! direct calls to leaf functions (functions that do not make any internal
-//! calls) in the original program's call graph.
+//!   direct calls to leaf functions (functions that do not make any internal
+//!   calls) in the original program's call graph.
+//!
+//! - [inline_small_fns] and [inline_within_budget] inline transitively, going
+//!   up the call graph from the leaves, and differ in which calls they pick:
+//!   the ones to small enough callees, or the cheapest (or hottest) ones until
+//!   the program has grown by a given number of instructions.
 
 use crate::commons::Valid;
 
+use super::super::analysis::call_graph::CallGraph;
 use super::super::lir::*;
 use std::collections::{BTreeMap as Map, BTreeSet as Set};
 
@@ -67,8 +73,16 @@ impl NameGenerator {
     //   scope (e.g. for allocation site IDs), `scope` is the empty string.
     // - `name` is the name of the original variable.
     // - `N` is a number to ensure that the freshly-generated number is unique.
-    pub(super) fn _mangle_var(&mut self, _bb: &BbId, _orig: &VarId) -> VarId {
-        todo!()
+    pub(super) fn _mangle_var(&mut self, bb: &BbId, orig: &VarId) -> VarId {
+        let scope = orig.scope().map(|f| f.name().to_string()).unwrap_or_default();
+        let name = Self::_mangle_name(
+            &format!("{}.{}.{}", bb, scope, orig.name()),
+            &mut self.declared_vars,
+            |name| name.to_string(),
+        );
+
+        // allocation site IDs stay unscoped.
+        var_id(&name, orig.typ(), orig.scope().map(|_| self._scope.clone()))
     }
 
     // Create a fresh basic block ID based on the given call site and basic block.
@@ -96,11 +110,16 @@ impl NameGenerator {
     // This is a helper you can implement and use for implementing mangle_var
     // and mangle_bb.
     fn _mangle_name<Name: Ord + Eq + Clone, Builder: Fn(&str) -> Name>(
-        _prefix: &str,
-        _existing_names: &mut Set<Name>,
-        _builder: Builder,
+        prefix: &str,
+        existing_names: &mut Set<Name>,
+        builder: Builder,
     ) -> Name {
-        todo!()
+        let name = (1..)
+            .map(|n| builder(&format!("{prefix}.{n}")))
+            .find(|name| !existing_names.contains(name))
+            .unwrap();
+        existing_names.insert(name.clone());
+        name
     }
 }
 
@@ -114,48 +133,190 @@ impl NameGenerator {
 /// After adding the returned basic blocks, the call can be replaced with a jump
 /// to the entry block.
 pub fn gen_inlined_code(
-    _call_site: &BbId,
-    _lhs: &Option<VarId>,
-    _callee: &Function,
-    _args: &[Operand],
-    _next_bb: BbId,
-    _generator: &mut NameGenerator,
+    call_site: &BbId,
+    lhs: &Option<VarId>,
+    callee: &Function,
+    args: &[Operand],
+    next_bb: BbId,
+    generator: &mut NameGenerator,
 ) -> (Map<BbId, BasicBlock>, Set<VarId>, BbId) {
-    // Inlining:
-    //  - Generate fresh basic block IDs
-    //  - Rewrite each basic block in terminals
-    //  - Rewrite ret instructions to (1) set lhs, (2) jump to next_bb
-    //  - Rewrite all instructions
-    //  - Prepend $copy instructions to the beginning of the entry block to copy
-    //    the arguments
-
-    todo!()
+    // globals and functions are not renamed, so they are not in `vars`.
+    let vars: Map<&VarId, VarId> = callee
+        .params
+        .iter()
+        .chain(&callee.locals)
+        .map(|x| (x, generator._mangle_var(call_site, x)))
+        .collect();
+    let bbs: Map<&BbId, BbId> = callee
+        .body
+        .keys()
+        .map(|bb| (bb, generator._mangle_bb(call_site, &callee.id, bb)))
+        .collect();
+
+    let rename = |x: &mut VarId| {
+        if let Some(y) = vars.get(x) {
+            *x = y.clone();
+        }
+    };
+
+    let mut body = Map::new();
+    for bb in callee.body.values() {
+        let mut insts = vec![];
+        if bb.id == bb_id("entry") {
+            insts.extend(callee.params.iter().zip(args).map(|(x, arg)| Instruction::Copy {
+                lhs: vars[x].clone(),
+                op: arg.clone(),
+            }));
+            // locals start at zero on every call, so the ones read before they
+            // are assigned must not keep their value from the last time the
+            // inlined code ran.
+            insts.extend(maybe_uninitialized(callee).into_iter().map(|x| Instruction::Copy {
+                lhs: vars[x].clone(),
+                op: Operand::CInt(0),
+            }));
+        }
+
+        for inst in &bb.insts {
+            let mut inst = inst.clone();
+            inst.def_mut().map(rename);
+            inst.uses_mut().into_iter().for_each(rename);
+            match &mut inst {
+                Instruction::AddrOf { op, .. } => rename(op),
+                // every copy of an allocation site is a different site.
+                Instruction::Alloc { id, .. } => *id = generator._mangle_var(call_site, id),
+                _ => {}
+            }
+            insts.push(inst);
+        }
+
+        let term = match &bb.term {
+            Terminal::Ret(op) => {
+                if let (Some(lhs), Some(op)) = (lhs, op) {
+                    let mut op = op.clone();
+                    op.as_var_mut().map(rename);
+                    insts.push(Instruction::Copy { lhs: lhs.clone(), op });
+                }
+                Terminal::Jump(next_bb.clone())
+            }
+            term => {
+                let mut term = term.clone();
+                term.def_mut().map(rename);
+                term.uses_mut().into_iter().for_each(rename);
+                for succ in term.successors_mut() {
+                    *succ = bbs[succ].clone();
+                }
+                term
+            }
+        };
+
+        let id = bbs[&bb.id].clone();
+        body.insert(id.clone(), BasicBlock { id, insts, term });
+    }
+
+    (body, vars.into_values().collect(), bbs[&bb_id("entry")].clone())
+}
+
+// The locals of a function that it may read before assigning them.
+fn maybe_uninitialized(func: &Function) -> Set<&VarId> {
+    let entry = bb_id("entry");
+
+    // the locals assigned on every path to the start of each block.
+    let mut assigned: Map<&BbId, Set<&VarId>> = Map::from([(&entry, Set::new())]);
+    let mut worklist = vec![&entry];
+    while let Some(bbid) = worklist.pop() {
+        let bb = &func.body[bbid];
+        let mut out = assigned[bbid].clone();
+        out.extend(bb.insts.iter().filter_map(Instruction::def).chain(bb.term.def()));
+
+        for succ in bb.term.successors() {
+            let old = assigned.get(succ);
+            let new: Set<&VarId> = match old {
+                Some(old) => old.intersection(&out).cloned().collect(),
+                None => out.clone(),
+            };
+            if old != Some(&new) {
+                assigned.insert(succ, new);
+                worklist.push(succ);
+            }
+        }
+    }
+
+    let mut uninitialized = Set::new();
+    for (bbid, mut defined) in assigned {
+        let bb = &func.body[bbid];
+        for inst in &bb.insts {
+            let addr_of = match inst {
+                Instruction::AddrOf { op, .. } => Some(op),
+                _ => None,
+            };
+            uninitialized.extend(inst.uses().into_iter().chain(addr_of).filter(|x| !defined.contains(x)));
+            defined.extend(inst.def());
+        }
+        uninitialized.extend(bb.term.uses().into_iter().filter(|x| !defined.contains(x)));
+    }
+
+    uninitialized.retain(|x| func.locals.contains(*x));
+    uninitialized
 }
 
 // Inline given call at the end of the basic block with given ID.
 //
-// This function returns an error if the given basic block does not end with a
-// $call_dir instruction.
-fn _inline_call(
-    _program: &Program,
-    _caller: &mut Function,
-    _call_site: BbId,
-    _generator: &mut NameGenerator,
-) {
-    todo!()
+// This function panics if the given basic block does not end with a $call_dir
+// instruction.
+fn inline_call(program: &Program, caller: &mut Function, call_site: BbId, generator: &mut NameGenerator) {
+    let Terminal::CallDirect { lhs, callee, args, next_bb } = &caller.body[&call_site].term else {
+        panic!("{call_site} in {} does not end with $call_dir", caller.id);
+    };
+
+    let (body, locals, entry) = gen_inlined_code(
+        &call_site,
+        lhs,
+        &program.functions[callee],
+        args,
+        next_bb.clone(),
+        generator,
+    );
+
+    caller.body.get_mut(&call_site).unwrap().term = Terminal::Jump(entry);
+    caller.body.extend(body);
+    caller.locals.extend(locals);
 }
 
 /// Inline given call sites.  The call sites are grouped by function for a more
 /// efficient implementation.  All inlining happens simultaneously, later
 /// inlines don't copy the callee result from previous inlines.
-pub fn inline_call_sites(_program: &Program, _call_sites: &Map<FuncId, Set<BbId>>) -> Program {
-    todo!()
+pub fn inline_call_sites(program: &Program, call_sites: &Map<FuncId, Set<BbId>>) -> Program {
+    let mut inlined = program.clone();
+
+    for (f, sites) in call_sites {
+        let mut caller = program.functions[f].clone();
+        let mut generator = NameGenerator::new(&caller);
+        for call_site in sites {
+            inline_call(program, &mut caller, call_site.clone(), &mut generator);
+        }
+        inlined.functions.insert(f.clone(), caller);
+    }
+
+    inlined
 }
 
-/// A compiler pass that inlines all leaf functions in the call graph formed by
-/// direct calls.
-pub fn inline_leaf_functions(_program: Valid<Program>) -> Valid<Program> {
-    todo!()
+/// A compiler pass that inlines all direct calls to leaf functions in the call
+/// graph.
+pub fn inline_leaf_functions(program: Valid<Program>) -> Valid<Program> {
+    let graph = CallGraph::new(&program.0);
+
+    let call_sites = graph
+        .functions()
+        .map(|f| {
+            let sites = direct_calls(&graph, f)
+                .filter(|(_, g)| graph.is_leaf(g))
+                .map(|(bb, _)| bb.clone())
+                .collect();
+            (f.clone(), sites)
+        })
+        .collect();
+
+    inline_call_sites(&program.0, &call_sites).validate().unwrap()
 }
 
 /// A compiler pass that inlines all non-recursive calls where the number of
@@ -163,10 +324,103 @@ pub fn inline_leaf_functions(_program: Valid<Program>) -> Valid<Program> {
 /// given bounds.
 ///
 /// The number of instructions is the total number of instructions in the body.
-pub fn inline_small_fns(
-    _program: Valid<Program>,
-    _param_bound: usize,
-    _inst_bound: usize,
-) -> Valid<Program> {
-    todo!()
+/// Callees are inlined into before their callers, so the bound applies to a
+/// callee with the calls it made inlined, and inlining is transitive.
+pub fn inline_small_fns(program: Valid<Program>, param_bound: usize, inst_bound: usize) -> Valid<Program> {
+    let inlined = inline_bottom_up(program.0, |program, calls| {
+        let mut call_sites: Map<FuncId, Set<BbId>> = Map::new();
+        for (caller, call_site, callee) in calls {
+            let callee = &program.functions[&callee];
+            let insts: usize = callee.body.values().map(|bb| bb.insts.len()).sum();
+            if callee.params.len() < param_bound && insts < inst_bound {
+                call_sites.entry(caller).or_default().insert(call_site);
+            }
+        }
+        call_sites
+    });
+
+    inlined.validate().unwrap()
+}
+
+/// The number of times each call site ran in some execution, keyed by the
+/// caller and the block ending in the call.
+pub type CallCounts = Map<(FuncId, BbId), u64>;
+
+/// A compiler pass that inlines non-recursive calls as long as the program
+/// grows by at most `budget` instructions in total, counting terminals.
+///
+/// Inlining a call adds the callee's body, a copy per argument and a copy of
+/// the result.  Calls are considered from the bottom of the call graph up, so
+/// inlining is transitive, and the callers sharing a strongly connected
+/// component inline the cheapest calls first.  Given a profile, the calls that
+/// ran the most go first instead, and the ones that never ran are left alone.
+pub fn inline_within_budget(program: Valid<Program>, budget: usize, profile: Option<&CallCounts>) -> Valid<Program> {
+    let mut remaining = budget;
+
+    let inlined = inline_bottom_up(program.0, |program, calls| {
+        let mut ranked: Vec<(u64, usize, FuncId, BbId)> = calls
+            .into_iter()
+            .map(|(caller, call_site, callee)| {
+                let count = profile.map_or(1, |profile| {
+                    profile.get(&(caller.clone(), call_site.clone())).copied().unwrap_or(0)
+                });
+                let returns = program.functions[&caller].body[&call_site].term.def().is_some();
+                let growth = growth(&program.functions[&callee]) + usize::from(returns);
+                (count, growth, caller, call_site)
+            })
+            .filter(|(count, ..)| *count > 0)
+            .collect();
+        ranked.sort_by(|(c1, g1, ..), (c2, g2, ..)| c2.cmp(c1).then(g1.cmp(g2)));
+
+        let mut call_sites: Map<FuncId, Set<BbId>> = Map::new();
+        for (_, growth, caller, call_site) in ranked {
+            if growth <= remaining {
+                remaining -= growth;
+                call_sites.entry(caller).or_default().insert(call_site);
+            }
+        }
+        call_sites
+    });
+
+    inlined.validate().unwrap()
+}
+
+// The number of instructions and terminals inlining a call to `callee` adds,
+// besides the copy of the result.
+fn growth(callee: &Function) -> usize {
+    let body: usize = callee.body.values().map(|bb| bb.insts.len() + 1).sum();
+    body + callee.params.len()
+}
+
+// Inline the call sites `choose` picks for each strongly connected component of
+// the call graph, from the bottom up.  `choose` is given the calls leaving the
+// component as (caller, call site, callee) triples, and the program where all
+// the callees are done.
+fn inline_bottom_up<Choose>(mut program: Program, mut choose: Choose) -> Program
+where
+    Choose: FnMut(&Program, Vec<(FuncId, BbId, FuncId)>) -> Map<FuncId, Set<BbId>>,
+{
+    let graph = CallGraph::new(&program);
+
+    for scc in graph.bottom_up() {
+        let calls = scc
+            .iter()
+            .flat_map(|f| direct_calls(&graph, f).map(move |(bb, g)| (f.clone(), bb.clone(), g.clone())))
+            .filter(|(_, _, g)| !scc.contains(g))
+            .collect();
+
+        let call_sites = choose(&program, calls);
+        program = inline_call_sites(&program, &call_sites);
+    }
+
+    program
+}
+
+// The direct calls `f` makes, with the blocks they end.
+fn direct_calls<'a>(graph: &'a CallGraph, f: &FuncId) -> impl Iterator<Item = (&'a BbId, &'a FuncId)> {
+    graph
+        .call_sites(f)
+        .iter()
+        .filter(|(_, site)| !site.indirect)
+        .flat_map(|(bb, site)| site.callees.iter().map(move |g| (bb, g)))
 }
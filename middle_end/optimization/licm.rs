@@ -0,0 +1,233 @@
+//! Loop-invariant code motion.
+//!
+//! An instruction is invariant in a loop if it is pure and its operands are
+//! constants, variables the loop never assigns, or variables defined by other
+//! invariant instructions.  Invariant instructions are moved to the preheader
+//! of the loop, which is created if the loop has none: a block jumping to the
+//! header that every entry into the loop goes through.  Loops are processed
+//! innermost first, so an instruction can move out of several loops.
+//!
+//! Only instructions defining a variable in SSA form (see [ssa_vars]) move, so
+//! their value is the same everywhere it is read.  Moved instructions run even
+//! if the loop would not have reached them, so the ones that can fail at
+//! runtime (`$gep`, `$load` and divisions by anything but a non-zero constant)
+//! only move if their block runs on every way out of the loop.  A `$load` also
+//! needs a loop with no calls, whose stores, according to the points-to
+//! analysis, cannot write where it reads, and which assigns none of the
+//! variables it may read.
+
+use super::*;
+use super::ssa::{ssa_vars, var_names, FreshNames};
+use crate::commons::*;
+use crate::middle_end::analysis::{loops::Loop, Cfg, InstId};
+use crate::middle_end::lir::*;
+use crate::middle_end::optimization::pass_manager::Analyses;
+
+/// The actual optimization pass.
+pub fn licm(valid_program: Valid<Program>) -> Valid<Program> {
+    licm_with(valid_program, &mut Analyses::default())
+}
+
+/// The pass, reusing the points-to solution and the CFGs already computed.
+pub fn licm_with(valid_program: Valid<Program>, analyses: &mut Analyses) -> Valid<Program> {
+    let pts_to = analyses.points_to(&valid_program);
+    let functions = valid_program
+        .0
+        .functions
+        .iter()
+        .map(|(id, f)| {
+            let cfg = analyses.cfg(&valid_program, id);
+            (id.clone(), licm_func(f, &cfg, &valid_program.0, &pts_to))
+        })
+        .collect();
+
+    let mut program = valid_program.0;
+    program.functions = functions;
+
+    // Do not remove this validation check.  It is there to help you catch the
+    // bugs early on.  The autograder uses an internal final validation check.
+    program.validate().unwrap()
+}
+
+fn licm_func(func: &Function, cfg: &Cfg, program: &Program, pts_to: &Map<String, Set<String>>) -> Function {
+    let mut func = func.clone();
+
+    let mut headers: Vec<(usize, BbId)> = cfg.loops.loops.values().map(|l| (l.depth, l.header.clone())).collect();
+    headers.sort_by(|(d1, h1), (d2, h2)| d2.cmp(d1).then(h1.cmp(h2)));
+
+    for (_, header) in headers {
+        // the entry cannot have a predecessor to be the preheader.
+        if header == cfg.entry {
+            continue;
+        }
+
+        // a preheader added for an inner loop changes the cfg.
+        let cfg = Cfg::new(&func, program.globals.clone(), program.structs.clone());
+        let l = &cfg.loops.loops[&header];
+
+        let invariants = invariants(&func, &cfg, l, pts_to);
+        if invariants.is_empty() {
+            continue;
+        }
+
+        let moved: Vec<Instruction> = invariants
+            .iter()
+            .map(|(bbid, i)| func.body[bbid].insts[*i].clone())
+            .collect();
+        for bbid in &l.body {
+            let mut i = 0;
+            func.body.get_mut(bbid).unwrap().insts.retain(|_| {
+                i += 1;
+                !invariants.contains(&(bbid.clone(), i - 1))
+            });
+        }
+
+        let preheader = preheader(&mut func, &cfg, l, program);
+        func.body.get_mut(&preheader).unwrap().insts.extend(moved);
+    }
+
+    func
+}
+
+// The invariant instructions of a loop that can move out of it, in an order
+// where every instruction comes after those defining its operands.
+fn invariants(func: &Function, cfg: &Cfg, l: &Loop, pts_to: &Map<String, Set<String>>) -> Vec<InstId> {
+    let ssa_vars = ssa_vars(func, cfg);
+    let doms = cfg.dom_tree();
+
+    let blocks: Vec<&BasicBlock> = doms
+        .preorder()
+        .iter()
+        .filter(|bb| l.contains(bb))
+        .map(|bb| &func.body[bb])
+        .collect();
+
+    let assigned: Set<&VarId> = blocks
+        .iter()
+        .flat_map(|bb| bb.insts.iter().filter_map(Instruction::def).chain(bb.term.def()))
+        .collect();
+
+    // the blocks that run whenever the loop is left.
+    let always_run = |bb: &BbId| l.exits.iter().all(|(from, _)| doms.dominates(bb, from));
+
+    let may_write = |src: &VarId| {
+        let has_calls = blocks.iter().any(|bb| {
+            matches!(bb.term, Terminal::CallDirect { .. } | Terminal::CallIndirect { .. })
+                || bb.insts.iter().any(|inst| matches!(inst, Instruction::CallExt { .. }))
+        });
+        let pts = |x: &VarId| pts_to.get(&x.to_scope_string()).filter(|pts| !pts.is_empty());
+
+        // a pointer with no points-to set is one the analysis knows nothing
+        // about.
+        let Some(locs) = pts(src).filter(|_| !has_calls) else {
+            return true;
+        };
+
+        // an address-taken variable is written by assigning it too.
+        assigned.iter().any(|x| locs.contains(&x.to_scope_string()))
+            || blocks.iter().flat_map(|bb| &bb.insts).any(|inst| match inst {
+                Instruction::Store { dst, .. } => pts(dst).is_none_or(|dst| !dst.is_disjoint(locs)),
+                _ => false,
+            })
+    };
+
+    let mut invariant: Set<&VarId> = Set::new();
+    let mut moved: Vec<InstId> = vec![];
+    let mut changed = true;
+    while changed {
+        changed = false;
+
+        for bb in &blocks {
+            for (i, inst) in bb.insts.iter().enumerate() {
+                let Some(lhs) = inst.def().filter(|lhs| ssa_vars.contains(*lhs) && !invariant.contains(lhs)) else {
+                    continue;
+                };
+
+                let operands_invariant = inst.uses().into_iter().all(|x| {
+                    invariant.contains(x) || !(assigned.contains(x) || x.is_global() || cfg.addr_taken.contains(x))
+                });
+
+                let movable = operands_invariant
+                    && match inst {
+                        Instruction::Arith { aop: ArithmeticOp::Divide, op2: Operand::CInt(n), .. } => *n != 0 || always_run(&bb.id),
+                        Instruction::Arith { aop: ArithmeticOp::Divide, .. } | Instruction::Gep { .. } => always_run(&bb.id),
+                        Instruction::Arith { .. } | Instruction::Cmp { .. } | Instruction::Gfp { .. } => true,
+                        Instruction::Load { src, .. } => always_run(&bb.id) && !may_write(src),
+                        _ => false,
+                    };
+
+                if movable {
+                    invariant.insert(lhs);
+                    moved.push((bb.id.clone(), i));
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    moved
+}
+
+// The preheader of a loop, creating it if needed.  The phis of the header get
+// a single argument for all the predecessors outside the loop, merged by a phi
+// in the preheader if they differ.
+fn preheader(func: &mut Function, cfg: &Cfg, l: &Loop, program: &Program) -> BbId {
+    // moved instructions must run right before entering the loop.
+    if let Some(preheader) = l.preheader.as_ref().filter(|p| matches!(func.body[*p].term, Terminal::Jump(_))) {
+        return preheader.clone();
+    }
+
+    let header = &l.header;
+    let outside: Vec<&BbId> = cfg.pred(header).filter(|pred| !l.contains(pred)).collect();
+
+    let mut bb_names = FreshNames {
+        taken: func.body.keys().map(|bb| bb.name().to_string()).collect(),
+    };
+    let mut var_names = var_names(func, &program.globals);
+    let preheader = bb_id(&bb_names.fresh(&format!("{header}_preheader")));
+
+    let mut phis = vec![];
+    let mut new_locals = vec![];
+    for inst in &mut func.body.get_mut(header).unwrap().insts {
+        let Instruction::Phi { lhs, args } = inst else {
+            break;
+        };
+
+        let mut by_pred: Map<&BbId, Operand> = cfg.pred(header).zip(args.iter().cloned()).collect();
+        let entering: Vec<Operand> = outside.iter().map(|pred| by_pred.remove(pred).unwrap()).collect();
+        let arg = if entering.iter().all(|arg| *arg == entering[0]) {
+            entering[0].clone()
+        } else {
+            let x = var_names.fresh_var(lhs);
+            new_locals.push(x.clone());
+            phis.push(Instruction::Phi {
+                lhs: x.clone(),
+                args: entering,
+            });
+            Operand::Var(x)
+        };
+
+        by_pred.insert(&preheader, arg);
+        *args = by_pred.into_values().collect();
+    }
+    func.locals.extend(new_locals);
+
+    for pred in outside {
+        for succ in func.body.get_mut(pred).unwrap().term.successors_mut() {
+            if succ == header {
+                *succ = preheader.clone();
+            }
+        }
+    }
+
+    func.body.insert(
+        preheader.clone(),
+        BasicBlock {
+            id: preheader.clone(),
+            insts: phis,
+            term: Terminal::Jump(header.clone()),
+        },
+    );
+
+    preheader
+}
@@ -0,0 +1,384 @@
+//! The pass manager.
+//!
+//! A [PassManager] runs a pipeline of passes, where a step is either a pass or
+//! a group of steps repeated until the program stops changing.  The pipelines
+//! named by [PassManager::pipeline] are what `cfc -O1` and the like run.
+//!
+//! The analysis results the passes need are cached in an [Analyses], per
+//! function (except points-to, which is for the whole program), so a pass can
+//! reuse what the previous ones computed.  Each pass declares the analyses its
+//! changes keep valid: after it runs, the results of the functions it changed
+//! are dropped unless they are preserved.  A pass preserving the [Cfg] and the
+//! dominators must keep the successors of every block, and may only make the
+//! set of address-taken variables smaller.  Results for points-to stay valid
+//! through passes that only remove assignments, or replace them with ones
+//! giving pointers the same targets.
+//!
+//! Passes written as `fn(Valid<Program>) -> Valid<Program>` compute their own
+//! analyses.  The ones taking an [Analyses] as well use the cache.
+
+use std::ops::ControlFlow;
+use std::rc::Rc;
+
+use super::*;
+use crate::commons::*;
+use crate::middle_end::analysis::{dominators::DomTree, liveness, reaching_defs, Cfg, InstId};
+use crate::middle_end::analysis_constraints::points_to;
+use crate::middle_end::lir::*;
+use crate::middle_end::optimization::{
+    constant_prop::*, copy_prop::*, dead_store_elimination::*, gvn::*, inlining::*, licm::*, sccp::*,
+    simplify_cfg::*, ssa::*,
+};
+
+// SECTION: analysis cache
+
+/// The analyses whose results are cached.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Analysis {
+    Cfg,
+    Dominators,
+    ReachingDefs,
+    Liveness,
+    PointsTo,
+}
+
+type DataflowResult<Env> = Rc<(Map<BbId, Env>, Map<InstId, Env>)>;
+type PtsTo = Rc<Map<String, Set<String>>>;
+
+/// Analysis results, computed when first asked for and kept until they are
+/// invalidated.
+#[derive(Default)]
+pub struct Analyses {
+    cfg: Map<FuncId, Rc<Cfg>>,
+    dominators: Map<FuncId, Rc<DomTree>>,
+    reaching_defs: Map<FuncId, DataflowResult<reaching_defs::Env>>,
+    liveness: Map<FuncId, DataflowResult<liveness::Env>>,
+    points_to: Option<PtsTo>,
+    // how many times each analysis ran.
+    runs: Map<Analysis, usize>,
+}
+
+impl Analyses {
+    pub fn cfg(&mut self, program: &Valid<Program>, f: &FuncId) -> Rc<Cfg> {
+        if let Some(cfg) = self.cfg.get(f) {
+            return cfg.clone();
+        }
+
+        self.count(Analysis::Cfg);
+        let func = &program.0.functions[f];
+        let cfg = Rc::new(Cfg::new(func, program.0.globals.clone(), program.0.structs.clone()));
+        self.cfg.insert(f.clone(), cfg.clone());
+        cfg
+    }
+
+    pub fn dominators(&mut self, program: &Valid<Program>, f: &FuncId) -> Rc<DomTree> {
+        if let Some(doms) = self.dominators.get(f) {
+            return doms.clone();
+        }
+
+        let cfg = self.cfg(program, f);
+        self.count(Analysis::Dominators);
+        let doms = Rc::new(cfg.dom_tree());
+        self.dominators.insert(f.clone(), doms.clone());
+        doms
+    }
+
+    pub fn reaching_defs(&mut self, program: &Valid<Program>, f: &FuncId) -> DataflowResult<reaching_defs::Env> {
+        if let Some(rdefs) = self.reaching_defs.get(f) {
+            return rdefs.clone();
+        }
+
+        self.count(Analysis::ReachingDefs);
+        let rdefs = Rc::new(reaching_defs::analyze(program, f.clone()));
+        self.reaching_defs.insert(f.clone(), rdefs.clone());
+        rdefs
+    }
+
+    pub fn liveness(&mut self, program: &Valid<Program>, f: &FuncId) -> DataflowResult<liveness::Env> {
+        if let Some(live) = self.liveness.get(f) {
+            return live.clone();
+        }
+
+        let rdefs = self.reaching_defs(program, f);
+        self.count(Analysis::Liveness);
+        let live = Rc::new(liveness::analyze_with(program, f.clone(), &rdefs));
+        self.liveness.insert(f.clone(), live.clone());
+        live
+    }
+
+    /// The points-to solution of the program (see [points_to]).
+    pub fn points_to(&mut self, program: &Valid<Program>) -> PtsTo {
+        if let Some(pts_to) = &self.points_to {
+            return pts_to.clone();
+        }
+
+        self.count(Analysis::PointsTo);
+        let pts_to = Rc::new(points_to(program));
+        self.points_to = Some(pts_to.clone());
+        pts_to
+    }
+
+    /// How many times an analysis ran, over all functions.
+    pub fn runs(&self, analysis: Analysis) -> usize {
+        self.runs.get(&analysis).copied().unwrap_or(0)
+    }
+
+    fn count(&mut self, analysis: Analysis) {
+        *self.runs.entry(analysis).or_default() += 1;
+    }
+
+    /// Drop the results of `f` that a change to it does not preserve, and the
+    /// points-to solution unless it is preserved.
+    pub fn invalidate(&mut self, f: &FuncId, preserved: &[Analysis]) {
+        use Analysis::*;
+
+        if !preserved.contains(&Cfg) {
+            self.cfg.remove(f);
+        }
+        if !preserved.contains(&Dominators) {
+            self.dominators.remove(f);
+        }
+        if !preserved.contains(&ReachingDefs) {
+            self.reaching_defs.remove(f);
+        }
+        if !preserved.contains(&Liveness) {
+            self.liveness.remove(f);
+        }
+        if !preserved.contains(&PointsTo) {
+            self.points_to = None;
+        }
+    }
+
+    /// Invalidate the results of the functions that differ between two versions
+    /// of the program.  Everything is dropped if the globals or the structs
+    /// changed.
+    pub fn invalidate_changes(&mut self, before: &Program, after: &Program, preserved: &[Analysis]) {
+        if before.globals != after.globals || before.structs != after.structs {
+            let runs = std::mem::take(&mut self.runs);
+            *self = Analyses { runs, ..Analyses::default() };
+            return;
+        }
+
+        for (f, func) in &before.functions {
+            if after.functions.get(f) != Some(func) {
+                self.invalidate(f, preserved);
+            }
+        }
+        // a new function has no results yet, but points-to covers it.
+        if after.functions.keys().any(|f| !before.functions.contains_key(f)) {
+            self.points_to = None;
+        }
+    }
+}
+
+// SECTION: passes
+
+type RunPass = Box<dyn Fn(Valid<Program>, &mut Analyses) -> Valid<Program>>;
+
+/// An optimization pass, with the analyses its changes preserve.
+pub struct Pass {
+    pub name: String,
+    pub preserves: &'static [Analysis],
+    run: RunPass,
+}
+
+impl Pass {
+    pub fn new(
+        name: &str,
+        preserves: &'static [Analysis],
+        run: impl Fn(Valid<Program>, &mut Analyses) -> Valid<Program> + 'static,
+    ) -> Pass {
+        Pass {
+            name: name.to_string(),
+            preserves,
+            run: Box::new(run),
+        }
+    }
+
+    /// A pass that does not use the cache.
+    pub fn basic(name: &str, preserves: &'static [Analysis], run: fn(Valid<Program>) -> Valid<Program>) -> Pass {
+        Pass::new(name, preserves, move |program, _| run(program))
+    }
+
+    /// The pass with the given name, as `cfc -O` takes it.
+    pub fn named(name: &str) -> Result<Pass, String> {
+        use Analysis::*;
+
+        // the passes that only rewrite instructions keep the blocks and their
+        // successors.
+        const SAME_CFG: &[Analysis] = &[Cfg, Dominators];
+
+        let pass = match name {
+            "dse" => Pass::new(name, &[Cfg, Dominators, PointsTo], dead_store_elim_with),
+            "const-prop" => Pass::basic(name, SAME_CFG, constant_prop),
+            "copy-prop" => Pass::basic(name, SAME_CFG, copy_prop),
+            "gvn" => Pass::new(name, SAME_CFG, gvn_with),
+            "inline-leaves" => Pass::basic(name, &[], inline_leaf_functions),
+            "licm" => Pass::new(name, &[], licm_with),
+            "ssa" => Pass::basic(name, SAME_CFG, to_ssa),
+            "from-ssa" => Pass::basic(name, &[], from_ssa),
+            // a program not in SSA form goes there for the pass and back.
+            "sccp" => Pass::basic(name, &[], |program| match program.0.check_ssa() {
+                Ok(()) => sccp(program),
+                Err(_) => from_ssa(sccp(to_ssa(program))),
+            }),
+            "simplify-cfg" => Pass::basic(name, &[PointsTo], simplify_cfg),
+            "adce" => Pass::basic(name, &[PointsTo], aggressive_dce),
+            _ if name.starts_with("inline-small-") => {
+                let bounds = &name["inline-small-".len()..];
+                let (n, m) = bounds
+                    .split_once('-')
+                    .ok_or(format!("unknown optimization pass: {name}"))?;
+                let n = n.parse::<usize>().map_err(|e| e.to_string())?;
+                let m = m.parse::<usize>().map_err(|e| e.to_string())?;
+                Pass::new(name, &[], move |program, _| inline_small_fns(program, n, m))
+            }
+            _ if name.starts_with("inline-budget-") => {
+                let n = name["inline-budget-".len()..]
+                    .parse::<usize>()
+                    .map_err(|e| e.to_string())?;
+                Pass::new(name, &[], move |program, _| inline_within_budget(program, n, None))
+            }
+            _ => return Err(format!("unknown optimization pass: {name}")),
+        };
+
+        Ok(pass)
+    }
+}
+
+// SECTION: pipelines
+
+enum Step {
+    Pass(Pass),
+    // the steps are repeated until a round changes nothing, at most the given
+    // number of times.
+    Fixpoint(Vec<Step>, usize),
+}
+
+/// Runs a pipeline of passes, sharing their analysis results.
+#[derive(Default)]
+pub struct PassManager {
+    steps: Vec<Step>,
+    analyses: Analyses,
+}
+
+impl PassManager {
+    pub fn new() -> PassManager {
+        PassManager::default()
+    }
+
+    /// The pipeline with the given name:
+    ///
+    /// - `O0` runs nothing,
+    /// - `O1` goes to SSA form, propagates constants and copies, numbers
+    ///   values and removes dead code until that changes nothing, hoists loop
+    ///   invariants and goes back out of SSA form to clean up.
+    pub fn pipeline(name: &str) -> Result<PassManager, String> {
+        let mut pm = PassManager::new();
+
+        match name {
+            "O0" => (),
+            "O1" => {
+                pm.add_named("ssa")?;
+                pm.add_fixpoint(&["sccp", "copy-prop", "gvn", "adce"], 4)?;
+                pm.add_named("licm")?;
+                pm.add_named("from-ssa")?;
+                pm.add_named("copy-prop")?;
+                pm.add_named("dse")?;
+                pm.add_named("simplify-cfg")?;
+            }
+            _ => return Err(format!("unknown optimization pipeline: {name}")),
+        }
+
+        Ok(pm)
+    }
+
+    pub fn add(&mut self, pass: Pass) -> &mut PassManager {
+        self.steps.push(Step::Pass(pass));
+        self
+    }
+
+    /// Add a pass, or all the steps of a pipeline, by name.
+    pub fn add_named(&mut self, name: &str) -> Result<&mut PassManager, String> {
+        match Pass::named(name) {
+            Ok(pass) => Ok(self.add(pass)),
+            Err(e) => {
+                let pm = PassManager::pipeline(name).map_err(|_| e)?;
+                self.steps.extend(pm.steps);
+                Ok(self)
+            }
+        }
+    }
+
+    /// Add the passes with the given names, repeated until they change nothing
+    /// or they ran `max_rounds` times.
+    pub fn add_fixpoint(&mut self, names: &[&str], max_rounds: usize) -> Result<&mut PassManager, String> {
+        let passes = names.iter().map(|name| Pass::named(name).map(Step::Pass)).collect::<Result<_, _>>()?;
+        self.steps.push(Step::Fixpoint(passes, max_rounds));
+        Ok(self)
+    }
+
+    /// The analysis results cached so far.
+    pub fn analyses(&self) -> &Analyses {
+        &self.analyses
+    }
+
+    pub fn run(&mut self, program: Valid<Program>) -> Valid<Program> {
+        self.run_observed(program, &mut ())
+    }
+
+    /// Run the pipeline, showing `observer` the program each pass gets and
+    /// returns.  The result is the last program a pass returned, if the
+    /// observer stops the pipeline early.
+    pub fn run_observed(&mut self, program: Valid<Program>, observer: &mut dyn Observer) -> Valid<Program> {
+        match run_steps(&self.steps, program, &mut self.analyses, observer) {
+            ControlFlow::Continue(program) | ControlFlow::Break(program) => program,
+        }
+    }
+}
+
+/// Watches a pipeline run, pass by pass.
+pub trait Observer {
+    fn before_pass(&mut self, _pass: &Pass, _program: &Valid<Program>) {}
+
+    /// Returning false stops the pipeline after this pass.
+    fn after_pass(&mut self, _pass: &Pass, _program: &Valid<Program>) -> bool {
+        true
+    }
+}
+
+impl Observer for () {}
+
+fn run_steps(
+    steps: &[Step],
+    mut program: Valid<Program>,
+    analyses: &mut Analyses,
+    observer: &mut dyn Observer,
+) -> ControlFlow<Valid<Program>, Valid<Program>> {
+    for step in steps {
+        program = match step {
+            Step::Pass(pass) => {
+                observer.before_pass(pass, &program);
+                let before = program.0.clone();
+                let program = (pass.run)(program, analyses);
+                analyses.invalidate_changes(&before, &program.0, pass.preserves);
+                if !observer.after_pass(pass, &program) {
+                    return ControlFlow::Break(program);
+                }
+                program
+            }
+            Step::Fixpoint(steps, max_rounds) => {
+                for _ in 0..*max_rounds {
+                    let before = program.0.clone();
+                    program = run_steps(steps, program, analyses, observer)?;
+                    if program.0 == before {
+                        break;
+                    }
+                }
+                program
+            }
+        };
+    }
+
+    ControlFlow::Continue(program)
+}
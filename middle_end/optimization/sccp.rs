@@ -0,0 +1,309 @@
+//! Sparse conditional constant propagation (Wegman and Zadeck).
+//!
+//! The pass works on SSA form, so each variable has a single lattice value for
+//! the whole function.  The values and the edges that may be executed are
+//! computed together: a block is only evaluated once an edge into it is known
+//! to be executable, a phi only reads the arguments of the executable edges
+//! into its block, and a branch on a constant only makes one of its edges
+//! executable.  This finds every constant that constant propagation finds, and
+//! also the ones only reaching a use through paths that are never taken.
+//!
+//! Variables holding a constant are defined with a copy of it and their uses
+//! read the constant, branches on a constant become jumps, and the blocks no
+//! executable edge reaches are deleted along with the phi arguments coming
+//! from them.
+//!
+//! Only integer variables that are renamed in SSA form are tracked: globals,
+//! address-taken locals, pointers and everything read from memory or returned
+//! by a call are unknown.  Locals are zero on entry to the function.
+
+use super::*;
+use crate::commons::*;
+use crate::middle_end::analysis::{constant_prop::Value, AbstractValue, Cfg};
+use crate::middle_end::lir::*;
+
+use Value as V;
+
+/// The actual optimization pass.  The program must be in SSA form.
+pub fn sccp(valid_program: Valid<Program>) -> Valid<Program> {
+    let mut program = valid_program.0;
+    program.check_ssa().expect("sccp needs a program in SSA form");
+
+    program.functions = program
+        .functions
+        .iter()
+        .map(|(id, f)| (id.clone(), sccp_func(f, &program)))
+        .collect();
+
+    // Do not remove this validation check.  It is there to help you catch the
+    // bugs early on.  The autograder uses an internal final validation check.
+    let program = program.validate().unwrap();
+    program.0.check_ssa().unwrap();
+    program
+}
+
+fn sccp_func(func: &Function, program: &Program) -> Function {
+    let cfg = Cfg::new(func, program.globals.clone(), program.structs.clone());
+    let mut solver = Solver::new(func, &cfg);
+    solver.run();
+
+    let mut out = func.clone();
+    solver.fold(&mut out);
+
+    // deleting the edges never taken can leave a loop with no way out, or no
+    // way to return at all.  Such a loop is never entered, so in that case the
+    // blocks are kept, which is still correct.
+    if !solver.reached.contains(&cfg.exit) {
+        return out;
+    }
+    let mut pruned = out.clone();
+    solver.prune(&mut pruned);
+    let pruned_cfg = Cfg::new(&pruned, program.globals.clone(), program.structs.clone());
+    if pruned_cfg.reversed().reverse_postorder().len() == pruned.body.len() {
+        pruned
+    } else {
+        out
+    }
+}
+
+struct Solver<'a> {
+    func: &'a Function,
+    cfg: &'a Cfg,
+    // the value of every tracked variable; the others are unknown.
+    values: Map<VarId, Value>,
+    executable: Set<(BbId, BbId)>,
+    reached: Set<BbId>,
+    // the blocks reading each tracked variable.
+    users: Map<VarId, Set<BbId>>,
+    worklist: Set<BbId>,
+}
+
+impl<'a> Solver<'a> {
+    fn new(func: &'a Function, cfg: &'a Cfg) -> Self {
+        let tracked = |x: &VarId| x.typ().is_int() && !x.is_global() && !cfg.addr_taken.contains(x);
+
+        let mut values: Map<VarId, Value> = Map::new();
+        for x in func.params.iter().filter(|x| tracked(x)) {
+            values.insert(x.clone(), V::Top);
+        }
+        for x in func.locals.iter().filter(|x| tracked(x)) {
+            values.insert(x.clone(), V::Int(0));
+        }
+
+        let mut users: Map<VarId, Set<BbId>> = Map::new();
+        for (bbid, bb) in &func.body {
+            let defs = bb.insts.iter().filter_map(Instruction::def).chain(bb.term.def());
+            for x in defs.filter(|x| tracked(x)) {
+                // defined in the body: unknown until its definition runs.
+                values.insert(x.clone(), V::Bot);
+            }
+
+            let uses = bb.insts.iter().flat_map(Instruction::uses).chain(bb.term.uses());
+            for x in uses.filter(|x| tracked(x)) {
+                users.entry(x.clone()).or_default().insert(bbid.clone());
+            }
+        }
+
+        Solver {
+            func,
+            cfg,
+            values,
+            executable: Set::new(),
+            reached: Set::from([cfg.entry.clone()]),
+            users,
+            worklist: Set::from([cfg.entry.clone()]),
+        }
+    }
+
+    fn value(&self, op: &Operand) -> Value {
+        match op {
+            Operand::CInt(n) => V::alpha(*n),
+            Operand::Var(x) => *self.values.get(x).unwrap_or(&V::Top),
+        }
+    }
+
+    // lower the value of x to include `val`, revisiting the blocks reading it
+    // if it changed.
+    fn update(&mut self, x: &VarId, val: Value) {
+        let Some(old) = self.values.get_mut(x) else {
+            return;
+        };
+        let new = old.join(&val);
+        if new != *old {
+            *old = new;
+            let users = self.users.get(x).into_iter().flatten();
+            self.worklist.extend(users.filter(|bb| self.reached.contains(*bb)).cloned());
+        }
+    }
+
+    fn run(&mut self) {
+        while let Some(bbid) = self.worklist.pop_first() {
+            let bb = &self.func.body[&bbid];
+
+            for inst in &bb.insts {
+                let val = self.eval(&bbid, inst);
+                if let Some(lhs) = inst.def() {
+                    self.update(lhs, val);
+                }
+            }
+
+            if let Some(lhs) = bb.term.def() {
+                self.update(lhs, V::Top);
+            }
+
+            let succs: Vec<&BbId> = match &bb.term {
+                Terminal::Branch { cond, tt, ff } => match self.value(cond) {
+                    V::Int(0) => vec![ff],
+                    V::Int(_) => vec![tt],
+                    V::Top => vec![tt, ff],
+                    V::Bot => vec![],
+                },
+                term => term.successors(),
+            };
+            for succ in succs {
+                if self.executable.insert((bbid.clone(), succ.clone())) {
+                    self.reached.insert(succ.clone());
+                    self.worklist.insert(succ.clone());
+                }
+            }
+        }
+    }
+
+    fn eval(&self, bbid: &BbId, inst: &Instruction) -> Value {
+        use Instruction::*;
+
+        match inst {
+            Copy { op, .. } => self.value(op),
+            Arith { aop, op1, op2, .. } => arith(*aop, self.value(op1), self.value(op2)),
+            Cmp { rop, op1, op2, .. } => cmp(*rop, self.value(op1), self.value(op2)),
+            // the arguments of the edges that are not executable yet are
+            // ignored.
+            Phi { args, .. } => self
+                .cfg
+                .pred(bbid)
+                .zip(args)
+                .filter(|(pred, _)| self.executable.contains(&((*pred).clone(), bbid.clone())))
+                .fold(V::Bot, |val, (_, arg)| val.join(&self.value(arg))),
+            _ => V::Top,
+        }
+    }
+
+    // the constant x holds, if any.
+    fn constant(&self, x: &VarId) -> Option<i32> {
+        match self.values.get(x) {
+            Some(V::Int(n)) => i32::try_from(*n).ok(),
+            _ => None,
+        }
+    }
+
+    // replace the variables holding a constant with the constant, and define
+    // them with a copy of it.
+    fn fold(&self, func: &mut Function) {
+        for bb in func.body.values_mut() {
+            let ops = bb
+                .insts
+                .iter_mut()
+                .flat_map(|inst| inst.operands_mut())
+                .chain(bb.term.operands_mut());
+            for op in ops {
+                if let Some(n) = op.as_var().and_then(|x| self.constant(x)) {
+                    *op = Operand::CInt(n);
+                }
+            }
+
+            for inst in &mut bb.insts {
+                if let Instruction::Arith { lhs, .. } | Instruction::Cmp { lhs, .. } = inst {
+                    if let Some(n) = self.constant(lhs) {
+                        let lhs = lhs.clone();
+                        *inst = Instruction::Copy { lhs, op: Operand::CInt(n) };
+                    }
+                }
+            }
+        }
+    }
+
+    // delete the blocks and edges that are never executed, along with the
+    // phi arguments coming from them.  Phis left with a single argument, or
+    // holding a constant, become copies.
+    fn prune(&self, func: &mut Function) {
+        func.body.retain(|bbid, _| self.reached.contains(bbid));
+
+        for (bbid, bb) in &mut func.body {
+            if let Terminal::Branch { tt, ff, .. } = &bb.term {
+                let taken = |to: &BbId| self.executable.contains(&(bbid.clone(), to.clone()));
+                match (taken(tt), taken(ff)) {
+                    (true, false) => bb.term = Terminal::Jump(tt.clone()),
+                    (false, true) => bb.term = Terminal::Jump(ff.clone()),
+                    _ => (),
+                }
+            }
+
+            let live_preds: Vec<bool> = self
+                .cfg
+                .pred(bbid)
+                .map(|pred| self.executable.contains(&(pred.clone(), bbid.clone())))
+                .collect();
+
+            let num_phis = bb.insts.iter().take_while(|inst| matches!(inst, Instruction::Phi { .. })).count();
+            let mut phis = vec![];
+            let mut copies = vec![];
+            for inst in bb.insts.drain(..num_phis) {
+                let Instruction::Phi { lhs, args } = inst else { unreachable!() };
+                let mut args: Vec<Operand> = args
+                    .into_iter()
+                    .zip(&live_preds)
+                    .filter_map(|(arg, live)| live.then_some(arg))
+                    .collect();
+
+                if let Some(n) = self.constant(&lhs) {
+                    copies.push(Instruction::Copy { lhs, op: Operand::CInt(n) });
+                } else if args.len() == 1 {
+                    copies.push(Instruction::Copy { lhs, op: args.pop().unwrap() });
+                } else {
+                    phis.push(Instruction::Phi { lhs, args });
+                }
+            }
+            bb.insts.splice(0..0, phis.into_iter().chain(copies));
+        }
+    }
+}
+
+// The value of an arithmetic operation.  Division by zero and results that do
+// not fit an operand are left to run.
+fn arith(aop: ArithmeticOp, v1: Value, v2: Value) -> Value {
+    match (v1, v2) {
+        (V::Bot, _) | (_, V::Bot) => V::Bot,
+        (V::Int(0), V::Top) | (V::Top, V::Int(0)) if aop == ArithmeticOp::Multiply => V::Int(0),
+        (V::Int(x), V::Int(y)) => {
+            let n = match aop {
+                ArithmeticOp::Add => x.checked_add(y),
+                ArithmeticOp::Subtract => x.checked_sub(y),
+                ArithmeticOp::Multiply => x.checked_mul(y),
+                ArithmeticOp::Divide => x.checked_div(y),
+            };
+            match n.and_then(|n| i32::try_from(n).ok()) {
+                Some(n) => V::alpha(n),
+                None => V::Top,
+            }
+        }
+        _ => V::Top,
+    }
+}
+
+fn cmp(rop: ComparisonOp, v1: Value, v2: Value) -> Value {
+    match (v1, v2) {
+        (V::Bot, _) | (_, V::Bot) => V::Bot,
+        (V::Int(x), V::Int(y)) => {
+            let holds = match rop {
+                ComparisonOp::Eq => x == y,
+                ComparisonOp::Neq => x != y,
+                ComparisonOp::Less => x < y,
+                ComparisonOp::LessEq => x <= y,
+                ComparisonOp::Greater => x > y,
+                ComparisonOp::GreaterEq => x >= y,
+            };
+            V::alpha(holds as i32)
+        }
+        _ => V::Top,
+    }
+}
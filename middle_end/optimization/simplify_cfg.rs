@@ -0,0 +1,447 @@
+//! Control flow graph simplification and aggressive dead code elimination.
+//!
+//! [simplify_cfg] cleans up the shapes other passes leave behind, until none
+//! is left:
+//!
+//! - a `$branch` on a constant, or to the same block twice, becomes a `$jump`,
+//! - blocks the entry cannot reach are removed,
+//! - jumps to an empty block that only jumps on go straight to its target,
+//! - a block whose only predecessor only jumps to it is merged into it.
+//!
+//! A branch is only folded if every block left still reaches the exit, as
+//! validation requires, so loops that would never be left are kept.
+//!
+//! [aggressive_dce] assumes instructions are dead until proven useful, unlike
+//! dead store elimination, so it can also remove computations that only feed
+//! each other (e.g. around a loop) and branches that decide nothing useful.  An
+//! instruction is useful if it has an effect besides assigning its left-hand
+//! side (see [removable]), assigns a global or an address-taken variable, or
+//! defines a variable a useful instruction reads.  A branch is useful if a
+//! useful instruction is control dependent on it, or if it leaves a loop, so
+//! that loops are not removed even if they might not terminate.  Useless
+//! branches jump to their nearest useful post-dominator instead.
+//!
+//! Definitions are matched with uses by variable, without a reaching
+//! definitions analysis, so the pass is most precise on SSA programs.
+
+use super::dead_store_elimination::removable;
+use super::*;
+use crate::commons::*;
+use crate::middle_end::analysis::{Cfg, InstId};
+use crate::middle_end::lir::*;
+
+/// The CFG simplification pass.
+pub fn simplify_cfg(valid_program: Valid<Program>) -> Valid<Program> {
+    let mut program = valid_program.0;
+
+    program.functions = program
+        .functions
+        .iter()
+        .map(|(id, f)| (id.clone(), simplify_func(f)))
+        .collect();
+
+    // Do not remove this validation check.  It is there to help you catch the
+    // bugs early on.  The autograder uses an internal final validation check.
+    program.validate().unwrap()
+}
+
+/// The aggressive dead code elimination pass.  The CFG is simplified
+/// afterwards, to remove the blocks that useless branches no longer reach.
+pub fn aggressive_dce(valid_program: Valid<Program>) -> Valid<Program> {
+    let mut program = valid_program.0;
+
+    program.functions = program
+        .functions
+        .iter()
+        .map(|(id, f)| (id.clone(), simplify_func(&adce_func(f, &program))))
+        .collect();
+
+    // Do not remove this validation check.  It is there to help you catch the
+    // bugs early on.  The autograder uses an internal final validation check.
+    program.validate().unwrap()
+}
+
+// SECTION: CFG simplification
+
+fn simplify_func(func: &Function) -> Function {
+    let mut func = func.clone();
+
+    while fold_branches(&mut func) | remove_unreachable(&mut func) | thread_jumps(&mut func) | merge_chains(&mut func) {}
+
+    func
+}
+
+// The predecessors of each block, in the order of phi arguments.
+fn preds(func: &Function) -> Map<BbId, Set<BbId>> {
+    let mut preds: Map<BbId, Set<BbId>> = func.body.keys().map(|bb| (bb.clone(), Set::new())).collect();
+    for bb in func.body.values() {
+        for succ in bb.term.successors() {
+            preds.get_mut(succ).unwrap().insert(bb.id.clone());
+        }
+    }
+    preds
+}
+
+// Rewrite the phi arguments for the predecessors the blocks have now, given
+// the ones they had before an edit, and the blocks the edit renamed.  An edit
+// must not give a block with phis a predecessor it did not have.
+fn fix_phis(func: &mut Function, before: &Map<BbId, Set<BbId>>, renamed: &Map<BbId, BbId>) {
+    let after = preds(func);
+
+    for (bbid, bb) in &mut func.body {
+        for inst in &mut bb.insts {
+            let Instruction::Phi { args, .. } = inst else {
+                break;
+            };
+
+            let by_pred: Map<&BbId, &Operand> = before[bbid]
+                .iter()
+                .map(|pred| renamed.get(pred).unwrap_or(pred))
+                .zip(args.iter())
+                .collect();
+            *args = after[bbid].iter().map(|pred| by_pred[pred].clone()).collect();
+        }
+    }
+}
+
+fn has_phis(bb: &BasicBlock) -> bool {
+    matches!(bb.insts.first(), Some(Instruction::Phi { .. }))
+}
+
+// Whether the exit is reachable from every block the entry reaches.
+fn exit_reachable(func: &Function) -> bool {
+    let preds = preds(func);
+    let reached_from = |start: &BbId, next: &dyn Fn(&BbId) -> Vec<BbId>| {
+        let mut reached = Set::from([start.clone()]);
+        let mut worklist = vec![start.clone()];
+        while let Some(bb) = worklist.pop() {
+            for next in next(&bb) {
+                if reached.insert(next.clone()) {
+                    worklist.push(next);
+                }
+            }
+        }
+        reached
+    };
+
+    let exit = func.body.values().find(|bb| matches!(bb.term, Terminal::Ret(_))).unwrap();
+    let forward = reached_from(&bb_id("entry"), &|bb| func.body[bb].term.successors().into_iter().cloned().collect());
+    let backward = reached_from(&exit.id, &|bb| preds[bb].iter().cloned().collect());
+
+    forward.is_subset(&backward)
+}
+
+fn fold_branches(func: &mut Function) -> bool {
+    let mut changed = false;
+
+    let branches: Vec<BbId> = func
+        .body
+        .values()
+        .filter(|bb| matches!(bb.term, Terminal::Branch { .. }))
+        .map(|bb| bb.id.clone())
+        .collect();
+
+    for bbid in branches {
+        let Terminal::Branch { cond, tt, ff } = &func.body[&bbid].term else {
+            unreachable!()
+        };
+        let target = match cond {
+            _ if tt == ff => tt.clone(),
+            Operand::CInt(0) => ff.clone(),
+            Operand::CInt(_) => tt.clone(),
+            Operand::Var(_) => continue,
+        };
+
+        let before = preds(func);
+        let old = std::mem::replace(&mut func.body.get_mut(&bbid).unwrap().term, Terminal::Jump(target));
+        if exit_reachable(func) {
+            fix_phis(func, &before, &Map::new());
+            changed = true;
+        } else {
+            func.body.get_mut(&bbid).unwrap().term = old;
+        }
+    }
+
+    changed
+}
+
+fn remove_unreachable(func: &mut Function) -> bool {
+    let mut reached = Set::from([bb_id("entry")]);
+    let mut worklist = vec![bb_id("entry")];
+    while let Some(bb) = worklist.pop() {
+        for succ in func.body[&bb].term.successors() {
+            if reached.insert(succ.clone()) {
+                worklist.push(succ.clone());
+            }
+        }
+    }
+
+    if reached.len() == func.body.len() {
+        return false;
+    }
+
+    let before = preds(func);
+    func.body.retain(|bb, _| reached.contains(bb));
+    fix_phis(func, &before, &Map::new());
+
+    true
+}
+
+// Retarget the jumps to empty blocks that only jump on.  The empty blocks are
+// then unreachable.
+fn thread_jumps(func: &mut Function) -> bool {
+    let forward: Map<BbId, BbId> = func
+        .body
+        .values()
+        .filter_map(|bb| match &bb.term {
+            // the entry cannot be skipped, and a block with phis cannot take
+            // new predecessors.
+            Terminal::Jump(next)
+                if bb.insts.is_empty() && bb.id != bb_id("entry") && *next != bb.id && !has_phis(&func.body[next]) =>
+            {
+                Some((bb.id.clone(), next.clone()))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut changed = false;
+    for bb in func.body.values_mut() {
+        for succ in bb.term.successors_mut() {
+            // a chain of empty blocks is followed to its end, unless it is a
+            // cycle.
+            let mut seen = Set::from([succ.clone()]);
+            while let Some(next) = forward.get(succ).filter(|next| seen.insert((*next).clone())) {
+                *succ = next.clone();
+                changed = true;
+            }
+        }
+    }
+
+    changed
+}
+
+// Merge blocks into their only predecessor when it only jumps to them.
+fn merge_chains(func: &mut Function) -> bool {
+    let mut changed = false;
+
+    loop {
+        let preds = preds(func);
+        let mergeable = func.body.values().find_map(|bb| match &bb.term {
+            Terminal::Jump(next)
+                if *next != bb.id
+                    && *next != bb_id("entry")
+                    && preds[next].len() == 1
+                    && phis_are_sequential(&func.body[next]) =>
+            {
+                Some((bb.id.clone(), next.clone()))
+            }
+            _ => None,
+        });
+        let Some((bbid, next)) = mergeable else {
+            return changed;
+        };
+
+        // the phis of `next` have a single argument, from `bbid`.
+        let merged = func.body.remove(&next).unwrap();
+        let bb = func.body.get_mut(&bbid).unwrap();
+        bb.insts.extend(merged.insts.into_iter().map(|inst| match inst {
+            Instruction::Phi { lhs, mut args } => Instruction::Copy {
+                lhs,
+                op: args.pop().unwrap(),
+            },
+            inst => inst,
+        }));
+        bb.term = merged.term;
+
+        fix_phis(func, &preds, &Map::from([(next, bbid)]));
+        changed = true;
+    }
+}
+
+// Whether the phis of a block with a single predecessor can become copies in
+// order: phis read their arguments all at once, so none can read what another
+// one assigns.
+fn phis_are_sequential(bb: &BasicBlock) -> bool {
+    let phis: Vec<&Instruction> = bb.insts.iter().take_while(|inst| matches!(inst, Instruction::Phi { .. })).collect();
+    let assigned: Set<&VarId> = phis.iter().filter_map(|inst| inst.def()).collect();
+    phis.iter().all(|inst| inst.uses().iter().all(|x| !assigned.contains(x)))
+}
+
+// SECTION: aggressive dead code elimination
+
+fn adce_func(func: &Function, program: &Program) -> Function {
+    let cfg = Cfg::new(func, program.globals.clone(), program.structs.clone());
+    let postdoms = cfg.post_dom_tree();
+    let control_deps = postdoms.frontiers(&cfg.reversed());
+
+    let mut defs: Map<&VarId, Vec<InstId>> = Map::new();
+    for bb in func.body.values() {
+        for (i, inst) in bb.insts.iter().enumerate() {
+            if let Some(x) = inst.def() {
+                defs.entry(x).or_default().push((bb.id.clone(), i));
+            }
+        }
+        if let Some(x) = bb.term.def() {
+            defs.entry(x).or_default().push((bb.id.clone(), bb.insts.len()));
+        }
+    }
+
+    let loop_exits: Set<&BbId> = cfg.loops.loops.values().flat_map(|l| l.exits.iter().map(|(from, _)| from)).collect();
+    let escapes = |x: &VarId| x.is_global() || cfg.addr_taken.contains(x);
+
+    let mut marker = Marker {
+        func,
+        cfg: &cfg,
+        defs: &defs,
+        control_deps: &control_deps,
+        useful: Set::new(),
+        worklist: vec![],
+    };
+
+    for bb in func.body.values() {
+        for (i, inst) in bb.insts.iter().enumerate() {
+            let effect_free = matches!(inst, Instruction::Phi { .. }) || removable(inst);
+            if !effect_free || inst.def().is_some_and(escapes) {
+                marker.mark((bb.id.clone(), i));
+            }
+        }
+
+        let useful_term = match &bb.term {
+            Terminal::Branch { .. } => loop_exits.contains(&bb.id),
+            Terminal::Jump(_) => false,
+            Terminal::CallDirect { .. } | Terminal::CallIndirect { .. } | Terminal::Ret(_) => true,
+        };
+        if useful_term {
+            marker.mark((bb.id.clone(), bb.insts.len()));
+        }
+    }
+
+    // a useless branch jumps to its nearest useful post-dominator, and that
+    // cannot be a block with phis, which would get a new predecessor.  Such a
+    // branch is kept.
+    let retargeted = loop {
+        marker.propagate();
+
+        let useful_blocks: Set<&BbId> = marker.useful.iter().map(|(bb, _)| bb).collect();
+        let mut retargeted = Map::new();
+        let mut kept = vec![];
+        for bb in func.body.values() {
+            let term = (bb.id.clone(), bb.insts.len());
+            if !matches!(bb.term, Terminal::Branch { .. }) || marker.useful.contains(&term) {
+                continue;
+            }
+
+            let mut target = postdoms.idom(&bb.id).unwrap();
+            while !useful_blocks.contains(target) {
+                target = postdoms.idom(target).unwrap();
+            }
+
+            let target_has_phis = func.body[target]
+                .insts
+                .iter()
+                .enumerate()
+                .any(|(i, inst)| matches!(inst, Instruction::Phi { .. }) && marker.useful.contains(&(target.clone(), i)));
+            if target_has_phis {
+                kept.push(term);
+            } else {
+                retargeted.insert(bb.id.clone(), target.clone());
+            }
+        }
+
+        if kept.is_empty() {
+            break retargeted;
+        }
+        for term in kept {
+            marker.mark(term);
+        }
+    };
+
+    let mut opt_func = func.clone();
+    for (bbid, bb) in &mut opt_func.body {
+        let mut i = 0;
+        bb.insts.retain(|_| {
+            i += 1;
+            marker.useful.contains(&(bbid.clone(), i - 1))
+        });
+        if let Some(target) = retargeted.get(bbid) {
+            bb.term = Terminal::Jump(target.clone());
+        }
+    }
+
+    // remove the locals no instruction mentions anymore.
+    let used: Set<VarId> = opt_func
+        .body
+        .values()
+        .flat_map(|bb| {
+            bb.insts
+                .iter()
+                .flat_map(|inst| {
+                    let addr_of = match inst {
+                        Instruction::AddrOf { op, .. } => Some(op),
+                        _ => None,
+                    };
+                    inst.def().into_iter().chain(inst.uses()).chain(addr_of)
+                })
+                .chain(bb.term.def())
+                .chain(bb.term.uses())
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    opt_func.locals.retain(|x| used.contains(x));
+
+    opt_func
+}
+
+// Marks the useful instructions and terminals, and everything they need.
+struct Marker<'a> {
+    func: &'a Function,
+    cfg: &'a Cfg,
+    defs: &'a Map<&'a VarId, Vec<InstId>>,
+    control_deps: &'a Map<BbId, Set<BbId>>,
+    useful: Set<InstId>,
+    worklist: Vec<InstId>,
+}
+
+impl Marker<'_> {
+    fn mark(&mut self, id: InstId) {
+        if self.useful.insert(id.clone()) {
+            self.worklist.push(id);
+        }
+    }
+
+    fn terminal(&self, bbid: &BbId) -> InstId {
+        (bbid.clone(), self.func.body[bbid].insts.len())
+    }
+
+    fn propagate(&mut self) {
+        let (func, cfg, defs, control_deps) = (self.func, self.cfg, self.defs, self.control_deps);
+
+        while let Some((bbid, i)) = self.worklist.pop() {
+            let bb = &func.body[&bbid];
+
+            let uses = match bb.insts.get(i) {
+                Some(inst) => inst.uses(),
+                None => bb.term.uses(),
+            };
+            for x in uses {
+                for def in defs.get(x).into_iter().flatten() {
+                    self.mark(def.clone());
+                }
+            }
+
+            // the branches deciding whether this block runs.
+            for dep in &control_deps[&bbid] {
+                self.mark(self.terminal(dep));
+            }
+
+            // a phi also needs the edges it picks its argument by.
+            if let Some(Instruction::Phi { .. }) = bb.insts.get(i) {
+                for pred in cfg.pred(&bbid) {
+                    self.mark(self.terminal(pred));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,484 @@
+//! Conversion to and from static single assignment form.
+//!
+//! Phis are placed on the iterated dominance frontiers of the blocks defining
+//! each variable (Cytron et al.), and the definitions are renamed walking the
+//! dominator tree.  Only variables that are live across blocks get phis, and
+//! the phis whose value is never used are removed afterwards, so the result is
+//! pruned SSA.
+//!
+//! Only locals and parameters whose address is never taken are renamed:
+//! globals and address-taken locals live in memory, where calls, loads and
+//! stores can access them without naming them.  The original variable stands
+//! for its value on entry to the function (the argument for parameters, zero
+//! for locals), so it is never redefined.
+//!
+//! The argument `i` of a phi is the value coming from the `i`-th predecessor of
+//! its block, with the predecessors sorted by name.
+//!
+//! Going out of SSA, the phis of a block become copies at the end of each
+//! predecessor.  All the phis of a block read their arguments before any of
+//! them is assigned, so the copies along an edge are ordered to not overwrite
+//! a variable another copy still needs to read, going through a temporary
+//! when they form a cycle (the swap problem).  A copy can only be placed in a
+//! predecessor that jumps to the block unconditionally: on any other edge (in
+//! particular a critical edge, where the copied variable may be live on the
+//! other successor: the lost-copy problem) the edge is split with a new block
+//! holding the copies.
+
+use super::*;
+use crate::commons::*;
+use crate::middle_end::analysis::Cfg;
+use crate::middle_end::lir::*;
+
+/// The actual optimization pass.
+pub fn to_ssa(valid_program: Valid<Program>) -> Valid<Program> {
+    let mut program = valid_program.0;
+
+    program.functions = program
+        .functions
+        .iter()
+        .map(|(id, f)| (id.clone(), to_ssa_func(f, &program)))
+        .collect();
+
+    // Do not remove this validation check.  It is there to help you catch the
+    // bugs early on.  The autograder uses an internal final validation check.
+    let program = program.validate().unwrap();
+    program.0.check_ssa().unwrap();
+    program
+}
+
+/// Replace the phis with copies.  The result is not in SSA form anymore.
+pub fn from_ssa(valid_program: Valid<Program>) -> Valid<Program> {
+    let mut program = valid_program.0;
+
+    program.functions = program
+        .functions
+        .iter()
+        .map(|(id, f)| (id.clone(), from_ssa_func(f, &program)))
+        .collect();
+
+    // Do not remove this validation check.  It is there to help you catch the
+    // bugs early on.  The autograder uses an internal final validation check.
+    program.validate().unwrap()
+}
+
+// Functions that already have phis are assumed to be in SSA form, and are
+// left alone.  So are functions that jump back to their entry, since the
+// entry values would need a phi without a predecessor to come from.
+fn to_ssa_func(func: &Function, program: &Program) -> Function {
+    let cfg = Cfg::new(func, program.globals.clone(), program.structs.clone());
+
+    let has_phis = func
+        .body
+        .values()
+        .any(|bb| bb.insts.iter().any(|inst| matches!(inst, Instruction::Phi { .. })));
+    if has_phis || cfg.pred(&cfg.entry).next().is_some() {
+        return func.clone();
+    }
+
+    let renamed: Set<VarId> = func
+        .params
+        .iter()
+        .chain(&func.locals)
+        .filter(|x| !cfg.addr_taken.contains(*x))
+        .cloned()
+        .collect();
+
+    let mut ssa_func = func.clone();
+    let phis = place_phis(func, &cfg, &renamed);
+    Renamer::new(var_names(func, &program.globals), &renamed).rename(&mut ssa_func, &cfg, &phis);
+    prune_phis(&mut ssa_func);
+
+    ssa_func
+}
+
+/// The variables of `func` that are in SSA form, even if the rest of the
+/// function is not: locals and parameters whose address is never taken, with
+/// no definition or a single one dominating all their uses.  Parameters are
+/// defined on entry.  Such a variable holds the same value everywhere it is
+/// read.
+pub fn ssa_vars(func: &Function, cfg: &Cfg) -> Set<VarId> {
+    let doms = cfg.dom_tree();
+
+    // where each variable is defined; the terminal is at index insts.len().
+    let mut defs: Map<&VarId, Vec<(&BbId, usize)>> = Map::new();
+    for (bbid, bb) in &func.body {
+        let bb_defs = bb.insts.iter().map(Instruction::def).chain([bb.term.def()]);
+        for (pos, def) in bb_defs.enumerate() {
+            if let Some(x) = def {
+                defs.entry(x).or_default().push((bbid, pos));
+            }
+        }
+    }
+
+    let mut vars: Set<VarId> = func
+        .params
+        .iter()
+        .filter(|x| !defs.contains_key(x))
+        .chain(func.locals.iter().filter(|x| defs.get(x).is_none_or(|defs| defs.len() == 1)))
+        .filter(|x| !cfg.addr_taken.contains(*x))
+        .cloned()
+        .collect();
+
+    let dominated = |x: &VarId, bbid: &BbId, pos: usize| match defs.get(x).map(|defs| defs[0]) {
+        Some((def_bb, def_pos)) => (def_bb == bbid && def_pos < pos) || doms.strictly_dominates(def_bb, bbid),
+        None => true,
+    };
+
+    for (bbid, bb) in &func.body {
+        for (pos, inst) in bb.insts.iter().enumerate() {
+            match inst {
+                // a phi reads its arguments at the end of the predecessors.
+                Instruction::Phi { args, .. } => {
+                    for (pred, arg) in cfg.pred(bbid).zip(args) {
+                        if let Some(x) = arg.as_var() {
+                            if !dominated(x, pred, func.body[pred].insts.len() + 1) {
+                                vars.remove(x);
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    for x in inst.uses() {
+                        if !dominated(x, bbid, pos) {
+                            vars.remove(x);
+                        }
+                    }
+                }
+            }
+        }
+        for x in bb.term.uses() {
+            if !dominated(x, bbid, bb.insts.len()) {
+                vars.remove(x);
+            }
+        }
+    }
+
+    vars
+}
+
+// The variables each block needs a phi for.
+fn place_phis(func: &Function, cfg: &Cfg, renamed: &Set<VarId>) -> Map<BbId, Set<VarId>> {
+    let frontiers = cfg.dom_tree().frontiers(cfg);
+
+    // the blocks defining each variable, and the variables read before being
+    // defined in some block: the others never need a phi.
+    let mut def_blocks: Map<&VarId, Set<&BbId>> = Map::new();
+    let mut live_across = Set::new();
+    for (bbid, bb) in &func.body {
+        let mut defined = Set::new();
+        let defs_uses = bb
+            .insts
+            .iter()
+            .map(|inst| (inst.def(), inst.uses()))
+            .chain([(bb.term.def(), bb.term.uses())]);
+
+        for (def, uses) in defs_uses {
+            live_across.extend(uses.into_iter().filter(|x| renamed.contains(*x) && !defined.contains(*x)));
+            if let Some(def) = def.filter(|x| renamed.contains(*x)) {
+                defined.insert(def);
+                def_blocks.entry(def).or_default().insert(bbid);
+            }
+        }
+    }
+
+    let mut phis: Map<BbId, Set<VarId>> = Map::new();
+    for (x, blocks) in def_blocks {
+        if !live_across.contains(x) {
+            continue;
+        }
+
+        let mut worklist: Vec<&BbId> = blocks.into_iter().collect();
+        while let Some(bb) = worklist.pop() {
+            for frontier in frontiers.get(bb).into_iter().flatten() {
+                if phis.entry(frontier.clone()).or_default().insert(x.clone()) {
+                    worklist.push(frontier);
+                }
+            }
+        }
+    }
+
+    phis
+}
+
+// Generates names that are not in use yet, by adding a number to a base name.
+pub(super) struct FreshNames {
+    pub(super) taken: Set<String>,
+}
+
+impl FreshNames {
+    pub(super) fn fresh(&mut self, base: &str) -> String {
+        let name = (1..)
+            .map(|n| format!("{base}.{n}"))
+            .find(|name| !self.taken.contains(name))
+            .unwrap();
+        self.taken.insert(name.clone());
+        name
+    }
+
+    // a fresh variable like x.
+    pub(super) fn fresh_var(&mut self, x: &VarId) -> VarId {
+        var_id(&self.fresh(x.name()), x.typ(), x.scope())
+    }
+}
+
+// The variable names in use in a function.
+pub(super) fn var_names(func: &Function, globals: &Set<VarId>) -> FreshNames {
+    FreshNames {
+        taken: globals
+            .iter()
+            .chain(&func.params)
+            .chain(&func.locals)
+            .map(|x| x.name().to_string())
+            .collect(),
+    }
+}
+
+// Renames every definition of the renamed variables to a fresh variable, and
+// every use to the definition reaching it.
+struct Renamer {
+    renamed: Set<VarId>,
+    names: FreshNames,
+    // the current definition of each variable, innermost last.
+    stacks: Map<VarId, Vec<VarId>>,
+}
+
+impl Renamer {
+    fn new(names: FreshNames, renamed: &Set<VarId>) -> Self {
+        Renamer {
+            renamed: renamed.clone(),
+            names,
+            stacks: renamed.iter().map(|x| (x.clone(), vec![x.clone()])).collect(),
+        }
+    }
+
+    // a fresh version of x, which becomes its current definition.
+    fn define(&mut self, x: &mut VarId, new_locals: &mut Set<VarId>) -> VarId {
+        let base = x.clone();
+        *x = self.names.fresh_var(&base);
+        new_locals.insert(x.clone());
+        self.stacks.get_mut(&base).unwrap().push(x.clone());
+        base
+    }
+
+    fn use_var(&self, x: &mut VarId) {
+        if let Some(stack) = self.stacks.get(x) {
+            *x = stack.last().unwrap().clone();
+        }
+    }
+
+    fn rename(&mut self, func: &mut Function, cfg: &Cfg, phis: &Map<BbId, Set<VarId>>) {
+        let doms = cfg.dom_tree();
+        let mut new_locals = Set::new();
+
+        // insert the phis, with the entry values as placeholder arguments.
+        for (bbid, xs) in phis {
+            let num_preds = cfg.pred(bbid).count();
+            let bb = func.body.get_mut(bbid).unwrap();
+            bb.insts.splice(
+                0..0,
+                xs.iter().map(|x| Instruction::Phi {
+                    lhs: x.clone(),
+                    args: vec![Operand::Var(x.clone()); num_preds],
+                }),
+            );
+        }
+
+        // walk the dominator tree; a block's definitions are popped once its
+        // whole subtree is renamed.
+        let mut stack = vec![(doms.root().clone(), None)];
+        while let Some((bbid, pushed)) = stack.pop() {
+            if let Some(pushed) = pushed {
+                for x in pushed {
+                    self.stacks.get_mut(&x).unwrap().pop();
+                }
+                continue;
+            }
+
+            let mut pushed = vec![];
+            let bb = func.body.get_mut(&bbid).unwrap();
+
+            for inst in &mut bb.insts {
+                if !matches!(inst, Instruction::Phi { .. }) {
+                    inst.uses_mut().into_iter().for_each(|x| self.use_var(x));
+                }
+                if let Some(def) = inst.def_mut().filter(|x| self.renamed.contains(*x)) {
+                    pushed.push(self.define(def, &mut new_locals));
+                }
+            }
+
+            bb.term.uses_mut().into_iter().for_each(|x| self.use_var(x));
+            if let Some(def) = bb.term.def_mut().filter(|x| self.renamed.contains(*x)) {
+                pushed.push(self.define(def, &mut new_locals));
+            }
+
+            // fill in this block's argument of the successors' phis.
+            for succ in cfg.succ(&bbid) {
+                let i = cfg.pred(succ).position(|pred| *pred == bbid).unwrap();
+                for inst in &mut func.body.get_mut(succ).unwrap().insts {
+                    match inst {
+                        Instruction::Phi { args, .. } => {
+                            args[i].as_var_mut().into_iter().for_each(|x| self.use_var(x))
+                        }
+                        _ => break,
+                    }
+                }
+            }
+
+            stack.push((bbid.clone(), Some(pushed)));
+            stack.extend(doms.children(&bbid).map(|child| (child.clone(), None)));
+        }
+
+        func.locals.extend(new_locals);
+    }
+}
+
+// Remove the phis whose value only flows into other removed phis.
+fn prune_phis(func: &mut Function) {
+    let mut used: Set<VarId> = func
+        .body
+        .values()
+        .flat_map(|bb| {
+            bb.insts
+                .iter()
+                .filter(|inst| !matches!(inst, Instruction::Phi { .. }))
+                .flat_map(Instruction::uses)
+                .chain(bb.term.uses())
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let phis: Vec<(VarId, Vec<VarId>)> = func
+        .body
+        .values()
+        .flat_map(|bb| &bb.insts)
+        .filter_map(|inst| match inst {
+            Instruction::Phi { lhs, args } => Some((lhs.clone(), args.iter().filter_map(Operand::as_var).cloned().collect())),
+            _ => None,
+        })
+        .collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (lhs, args) in &phis {
+            if used.contains(lhs) {
+                for arg in args {
+                    changed |= used.insert(arg.clone());
+                }
+            }
+        }
+    }
+
+    let dead: Set<&VarId> = phis.iter().map(|(lhs, _)| lhs).filter(|lhs| !used.contains(*lhs)).collect();
+
+    for bb in func.body.values_mut() {
+        bb.insts.retain(|inst| match inst {
+            Instruction::Phi { lhs, .. } => !dead.contains(lhs),
+            _ => true,
+        });
+    }
+
+    func.locals.retain(|x| !dead.contains(x));
+}
+
+// SECTION: out of SSA
+
+fn from_ssa_func(func: &Function, program: &Program) -> Function {
+    let cfg = Cfg::new(func, program.globals.clone(), program.structs.clone());
+    let mut var_names = var_names(func, &program.globals);
+    let mut bb_names = FreshNames {
+        taken: func.body.keys().map(|bb| bb.name().to_string()).collect(),
+    };
+
+    let mut out = func.clone();
+
+    for (bbid, bb) in &func.body {
+        let phis: Vec<(&VarId, &Vec<Operand>)> = bb
+            .insts
+            .iter()
+            .map_while(|inst| match inst {
+                Instruction::Phi { lhs, args } => Some((lhs, args)),
+                _ => None,
+            })
+            .collect();
+
+        if phis.is_empty() {
+            continue;
+        }
+        out.body.get_mut(bbid).unwrap().insts.drain(..phis.len());
+
+        for (i, pred) in cfg.pred(bbid).enumerate() {
+            let copies = phis.iter().map(|(lhs, args)| ((*lhs).clone(), args[i].clone())).collect();
+            let mut temps = vec![];
+            let copies = sequentialize(copies, |x| {
+                let temp = var_names.fresh_var(x);
+                temps.push(temp.clone());
+                temp
+            });
+            out.locals.extend(temps);
+
+            if let Terminal::Jump(_) = func.body[pred].term {
+                out.body.get_mut(pred).unwrap().insts.extend(copies);
+            } else {
+                let split = bb_id(&bb_names.fresh(&format!("{pred}_{bbid}")));
+                for succ in out.body.get_mut(pred).unwrap().term.successors_mut() {
+                    if succ == bbid {
+                        *succ = split.clone();
+                    }
+                }
+                out.body.insert(
+                    split.clone(),
+                    BasicBlock {
+                        id: split,
+                        insts: copies,
+                        term: Terminal::Jump(bbid.clone()),
+                    },
+                );
+            }
+        }
+    }
+
+    out
+}
+
+// Turn the parallel copies into a sequence of copies with the same effect.
+// `temp` makes a new variable to hold the value of the given one.
+fn sequentialize(copies: Vec<(VarId, Operand)>, mut temp: impl FnMut(&VarId) -> VarId) -> Vec<Instruction> {
+    let mut pending: Vec<(VarId, Operand)> = copies
+        .into_iter()
+        .filter(|(lhs, op)| op.as_var() != Some(lhs))
+        .collect();
+    let mut seq = vec![];
+
+    while !pending.is_empty() {
+        // a copy can go first if no other copy reads what it overwrites.
+        let ready = pending
+            .iter()
+            .position(|(lhs, _)| pending.iter().all(|(_, op)| op.as_var() != Some(lhs)));
+
+        match ready {
+            Some(i) => {
+                let (lhs, op) = pending.remove(i);
+                seq.push(Instruction::Copy { lhs, op });
+            }
+            // the rest are cycles: save a variable so its copy can go first.
+            None => {
+                let x = pending[0].0.clone();
+                let saved = temp(&x);
+                seq.push(Instruction::Copy {
+                    lhs: saved.clone(),
+                    op: Operand::Var(x.clone()),
+                });
+                for (_, op) in &mut pending {
+                    if op.as_var() == Some(&x) {
+                        *op = Operand::Var(saved.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    seq
+}
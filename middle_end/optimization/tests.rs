@@ -3,7 +3,16 @@ use collapse::*;
 use crate::{commons::Valid, middle_end::lir::Program};
 
 mod constant_prop;
+mod copy_prop;
+mod dead_store_elimination;
+mod gvn;
 mod inlining;
+mod licm;
+mod pass_manager;
+mod sccp;
+mod simplify_cfg;
+mod ssa;
+mod translation_validation;
 
 // Read given test file, run given analysis, and compare its results to the
 // expected results from given result file.
@@ -0,0 +1,225 @@
+use crate::middle_end::{lir::*, optimization::copy_prop::*};
+
+// Check if the input program optimizes to the expected output program
+fn optimizes_to(input: &str, expected: &str) {
+    let input = input.parse::<Program>().unwrap().validate().unwrap();
+    let expected = expected
+        .parse::<Program>()
+        .unwrap()
+        .validate()
+        .unwrap()
+        .0
+        .to_string();
+
+    let actual = copy_prop(input).0;
+
+    assert_eq!(actual.to_string(), expected);
+}
+
+// Copies of copies go back to the original.  Once it is redefined, `y` is
+// still a copy of `x`.
+#[test]
+fn chains_of_copies() {
+    optimizes_to(
+        r#"
+    fn f(a:int) -> int {
+    let x:int, y:int, z:int, w:int
+    entry:
+      x = $copy a
+      y = $copy x
+      z = $arith add y x
+      a = $copy 3
+      w = $arith add y 1
+      $ret w
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn f(a:int) -> int {
+    let x:int, y:int, z:int, w:int
+    entry:
+      x = $copy a
+      y = $copy a
+      z = $arith add a a
+      a = $copy 3
+      w = $arith add x 1
+      $ret w
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+// A copy is only available after a join if it is on every incoming path.
+#[test]
+fn joins_intersect() {
+    optimizes_to(
+        r#"
+    fn f(a:int, b:int) -> int {
+    let x:int, y:int, r:int
+    entry:
+      x = $copy a
+      y = $copy a
+      $branch b yes no
+    yes:
+      y = $copy b
+      $jump exit
+    no:
+      $jump exit
+    exit:
+      r = $arith add x y
+      $ret r
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn f(a:int, b:int) -> int {
+    let x:int, y:int, r:int
+    entry:
+      x = $copy a
+      y = $copy a
+      $branch b yes no
+    yes:
+      y = $copy b
+      $jump exit
+    no:
+      $jump exit
+    exit:
+      r = $arith add a y
+      $ret r
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+// The header starts from the top state, so the copy made before the loop
+// survives the back edge.  Had it started empty, nothing in the loop would be
+// propagated.
+#[test]
+fn copies_survive_loops_not_changing_them() {
+    optimizes_to(
+        r#"
+    fn f(a:int, n:int) -> int {
+    let x:int, i:int, s:int, c:int
+    entry:
+      x = $copy a
+      $jump header
+    header:
+      c = $cmp lt i n
+      $branch c body exit
+    body:
+      s = $arith add s x
+      i = $arith add i 1
+      $jump header
+    exit:
+      $ret x
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn f(a:int, n:int) -> int {
+    let x:int, i:int, s:int, c:int
+    entry:
+      x = $copy a
+      $jump header
+    header:
+      c = $cmp lt i n
+      $branch c body exit
+    body:
+      s = $arith add s a
+      i = $arith add i 1
+      $jump header
+    exit:
+      $ret a
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+// Globals and address-taken variables can change behind our back.
+#[test]
+fn escaping_variables_are_not_propagated() {
+    let program = r#"
+    @g:int
+
+    extern touch:() -> _
+
+    fn main() -> int {
+    let x:int, y:int, p:&int, r:int
+    entry:
+      x = $copy @g
+      p = $addrof y
+      y = $copy x
+      $call_ext touch()
+      $store p 5
+      r = $arith add y 1
+      $ret r
+    }
+    "#;
+    optimizes_to(program, program);
+}
+
+#[test]
+fn preserves_behavior() {
+    let program = r#"
+    extern print:(int) -> _
+
+    fn main() -> int {
+      let i:int, j:int, k:int, s:int, c:int, d:int
+      entry:
+        k = $copy 3
+        j = $copy k
+        $jump header
+      header:
+        c = $cmp lt i 10
+        $branch c body exit
+      body:
+        d = $arith div i 2
+        s = $arith add s j
+        $branch d odd latch
+      odd:
+        k = $copy i
+        j = $copy k
+        $call_ext print(j)
+        $jump latch
+      latch:
+        i = $arith add i 1
+        $jump header
+      exit:
+        $ret s
+    }
+    "#
+    .parse::<Program>()
+    .unwrap();
+
+    let run = |program: Program| crate::interpreter::interpret_with_output(program).unwrap();
+
+    let optimized = copy_prop(program.clone().validate().unwrap()).0;
+    assert_eq!(run(optimized), run(program));
+}
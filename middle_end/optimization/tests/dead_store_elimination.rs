@@ -0,0 +1,67 @@
+// Basic tests for dead store elimination.
+
+use crate::middle_end::{lir::*, optimization::dead_store_elimination::*};
+
+fn dse(code: &str) -> Program {
+    let program = code.parse::<Program>().unwrap().validate().unwrap();
+    dead_store_elim(program).0
+}
+
+#[test]
+fn removes_chains_of_dead_stores() {
+    let program = dse(r#"
+    fn main() -> int {
+      let a:int, b:int, c:int, d:int
+      entry:
+        a = $copy 1
+        b = $arith add a 2
+        c = $cmp lt b 3
+        d = $copy 4
+        $ret d
+    }
+    "#);
+
+    let main = &program.functions[&func_id("main")];
+    let d = var_id("d", int_ty(), Some(func_id("main")));
+
+    assert_eq!(
+        main.body[&bb_id("entry")].insts,
+        vec![Instruction::Copy { lhs: d.clone(), op: Operand::CInt(4) }]
+    );
+    assert_eq!(main.locals, [d].into_iter().collect());
+}
+
+#[test]
+fn keeps_defs_live_around_loops_and_side_effects() {
+    let program = dse(r#"
+    fn main() -> int {
+      entry:
+        $ret 0
+    }
+
+    fn f(n:int) -> int {
+      let i:int, q:int, c:int, unused:int
+      entry:
+        i = $copy 0
+        q = $arith div 10 n
+        $jump loop
+      loop:
+        unused = $copy i
+        c = $cmp lt i n
+        $branch c body exit
+      body:
+        i = $arith add i 1
+        $jump loop
+      exit:
+        $ret i
+    }
+    "#);
+
+    let f = &program.functions[&func_id("f")];
+    let insts = |bb: &str| f.body[&bb_id(bb)].insts.len();
+
+    // `q` is dead, but the division may fail.
+    assert_eq!(insts("entry"), 2);
+    assert_eq!(insts("loop"), 1);
+    assert_eq!(insts("body"), 1);
+}
@@ -0,0 +1,176 @@
+use crate::middle_end::{lir::*, optimization::gvn::*};
+
+// Check if the input program optimizes to the expected output program
+fn optimizes_to(input: &str, expected: &str) {
+    let input = input.parse::<Program>().unwrap().validate().unwrap();
+    let expected = expected
+        .parse::<Program>()
+        .unwrap()
+        .validate()
+        .unwrap()
+        .0
+        .to_string();
+
+    let actual = gvn(input).0;
+
+    assert_eq!(actual.to_string(), expected);
+}
+
+#[test]
+fn redundant_arith_in_dominated_block() {
+    optimizes_to(
+        r#"
+    fn f(a:int, b:int) -> int {
+    let x:int, y:int, z:int, w:int, c:int
+    entry:
+      x = $arith add a b
+      c = $cmp gt a b
+      $branch c yes no
+    yes:
+      y = $arith add b a
+      $jump exit
+    no:
+      z = $copy x
+      w = $arith mul z 2
+      $jump exit
+    exit:
+      $ret x
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn f(a:int, b:int) -> int {
+    let x:int, y:int, z:int, w:int, c:int
+    entry:
+      x = $arith add a b
+      c = $cmp gt a b
+      $branch c yes no
+    yes:
+      y = $copy x
+      $jump exit
+    no:
+      z = $copy x
+      w = $arith mul z 2
+      $jump exit
+    exit:
+      $ret x
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+// Neither branch of a diamond dominates the other or the join.
+#[test]
+fn siblings_do_not_share_values() {
+    let program = r#"
+    fn f(a:int) -> int {
+    let x:int, y:int, z:int, c:int
+    entry:
+      c = $cmp lt a 0
+      $branch c yes no
+    yes:
+      x = $arith sub 0 a
+      $jump exit
+    no:
+      y = $arith sub 0 a
+      $jump exit
+    exit:
+      z = $arith sub 0 a
+      $ret z
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#;
+    optimizes_to(program, program);
+}
+
+#[test]
+fn address_computations_are_reused() {
+    optimizes_to(
+        r#"
+    struct pair {
+      fst:int
+      snd:int
+    }
+
+    fn f(p:&pair, a:&int) -> int {
+    let x:&int, y:&int, u:&int, v:&int, c:int, d:int
+    entry:
+      x = $gfp p fst
+      $store x 1
+      y = $gfp p fst
+      c = $cmp lte 1 2
+      d = $cmp gte 2 1
+      u = $gep a 3
+      v = $gep a 3
+      $ret c
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    struct pair {
+      fst:int
+      snd:int
+    }
+
+    fn f(p:&pair, a:&int) -> int {
+    let x:&int, y:&int, u:&int, v:&int, c:int, d:int
+    entry:
+      x = $gfp p fst
+      $store x 1
+      y = $copy x
+      c = $cmp lte 1 2
+      d = $copy c
+      u = $gep a 3
+      v = $copy u
+      $ret c
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+// Memory can change between two loads, and a variable assigned twice can
+// change between two computations reading it.
+#[test]
+fn loads_and_reassigned_variables_are_not_reused() {
+    let program = r#"
+    fn f(p:&int) -> int {
+    let x:int, y:int, n:int, s:int, t:int
+    entry:
+      x = $load p
+      $store p 2
+      y = $load p
+      s = $arith add n 1
+      n = $copy 5
+      t = $arith add n 1
+      $ret t
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#;
+    optimizes_to(program, program);
+}
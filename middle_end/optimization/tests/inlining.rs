@@ -383,7 +383,6 @@ fn rewrite_vars_in_all_insts() {
         y = $gfp z f1
         $store y a
         x = $load y
-        x = $phi(x.1, x.2)
         x = $call_ext foo(x, y, a)
         $branch x bb1 bb2
       bb1:
@@ -392,6 +391,7 @@ fn rewrite_vars_in_all_insts() {
         x = $copy 0
         $jump exit
       exit:
+        x = $phi(x.1, x.2)
         $ret x
     }
 
@@ -433,12 +433,11 @@ fn rewrite_vars_in_all_insts() {
       y = $gfp z f1
       $store y a
       x = $load y
-      x = $phi(x.1, x.2)
       x = $call_ext foo(x, y, a)
       $branch x bb1 bb2
 
     exit:
-
+      x = $phi(x.1, x.2)
       $ret x
     }
 
@@ -458,6 +457,8 @@ fn rewrite_vars_in_all_insts() {
 
     entry.f.entry.1:
       entry.f.a.1 = $copy x
+      entry.f.x.1.1 = $copy 0
+      entry.f.x.2.1 = $copy 0
       entry.f.a.1 = $arith add entry.f.a.1 1
       entry.f.x.1 = $copy entry.f.a.1
       entry.f.x.1 = $cmp lt entry.f.x.1 3
@@ -467,11 +468,11 @@ fn rewrite_vars_in_all_insts() {
       entry.f.y.1 = $gfp entry.f.z.1 f1
       $store entry.f.y.1 entry.f.a.1
       entry.f.x.1 = $load entry.f.y.1
-      entry.f.x.1 = $phi(entry.f.x.1.1, entry.f.x.2.1)
       entry.f.x.1 = $call_ext foo(entry.f.x.1, entry.f.y.1, entry.f.a.1)
       $branch entry.f.x.1 entry.f.bb1.1 entry.f.bb2.1
 
     entry.f.exit.1:
+      entry.f.x.1 = $phi(entry.f.x.1.1, entry.f.x.2.1)
       x = $copy entry.f.x.1
       $jump exit
 
@@ -568,3 +569,165 @@ fn multiple_calls_to_same_func() {
         inlined_program.to_string(),
     );
 }
+
+// SECTION: Inlining policies
+
+// A call chain main -> g -> h, with g and h recursive on the side.
+const CALL_CHAIN: &str = r#"
+    extern print:(int) -> _
+
+    fn h(a:int) -> int {
+      let x:int
+      entry:
+        x = $arith add a 1
+        $ret x
+    }
+
+    fn g(a:int) -> int {
+      let x:int, y:int
+      entry:
+        x = $call_dir h(a) then next
+      next:
+        y = $arith mul x 2
+        $ret y
+    }
+
+    fn rec(n:int) -> int {
+      let c:int, m:int, r:int
+      entry:
+        r = $copy 0
+        c = $cmp gt n 0
+        $branch c more done
+      more:
+        m = $arith sub n 1
+        r = $call_dir rec(m) then done
+      done:
+        $ret r
+    }
+
+    fn main() -> int {
+      let x:int, y:int, z:int
+      entry:
+        x = $call_dir g(3) then bb1
+      bb1:
+        y = $call_dir rec(x) then bb2
+      bb2:
+        z = $call_dir h(y) then bb3
+      bb3:
+        $call_ext print(z)
+        $ret x
+    }
+    "#;
+
+// The call sites left in each function.
+fn calls_left(program: &Program) -> Map<String, usize> {
+    program
+        .functions
+        .values()
+        .map(|f| {
+            let calls = f
+                .body
+                .values()
+                .filter(|bb| matches!(bb.term, Terminal::CallDirect { .. }))
+                .count();
+            (f.id.to_string(), calls)
+        })
+        .collect()
+}
+
+fn instructions(program: &Program) -> usize {
+    program
+        .functions
+        .values()
+        .flat_map(|f| f.body.values())
+        .map(|bb| bb.insts.len() + 1)
+        .sum()
+}
+
+#[test]
+fn inline_small_fns_transitively() {
+    let program: Program = CALL_CHAIN.parse().unwrap();
+    let inlined = inline_small_fns(program.clone().validate().unwrap(), 2, 5).0;
+
+    // g is small enough once h is inlined into it.  The call to rec in main is
+    // not recursive, so rec is inlined there once, along with its recursive
+    // call.
+    assert_eq!(
+        calls_left(&inlined),
+        Map::from([
+            ("g".to_string(), 0),
+            ("h".to_string(), 0),
+            ("main".to_string(), 1),
+            ("rec".to_string(), 1),
+        ])
+    );
+
+    let run = |program: Program| crate::interpreter::interpret_with_output(program).unwrap();
+    assert_eq!(run(inlined), run(program));
+}
+
+#[test]
+fn inline_small_fns_respects_bounds() {
+    let program: Program = CALL_CHAIN.parse().unwrap();
+
+    // no callee has fewer than one parameter.
+    let inlined = inline_small_fns(program.clone().validate().unwrap(), 1, 100).0;
+    assert_eq!(inlined.to_string(), program.to_string());
+
+    // h is small enough, but g with h inlined is not.
+    let inlined = inline_small_fns(program.clone().validate().unwrap(), 2, 2).0;
+    assert_eq!(
+        calls_left(&inlined),
+        Map::from([
+            ("g".to_string(), 0),
+            ("h".to_string(), 0),
+            ("main".to_string(), 2),
+            ("rec".to_string(), 1),
+        ])
+    );
+}
+
+#[test]
+fn inline_within_budget_stays_within_budget() {
+    let program: Program = CALL_CHAIN.parse().unwrap();
+    let size = instructions(&program);
+
+    for budget in [0, 3, 4, 10, 20, 100] {
+        let inlined = inline_within_budget(program.clone().validate().unwrap(), budget, None).0;
+        assert!(instructions(&inlined) <= size + budget);
+    }
+
+    // inlining h into g costs 4: 2 instructions, a copy for the argument and
+    // one for the result.  The call to h in main is no cheaper, and comes
+    // later.
+    let inlined = inline_within_budget(program.clone().validate().unwrap(), 4, None).0;
+    assert_eq!(instructions(&inlined), size + 4);
+    assert_eq!(calls_left(&inlined)["g"], 0);
+    assert_eq!(calls_left(&inlined)["main"], 3);
+}
+
+#[test]
+fn inline_within_budget_follows_profile() {
+    let program: Program = CALL_CHAIN.parse().unwrap();
+    let profile = CallCounts::from([
+        ((func_id("main"), bb_id("bb1")), 1),
+        ((func_id("main"), bb_id("bb2")), 1),
+        ((func_id("rec"), bb_id("more")), 3),
+    ]);
+
+    // the calls that never ran stay, and so do the recursive ones: main is
+    // left with its call to g and the recursive call of the inlined rec.
+    let inlined = inline_within_budget(program.clone().validate().unwrap(), 100, Some(&profile)).0;
+    assert_eq!(
+        calls_left(&inlined),
+        Map::from([
+            ("g".to_string(), 1),
+            ("h".to_string(), 0),
+            ("main".to_string(), 2),
+            ("rec".to_string(), 1),
+        ])
+    );
+
+    let run = |program: Program| crate::interpreter::interpret_with_output(program).unwrap();
+    assert_eq!(run(inlined), run(program));
+}
@@ -0,0 +1,256 @@
+use crate::middle_end::{lir::*, optimization::licm::*};
+
+// Check if the input program optimizes to the expected output program
+fn optimizes_to(input: &str, expected: &str) {
+    let input = input.parse::<Program>().unwrap().validate().unwrap();
+    let expected = expected
+        .parse::<Program>()
+        .unwrap()
+        .validate()
+        .unwrap()
+        .0
+        .to_string();
+
+    let actual = licm(input).0;
+
+    assert_eq!(actual.to_string(), expected);
+}
+
+// The entry only jumps to the outer header, so it is its preheader.  The
+// division may not run at all, so it stays.
+#[test]
+fn hoists_out_of_nested_loops() {
+    optimizes_to(
+        r#"
+    fn f(n:int, m:int) -> int {
+    let i:int, j:int, s:int, a:int, b:int, c:int, d:int, e:int
+    entry:
+      $jump outer
+    outer:
+      c = $cmp lt i n
+      $branch c inner exit
+    inner:
+      a = $arith mul n 4
+      b = $arith add a m
+      d = $cmp lt j b
+      $branch d body latch
+    body:
+      e = $arith div s n
+      s = $arith add s b
+      j = $arith add j 1
+      $jump inner
+    latch:
+      i = $arith add i 1
+      $jump outer
+    exit:
+      $ret s
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn f(n:int, m:int) -> int {
+    let i:int, j:int, s:int, a:int, b:int, c:int, d:int, e:int
+    entry:
+      a = $arith mul n 4
+      b = $arith add a m
+      $jump outer
+    outer:
+      c = $cmp lt i n
+      $branch c inner_preheader.1 exit
+    inner_preheader.1:
+      $jump inner
+    inner:
+      d = $cmp lt j b
+      $branch d body latch
+    body:
+      e = $arith div s n
+      s = $arith add s b
+      j = $arith add j 1
+      $jump inner
+    latch:
+      i = $arith add i 1
+      $jump outer
+    exit:
+      $ret s
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+// The header is entered from two blocks, so a preheader merging them is
+// created, with a phi for the values they bring.
+#[test]
+fn creates_preheader() {
+    optimizes_to(
+        r#"
+    fn f(n:int) -> int {
+    let i:int, i.1:int, i.2:int, c:int, k:int
+    entry:
+      $branch n loop skip
+    skip:
+      $jump loop
+    loop:
+      i.1 = $phi(i, i.2, 5)
+      k = $arith mul n n
+      i.2 = $arith add i.1 k
+      c = $cmp lt i.2 100
+      $branch c loop exit
+    exit:
+      $ret i.2
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn f(n:int) -> int {
+    let i:int, i.1:int, i.1.1:int, i.2:int, c:int, k:int
+    entry:
+      $branch n loop_preheader.1 skip
+    skip:
+      $jump loop_preheader.1
+    loop:
+      i.1 = $phi(i.2, i.1.1)
+      i.2 = $arith add i.1 k
+      c = $cmp lt i.2 100
+      $branch c loop exit
+    loop_preheader.1:
+      i.1.1 = $phi(i, 5)
+      k = $arith mul n n
+      $jump loop
+    exit:
+      $ret i.2
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+// The load of `p` in the header runs whenever the loop is left, and the loop
+// only stores where `q` points.  The load of `q` could read the store.
+#[test]
+fn hoists_loads_not_aliased() {
+    optimizes_to(
+        r#"
+    fn main() -> int {
+    let p:&int, q:&int, x:int, y:int, c:int
+    entry:
+      p = $alloc 1 [_a1]
+      q = $alloc 1 [_a2]
+      $jump loop
+    loop:
+      x = $load p
+      y = $load q
+      $store q x
+      c = $cmp lt y 10
+      $branch c loop exit
+    exit:
+      $ret y
+    }
+    "#,
+        r#"
+    fn main() -> int {
+    let p:&int, q:&int, x:int, y:int, c:int
+    entry:
+      p = $alloc 1 [_a1]
+      q = $alloc 1 [_a2]
+      x = $load p
+      $jump loop
+    loop:
+      y = $load q
+      $store q x
+      c = $cmp lt y 10
+      $branch c loop exit
+    exit:
+      $ret y
+    }
+    "#,
+    );
+}
+
+// `p` points to `x`, which the loop assigns, so the load reads a different
+// value each time around.
+#[test]
+fn keeps_loads_of_assigned_variables() {
+    let program = r#"
+    fn main() -> int {
+    let p:&int, x:int, y:int, c:int
+    entry:
+      p = $addrof x
+      $jump loop
+    loop:
+      y = $load p
+      x = $arith add x 1
+      c = $cmp lt x 3
+      $branch c loop exit
+    exit:
+      $ret y
+    }
+    "#;
+    optimizes_to(program, program);
+
+    let program = program.parse::<Program>().unwrap().validate().unwrap();
+    let optimized = licm(program.clone()).0;
+    assert_eq!(
+        crate::interpreter::interpret_with_output(optimized).unwrap(),
+        crate::interpreter::interpret_with_output(program.0).unwrap()
+    );
+}
+
+#[test]
+fn preserves_behavior() {
+    let program = r#"
+    struct pair {
+      fst:int
+      snd:int
+    }
+
+    extern print:(int) -> _
+
+    fn main() -> int {
+      let p:&pair, f:&int, i:int, s:int, c:int, k:int, v:int
+      entry:
+        p = $alloc 1 [_a1]
+        $jump header
+      header:
+        c = $cmp lt i 10
+        $branch c body exit
+      body:
+        f = $gfp p snd
+        k = $arith mul 3 7
+        v = $load f
+        v = $arith add v k
+        $store f v
+        $call_ext print(v)
+        i = $arith add i 1
+        $jump header
+      exit:
+        $ret s
+    }
+    "#
+    .parse::<Program>()
+    .unwrap();
+
+    let run = |program: Program| crate::interpreter::interpret_with_output(program).unwrap();
+
+    let optimized = licm(program.clone().validate().unwrap()).0;
+    let entry = &optimized.functions[&func_id("main")].body[&bb_id("entry")];
+
+    assert_eq!(entry.insts.len(), 3);
+    assert_eq!(run(optimized), run(program));
+}
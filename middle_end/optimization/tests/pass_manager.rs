@@ -0,0 +1,198 @@
+use crate::middle_end::{lir::*, optimization::pass_manager::*, optimization::ssa::to_ssa};
+
+// Only `f` changes, and the dead store elimination keeps its CFG, so only the
+// CFG simplification makes the second value numbering compute it again.
+#[test]
+fn caches_analyses_until_invalidated() {
+    let program = r#"
+    fn f(a:int) -> int {
+    let x:int, y:int, z:int
+    entry:
+      x = $arith add a 1
+      y = $arith add a 1
+      z = $arith mul x 2
+      $jump next
+    next:
+      $ret y
+    }
+
+    fn main() -> int {
+    let r:int
+    entry:
+      r = $call_dir f(1) then exit
+    exit:
+      $ret r
+    }
+    "#
+    .parse::<Program>()
+    .unwrap()
+    .validate()
+    .unwrap();
+
+    let mut pm = PassManager::new();
+    for name in ["gvn", "dse", "gvn", "simplify-cfg", "gvn"] {
+        pm.add_named(name).unwrap();
+    }
+    let optimized = pm.run(program);
+
+    let f = &optimized.0.functions[&func_id("f")];
+    assert_eq!(f.body.len(), 1);
+    assert_eq!(f.body[&bb_id("entry")].insts.len(), 2);
+
+    assert_eq!(pm.analyses().runs(Analysis::Cfg), 3);
+    assert_eq!(pm.analyses().runs(Analysis::Dominators), 3);
+    assert_eq!(pm.analyses().runs(Analysis::ReachingDefs), pm.analyses().runs(Analysis::Liveness));
+    assert_eq!(pm.analyses().runs(Analysis::PointsTo), 0);
+}
+
+// The steps of a fixpoint have nothing left to do once it is reached.
+#[test]
+fn fixpoint_is_stable() {
+    let program = r#"
+    fn main() -> int {
+    let i:int, s:int, t:int, c:int, d:int
+    entry:
+      d = $copy 1
+      $jump header
+    header:
+      c = $cmp lt i 10
+      $branch c body exit
+    body:
+      t = $copy i
+      s = $arith add s t
+      $branch d inc dead
+    dead:
+      s = $arith add s 100
+      $jump inc
+    inc:
+      i = $arith add t 1
+      $jump header
+    exit:
+      $ret s
+    }
+    "#
+    .parse::<Program>()
+    .unwrap()
+    .validate()
+    .unwrap();
+
+    let steps = ["sccp", "copy-prop", "gvn", "adce"];
+
+    let mut pm = PassManager::new();
+    pm.add_fixpoint(&steps, 10).unwrap();
+    let optimized = pm.run(to_ssa(program));
+
+    let mut again = PassManager::new();
+    for name in steps {
+        again.add_named(name).unwrap();
+    }
+    assert_eq!(again.run(optimized.clone()).0, optimized.0);
+    assert!(!optimized.0.functions[&func_id("main")].body.contains_key(&bb_id("dead")));
+}
+
+// Alone, sccp takes the program to SSA form and back.
+#[test]
+fn sccp_takes_programs_not_in_ssa_form() {
+    let program = r#"
+    fn main() -> int {
+    let x:int, c:int
+    entry:
+      x = $copy 1
+      c = $cmp lt x 2
+      $branch c set exit
+    set:
+      x = $copy 2
+      $jump exit
+    exit:
+      $ret x
+    }
+    "#
+    .parse::<Program>()
+    .unwrap()
+    .validate()
+    .unwrap();
+    assert!(program.0.check_ssa().is_err());
+
+    let mut pm = PassManager::new();
+    pm.add_named("sccp").unwrap();
+    let optimized = pm.run(program.clone());
+
+    assert_eq!(
+        crate::interpreter::interpret_with_output(optimized.0).unwrap(),
+        crate::interpreter::interpret_with_output(program.0).unwrap()
+    );
+}
+
+#[test]
+fn rejects_unknown_names() {
+    assert!(PassManager::pipeline("O7").is_err());
+    assert!(PassManager::new().add_named("inline-small-3").is_err());
+    assert!(PassManager::new().add_fixpoint(&["gvn", "nope"], 2).is_err());
+}
+
+#[test]
+fn o1_preserves_behavior() {
+    let program = r#"
+    struct pair {
+      fst:int
+      snd:int
+    }
+
+    extern print:(int) -> _
+
+    fn sum(p:&pair, n:int) -> int {
+      let f:&int, i:int, s:int, c:int, k:int, v:int, w:int
+      entry:
+        $jump header
+      header:
+        c = $cmp lt i n
+        $branch c body exit
+      body:
+        f = $gfp p snd
+        k = $arith mul n 3
+        v = $load f
+        w = $arith mul n 3
+        v = $arith add v k
+        v = $arith add v w
+        $store f v
+        s = $arith add s v
+        i = $arith add i 1
+        $jump header
+      exit:
+        $ret s
+    }
+
+    fn main() -> int {
+      let p:&pair, r:int, d:int, u:int
+      entry:
+        p = $alloc 1 [_a1]
+        d = $copy 0
+        u = $arith add d 5
+        $branch d skip call
+      skip:
+        $jump call
+      call:
+        r = $call_dir sum(p, u) then exit
+      exit:
+        $call_ext print(r)
+        $ret r
+    }
+    "#
+    .parse::<Program>()
+    .unwrap();
+
+    let run = |program: Program| crate::interpreter::interpret_with_output(program).unwrap();
+    let size = |program: &Program| -> usize {
+        program
+            .functions
+            .values()
+            .flat_map(|f| f.body.values())
+            .map(|bb| bb.insts.len() + 1)
+            .sum()
+    };
+
+    let optimized = PassManager::pipeline("O1").unwrap().run(program.clone().validate().unwrap()).0;
+
+    assert!(size(&optimized) < size(&program));
+    assert_eq!(run(optimized), run(program));
+}
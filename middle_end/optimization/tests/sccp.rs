@@ -0,0 +1,316 @@
+// Tests for sparse conditional constant propagation.  The inputs are already in
+// SSA form, except for the whole-program check at the end.
+
+use crate::middle_end::{lir::*, optimization::sccp::*, optimization::ssa::*};
+
+// Check if the input program optimizes to the expected output program
+fn optimizes_to(input: &str, expected: &str) {
+    let input = input.parse::<Program>().unwrap().validate().unwrap();
+    let expected = expected
+        .parse::<Program>()
+        .unwrap()
+        .validate()
+        .unwrap()
+        .0
+        .to_string();
+
+    let actual = sccp(input).0;
+
+    assert_eq!(actual.to_string(), expected);
+}
+
+// The straight-line cases of constant propagation: a division by zero stays,
+// and so does everything using its result.
+#[test]
+fn simple_arith() {
+    optimizes_to(
+        r#"
+    fn test(top:int) -> _ {
+    let a:int, b:int, c:int, d:int, e:int, f:int, g:int, h:int, i:int, j:int, k:int, l:int, bot:int
+    entry:
+      a = $arith add 1 1
+      b = $arith sub a 4
+      c = $arith mul b 2
+      d = $arith div c 3
+      bot = $arith div 2 0
+      e = $arith add top 1
+      f = $arith sub top 1
+      g = $arith mul 0 top
+      h = $arith div 1 top
+      i = $arith add bot 1
+      j = $arith sub bot top
+      k = $cmp lt b a
+      l = $cmp eq top a
+      $ret
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn test(top:int) -> _ {
+    let a:int, b:int, c:int, d:int, e:int, f:int, g:int, h:int, i:int, j:int, k:int, l:int, bot:int
+    entry:
+      a = $copy 2
+      b = $copy -2
+      c = $copy -4
+      d = $copy -1
+      bot = $arith div 2 0
+      e = $arith add top 1
+      f = $arith sub top 1
+      g = $copy 0
+      h = $arith div 1 top
+      i = $arith add bot 1
+      j = $arith sub bot top
+      k = $copy 1
+      l = $cmp eq top 2
+      $ret
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+#[test]
+fn constant_branch_becomes_jump() {
+    optimizes_to(
+        r#"
+    fn main() -> int {
+    let c:int, x:int, x.1:int, x.2:int
+    entry:
+      c = $cmp lt 1 2
+      $branch c yes no
+    yes:
+      x.1 = $copy 3
+      $jump join
+    no:
+      x.2 = $copy 4
+      $jump join
+    join:
+      x = $phi(x.2, x.1)
+      $ret x
+    }
+    "#,
+        r#"
+    fn main() -> int {
+    let c:int, x:int, x.1:int, x.2:int
+    entry:
+      c = $copy 1
+      $jump yes
+    yes:
+      x.1 = $copy 3
+      $jump join
+    join:
+      x = $copy 3
+      $ret 3
+    }
+    "#,
+    );
+}
+
+// `x` stays 1 around the loop because the block setting it to 2 is never
+// executed, which constant propagation alone cannot tell.
+#[test]
+fn constant_through_loop() {
+    optimizes_to(
+        r#"
+    fn f(n:int) -> int {
+    let c:int, d:int, x:int, x.1:int, x.2:int
+    entry:
+      $jump header
+    header:
+      x = $phi(1, x.1)
+      c = $cmp lt n 10
+      $branch c body exit
+    body:
+      d = $cmp neq x 1
+      $branch d bad latch
+    bad:
+      x.2 = $copy 2
+      $jump latch
+    latch:
+      x.1 = $phi(x.2, x)
+      $jump header
+    exit:
+      $ret x
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn f(n:int) -> int {
+    let c:int, d:int, x:int, x.1:int, x.2:int
+    entry:
+      $jump header
+    header:
+      x = $copy 1
+      c = $cmp lt n 10
+      $branch c body exit
+    body:
+      d = $copy 0
+      $jump latch
+    latch:
+      x.1 = $copy 1
+      $jump header
+    exit:
+      $ret 1
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+#[test]
+fn phis_lose_arguments_of_dead_edges() {
+    optimizes_to(
+        r#"
+    fn f(p:int) -> int {
+    let c:int, x:int, x.1:int, x.2:int, x.3:int
+    entry:
+      $branch p left mid
+    left:
+      x.1 = $copy p
+      $jump join
+    mid:
+      c = $copy 0
+      $branch c right join
+    right:
+      x.2 = $copy 7
+      $jump join
+    join:
+      x.3 = $phi(x.1, 8, x.2)
+      $ret x.3
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn f(p:int) -> int {
+    let c:int, x:int, x.1:int, x.2:int, x.3:int
+    entry:
+      $branch p left mid
+    left:
+      x.1 = $copy p
+      $jump join
+    mid:
+      c = $copy 0
+      $jump join
+    join:
+      x.3 = $phi(x.1, 8)
+      $ret x.3
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+// Deleting the edge out of the loop would leave it with no way to the exit, so
+// only the values are folded.
+#[test]
+fn loop_without_exit_is_kept() {
+    optimizes_to(
+        r#"
+    fn f(p:int) -> int {
+    let c:int
+    entry:
+      $branch p spin exit
+    spin:
+      c = $cmp eq 1 1
+      $branch c spin exit
+    exit:
+      $ret p
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn f(p:int) -> int {
+    let c:int
+    entry:
+      $branch p spin exit
+    spin:
+      c = $copy 1
+      $branch 1 spin exit
+    exit:
+      $ret p
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+#[test]
+fn preserves_behavior() {
+    let program = r#"
+    extern print:(int) -> _
+
+    fn main() -> int {
+      let i:int, s:int, c:int, d:int, k:int
+      entry:
+        k = $copy 3
+        $jump header
+      header:
+        c = $cmp lt i 10
+        $branch c body exit
+      body:
+        d = $arith div i k
+        $branch d odd even
+      odd:
+        s = $arith add s k
+        $jump latch
+      even:
+        k = $copy 3
+        $call_ext print(s)
+        $jump latch
+      latch:
+        i = $arith add i 1
+        $jump header
+      exit:
+        $ret s
+    }
+    "#
+    .parse::<Program>()
+    .unwrap();
+
+    let run = |program: Program| crate::interpreter::interpret_with_output(program).unwrap();
+
+    let optimized = from_ssa(sccp(to_ssa(program.clone().validate().unwrap())));
+    let ks = optimized
+        .0
+        .functions
+        .values()
+        .flat_map(|f| f.body.values())
+        .flat_map(|bb| &bb.insts)
+        .filter(|inst| inst.uses().iter().any(|x| x.name().starts_with('k')))
+        .count();
+
+    assert_eq!(ks, 0);
+    assert_eq!(run(optimized.0), run(program));
+}
@@ -0,0 +1,276 @@
+use crate::commons::Valid;
+use crate::middle_end::{lir::*, optimization::simplify_cfg::*};
+
+// Check if the input program optimizes to the expected output program
+fn optimizes_to(pass: fn(Valid<Program>) -> Valid<Program>, input: &str, expected: &str) {
+    let input = input.parse::<Program>().unwrap().validate().unwrap();
+    let expected = expected
+        .parse::<Program>()
+        .unwrap()
+        .validate()
+        .unwrap()
+        .0
+        .to_string();
+
+    let actual = pass(input).0;
+
+    assert_eq!(actual.to_string(), expected);
+}
+
+// The branch on a constant only goes to `yes`, so `no` is unreachable and the
+// phi in `exit` loses its argument.  The chain left is a single block.
+#[test]
+fn folds_constant_branches() {
+    optimizes_to(
+        simplify_cfg,
+        r#"
+    fn f(a:int) -> int {
+    let x:int, c:int
+    entry:
+      c = $copy 1
+      $branch 1 yes no
+    yes:
+      $jump exit
+    no:
+      $jump exit
+    exit:
+      x = $phi(a, 0)
+      $ret x
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn f(a:int) -> int {
+    let x:int, c:int
+    entry:
+      c = $copy 1
+      x = $copy 0
+      $ret x
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+// Both ways through the empty blocks lead to `exit`, which then has no phis
+// and is merged with its predecessor.
+#[test]
+fn threads_jumps_through_empty_blocks() {
+    optimizes_to(
+        simplify_cfg,
+        r#"
+    fn f(a:int) -> int {
+    let x:int
+    entry:
+      $branch a yes no
+    yes:
+      x = $copy 1
+      $jump hop
+    no:
+      $jump hop
+    hop:
+      $jump exit
+    exit:
+      $ret x
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn f(a:int) -> int {
+    let x:int
+    entry:
+      $branch a yes exit
+    yes:
+      x = $copy 1
+      $jump exit
+    exit:
+      $ret x
+    }
+
+    fn main() -> int {
+    entry:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+// Folding the branch leaving the loop would leave it with no way out, which
+// validation does not allow.
+#[test]
+fn keeps_loops_without_other_exits() {
+    let program = r#"
+    fn main() -> int {
+    let i:int
+    entry:
+      $jump loop
+    loop:
+      i = $arith add i 1
+      $branch 1 loop exit
+    exit:
+      $ret i
+    }
+    "#;
+
+    optimizes_to(simplify_cfg, program, program);
+}
+
+// The loop only computes values nothing reads.  The branch leaving it is kept,
+// since the loop might not terminate, but what feeds only itself is removed.
+// So is the branch deciding nothing but the value of a dead variable.
+#[test]
+fn adce_removes_dead_cycles_and_branches() {
+    optimizes_to(
+        aggressive_dce,
+        r#"
+    fn main() -> int {
+    let i:int, i.1:int, i.2:int, s:int, s.1:int, s.2:int, c:int, d:int, y:int
+    entry:
+      $branch c yes no
+    yes:
+      y = $copy 1
+      $jump join
+    no:
+      y = $copy 2
+      $jump join
+    join:
+      $jump loop
+    loop:
+      i.1 = $phi(i, i.2)
+      s.1 = $phi(s, s.2)
+      s.2 = $arith add s.1 i.1
+      i.2 = $arith add i.1 1
+      d = $cmp lt i.2 10
+      $branch d loop exit
+    exit:
+      $ret 0
+    }
+    "#,
+        r#"
+    fn main() -> int {
+    let i:int, i.1:int, i.2:int, d:int
+    entry:
+      $jump loop
+    loop:
+      i.1 = $phi(i, i.2)
+      i.2 = $arith add i.1 1
+      d = $cmp lt i.2 10
+      $branch d loop exit
+    exit:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+// Stores and external calls are kept, with what they need.  The load could
+// fail, so it is kept too, but not the arithmetic on its result.
+#[test]
+fn adce_keeps_effects() {
+    optimizes_to(
+        aggressive_dce,
+        r#"
+    extern print:(int) -> _
+
+    fn main() -> int {
+    let p:&int, x:int, y:int, z:int, c:int
+    entry:
+      p = $alloc 1 [_a1]
+      x = $arith mul 6 7
+      $branch c yes no
+    yes:
+      $store p x
+      $jump exit
+    no:
+      y = $load p
+      z = $arith add y 1
+      $call_ext print(x)
+      $jump exit
+    exit:
+      $ret 0
+    }
+    "#,
+        r#"
+    extern print:(int) -> _
+
+    fn main() -> int {
+    let p:&int, x:int, y:int, c:int
+    entry:
+      p = $alloc 1 [_a1]
+      x = $arith mul 6 7
+      $branch c yes no
+    yes:
+      $store p x
+      $jump exit
+    no:
+      y = $load p
+      $call_ext print(x)
+      $jump exit
+    exit:
+      $ret 0
+    }
+    "#,
+    );
+}
+
+#[test]
+fn preserves_behavior() {
+    let program = r#"
+    extern print:(int) -> _
+
+    fn main() -> int {
+      let p:&int, i:int, s:int, t:int, c:int, v:int
+      entry:
+        p = $alloc 1 [_a1]
+        $branch 0 dead header
+      dead:
+        $call_ext print(i)
+        $jump header
+      header:
+        c = $cmp lt i 10
+        $branch c body exit
+      body:
+        t = $arith mul t 3
+        $jump body2
+      body2:
+        v = $load p
+        v = $arith add v i
+        $store p v
+        $branch 1 print skip
+      print:
+        $call_ext print(v)
+        $jump skip
+      skip:
+        i = $arith add i 1
+        $jump header
+      exit:
+        s = $load p
+        $ret s
+    }
+    "#
+    .parse::<Program>()
+    .unwrap();
+
+    let run = |program: Program| crate::interpreter::interpret_with_output(program).unwrap();
+
+    let simplified = simplify_cfg(program.clone().validate().unwrap()).0;
+    let optimized = aggressive_dce(program.clone().validate().unwrap()).0;
+    let main = &optimized.functions[&func_id("main")];
+
+    assert!(simplified.functions[&func_id("main")].body.len() < program.functions[&func_id("main")].body.len());
+    assert!(!main.locals.contains(&var_id("t", int_ty(), Some(func_id("main")))));
+    assert_eq!(run(simplified), run(program.clone()));
+    assert_eq!(run(optimized), run(program));
+}
@@ -0,0 +1,244 @@
+// Basic tests for SSA construction and the SSA checks of the validator.
+
+use crate::middle_end::{lir::*, optimization::ssa::*};
+
+fn ssa(code: &str) -> Program {
+    let program = code.parse::<Program>().unwrap().validate().unwrap();
+    to_ssa(program).0
+}
+
+const LOOP: &str = r#"
+    fn main() -> int {
+      let i:int, s:int, c:int, t:int, p:&int
+      entry:
+        s = $copy 0
+        p = $addrof t
+        $jump header
+      header:
+        c = $cmp lt i 10
+        $branch c body exit
+      body:
+        s = $arith add s i
+        i = $arith add i 1
+        $store p i
+        $jump header
+      exit:
+        $ret s
+    }
+"#;
+
+#[test]
+fn loop_gets_phis_at_header() {
+    let program = ssa(LOOP);
+    program.check_ssa().unwrap();
+
+    let main = &program.functions[&func_id("main")];
+    let v = |name: &str| var_id(name, int_ty(), Some(func_id("main")));
+    let var = |name: &str| Operand::Var(v(name));
+
+    // the predecessors of header are body and entry, in this order.
+    assert_eq!(
+        main.body[&bb_id("header")].insts[..2],
+        [
+            Instruction::Phi { lhs: v("i.1"), args: vec![var("i.2"), var("i")] },
+            Instruction::Phi { lhs: v("s.2"), args: vec![var("s.3"), var("s.1")] },
+        ]
+    );
+    assert_eq!(main.body[&bb_id("exit")].term, Terminal::Ret(Some(var("s.2"))));
+
+    // only what is live across blocks needs a phi, and `t` is in memory.
+    let phis = main
+        .body
+        .values()
+        .flat_map(|bb| &bb.insts)
+        .filter(|inst| matches!(inst, Instruction::Phi { .. }))
+        .count();
+    assert_eq!(phis, 2);
+    assert!(main.locals.contains(&v("t")) && !main.locals.contains(&v("t.1")));
+}
+
+#[test]
+fn diamond_merges_definitions() {
+    let program = ssa(r#"
+    fn main() -> int {
+      let x:int, y:int, c:int
+      entry:
+        c = $copy 1
+        $branch c left right
+      left:
+        x = $copy 1
+        y = $copy 5
+        $jump join
+      right:
+        x = $copy 2
+        y = $copy 6
+        $jump join
+      join:
+        $ret x
+    }
+    "#);
+    program.check_ssa().unwrap();
+
+    let main = &program.functions[&func_id("main")];
+    let join = &main.body[&bb_id("join")];
+
+    // `y` is never read, so its phi is pruned.
+    assert_eq!(join.insts.len(), 1);
+    assert!(matches!(&join.insts[0], Instruction::Phi { args, .. } if args.len() == 2));
+}
+
+#[test]
+fn ssa_checks_reject_redefinitions_and_misplaced_phis() {
+    let program: Program = r#"
+    fn main() -> int {
+      let x:int
+      entry:
+        x = $copy 1
+        x = $copy 2
+        $ret x
+    }
+    "#
+    .parse()
+    .unwrap();
+    program.check_valid().unwrap();
+    assert!(program.check_ssa().is_err());
+
+    let program: Program = r#"
+    fn main() -> int {
+      let x:int, y:int
+      entry:
+        x = $copy 1
+        $jump next
+      next:
+        y = $copy x
+        x = $phi(y)
+        $ret x
+    }
+    "#
+    .parse()
+    .unwrap();
+    assert!(program.check_valid().is_err());
+
+    let program: Program = r#"
+    fn main() -> int {
+      let x:int, y:int
+      entry:
+        x = $copy 1
+        $jump next
+      next:
+        y = $phi(x, x)
+        $ret y
+    }
+    "#
+    .parse()
+    .unwrap();
+    assert!(program.check_valid().is_err());
+}
+
+// SECTION: out of SSA
+
+fn run(program: Program) -> (i64, Vec<i64>) {
+    crate::interpreter::interpret_with_output(program).unwrap()
+}
+
+fn from_ssa_of(code: &str) -> Program {
+    let program = code.parse::<Program>().unwrap().validate().unwrap();
+    program.0.check_ssa().unwrap();
+    from_ssa(program).0
+}
+
+#[test]
+fn round_trip_preserves_behavior() {
+    let program = r#"
+    extern print:(int) -> _
+
+    fn main() -> int {
+      let i:int, s:int, c:int, d:int
+      entry:
+        $jump header
+      header:
+        c = $cmp lt i 10
+        $branch c body exit
+      body:
+        d = $arith div i 3
+        $branch d odd even
+      odd:
+        s = $arith add s i
+        $jump latch
+      even:
+        s = $arith sub s 1
+        $call_ext print(s)
+        $jump latch
+      latch:
+        i = $arith add i 1
+        $jump header
+      exit:
+        $ret s
+    }
+    "#
+    .parse::<Program>()
+    .unwrap();
+
+    let ssa = to_ssa(program.clone().validate().unwrap());
+    assert_eq!(run(ssa.0.clone()), run(program.clone()));
+
+    let round_trip = from_ssa(ssa).0;
+    let phis = round_trip
+        .functions
+        .values()
+        .flat_map(|f| f.body.values())
+        .flat_map(|bb| &bb.insts)
+        .filter(|inst| matches!(inst, Instruction::Phi { .. }))
+        .count();
+
+    assert_eq!(phis, 0);
+    assert_eq!(run(round_trip), run(program));
+}
+
+// The copy `x = y` on the back edge would clobber the `x` read at exit if it
+// were placed at the end of `loop`.
+#[test]
+fn lost_copy() {
+    let program = from_ssa_of(r#"
+    fn main() -> int {
+      let x:int, y:int, c:int
+      entry:
+        $jump loop
+      loop:
+        x = $phi(1, y)
+        y = $arith add x 1
+        c = $cmp lt y 5
+        $branch c loop exit
+      exit:
+        $ret x
+    }
+    "#);
+
+    assert_eq!(program.functions[&func_id("main")].body.len(), 4);
+    assert_eq!(run(program).0, 4);
+}
+
+// The phis of a block assign their variables all at once.
+#[test]
+fn swap() {
+    let program = from_ssa_of(r#"
+    fn main() -> int {
+      let a:int, b:int, i:int, j:int, c:int, r:int, s:int
+      entry:
+        $jump loop
+      loop:
+        a = $phi(1, b)
+        b = $phi(2, a)
+        i = $phi(0, j)
+        j = $arith add i 1
+        c = $cmp lt j 3
+        $branch c loop exit
+      exit:
+        r = $arith mul a 10
+        s = $arith add r b
+        $ret s
+    }
+    "#);
+
+    assert_eq!(run(program).0, 12);
+}
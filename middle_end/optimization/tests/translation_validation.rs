@@ -0,0 +1,117 @@
+use crate::commons::Valid;
+use crate::interpreter::Stop;
+use crate::middle_end::lir::*;
+use crate::middle_end::optimization::{pass_manager::*, translation_validation::*};
+
+fn program() -> Valid<Program> {
+    r#"
+    extern print:(int) -> _
+
+    fn main() -> int {
+    let i:int, s:int, c:int
+    entry:
+      $jump header
+    header:
+      c = $cmp lt i 3
+      $branch c body exit
+    body:
+      s = $arith add s i
+      $call_ext print(s)
+      i = $arith add i 1
+      $jump header
+    exit:
+      $ret s
+    }
+    "#
+    .parse::<Program>()
+    .unwrap()
+    .validate()
+    .unwrap()
+}
+
+// Stops printing, but returns the same value.
+fn drop_prints(program: Valid<Program>) -> Valid<Program> {
+    let mut program = program.0;
+    for f in program.functions.values_mut() {
+        for bb in f.body.values_mut() {
+            bb.insts.retain(|inst| !matches!(inst, Instruction::CallExt { .. }));
+        }
+    }
+    program.validate().unwrap()
+}
+
+fn crash(_: Valid<Program>) -> Valid<Program> {
+    panic!("not implemented")
+}
+
+#[test]
+fn accepts_correct_pipelines() {
+    let mut pm = PassManager::pipeline("O1").unwrap();
+    let optimized = validate_pipeline(&mut pm, program()).unwrap();
+
+    assert_eq!(behavior(&optimized.0), Ok((3, vec![0, 1, 3])));
+}
+
+// The broken pass is reported with the program it was given: the one the
+// passes before it returned.
+#[test]
+fn reports_the_first_miscompile() {
+    let mut pm = PassManager::new();
+    pm.add_named("ssa").unwrap();
+    pm.add(Pass::basic("drop-prints", &[], drop_prints));
+    pm.add(Pass::basic("crash", &[], crash));
+
+    let failure = validate_pipeline(&mut pm, program()).unwrap_err();
+
+    let Failure::Miscompile { pass, input, before, after } = &*failure else {
+        panic!("expected a miscompile, got {failure}")
+    };
+    assert_eq!(pass, "drop-prints");
+    assert!(input.check_ssa().is_ok());
+    assert_eq!(*before, Ok((3, vec![0, 1, 3])));
+    assert_eq!(*after, Ok((3, vec![])));
+}
+
+#[test]
+fn reports_crashes() {
+    let mut pm = PassManager::new();
+    pm.add_fixpoint(&["copy-prop"], 2).unwrap();
+    pm.add(Pass::basic("crash", &[], crash));
+
+    let failure = validate_pipeline(&mut pm, program()).unwrap_err();
+
+    assert_eq!(failure.pass(), "crash");
+    assert_eq!(failure.to_string(), "crash panicked: not implemented");
+}
+
+// The output before a runtime error is part of the behavior.
+#[test]
+fn compares_the_output_before_runtime_errors() {
+    let program = r#"
+    extern print:(int) -> _
+
+    fn main() -> int {
+    let x:int
+    entry:
+      $call_ext print(1)
+      x = $arith div 1 0
+      $ret x
+    }
+    "#
+    .parse::<Program>()
+    .unwrap()
+    .validate()
+    .unwrap();
+
+    let mut pm = PassManager::new();
+    pm.add(Pass::basic("drop-prints", &[], drop_prints));
+
+    let failure = validate_pipeline(&mut pm, program).unwrap_err();
+
+    let Failure::Miscompile { pass, before, after, .. } = &*failure else {
+        panic!("expected a miscompile, got {failure}")
+    };
+    assert_eq!(pass, "drop-prints");
+    assert!(matches!(before, Err((Stop::Error(_), out)) if *out == [1]));
+    assert!(matches!(after, Err((Stop::Error(_), out)) if out.is_empty()));
+}
@@ -0,0 +1,162 @@
+//! Translation validation with the interpreter.
+//!
+//! The program is run before the pipeline and after each of its passes, and
+//! what it does each time is compared: the integers it prints, and the value
+//! `main` returns or the fact that it stops with a runtime error.  Which
+//! runtime error does not matter, since passes may change the message (e.g.
+//! inlining changes the function it names).  Running out of instructions is
+//! not a runtime error: a pass that makes the program fail instead, or the
+//! other way around, changes its behavior.  Two runs that both ran out agree
+//! if one prints the start of what the other does.
+//!
+//! The first pass that changes the behavior, or that panics, is reported with
+//! the program it was given, so it can be reproduced with that pass alone.
+//! This only checks the behavior of one run, without input, so it finds
+//! miscompiles but does not prove that there are none.
+
+use std::fmt::Display;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+use crate::commons::*;
+use crate::interpreter::{interpret_observed, RuntimeError, Stop};
+use crate::middle_end::lir::*;
+use crate::middle_end::optimization::pass_manager::{Observer, Pass, PassManager};
+
+/// What running a program does: the value `main` returns, or why it stopped,
+/// with the integers printed until then.
+pub type Behavior = Result<(i64, Vec<i64>), (Stop, Vec<i64>)>;
+
+/// Run the program with the interpreter.
+pub fn behavior(program: &Program) -> Behavior {
+    catch_unwind(|| interpret_observed(program.clone(), &mut ())).unwrap_or_else(|e| {
+        let e = RuntimeError(format!("the interpreter panicked: {}", panic_message(&*e)));
+        Err((Stop::Error(e), vec![]))
+    })
+}
+
+fn same_behavior(before: &Behavior, after: &Behavior) -> bool {
+    match (before, after) {
+        (Ok(before), Ok(after)) => before == after,
+        (Err((Stop::Error(_), before)), Err((Stop::Error(_), after))) => before == after,
+        // both runs were cut short, and a faster one prints more first.
+        (Err((Stop::OutOfInstructions, before)), Err((Stop::OutOfInstructions, after))) => {
+            before.iter().zip(after).all(|(x, y)| x == y)
+        }
+        _ => false,
+    }
+}
+
+/// A pass that broke the program.
+#[derive(Debug)]
+pub enum Failure {
+    /// The pass changed what the program does.
+    Miscompile {
+        pass: String,
+        input: Program,
+        before: Behavior,
+        after: Behavior,
+    },
+    /// The pass panicked, e.g. because it produced an invalid program.
+    Crash { pass: String, input: Program, message: String },
+}
+
+impl Failure {
+    /// The pass that broke the program.
+    pub fn pass(&self) -> &str {
+        match self {
+            Failure::Miscompile { pass, .. } | Failure::Crash { pass, .. } => pass,
+        }
+    }
+
+    /// The program the pass was given.
+    pub fn input(&self) -> &Program {
+        match self {
+            Failure::Miscompile { input, .. } | Failure::Crash { input, .. } => input,
+        }
+    }
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let show = |behavior: &Behavior| match behavior {
+            Ok((ret, out)) => format!("returns {ret} and prints {out:?}"),
+            Err((stop, out)) => format!("prints {out:?} and fails with \"{stop}\""),
+        };
+
+        match self {
+            Failure::Miscompile { pass, before, after, .. } => write!(
+                f,
+                "{pass} changed the behavior of the program: it {} before, and {} after",
+                show(before),
+                show(after)
+            ),
+            Failure::Crash { pass, message, .. } => write!(f, "{pass} panicked: {message}"),
+        }
+    }
+}
+
+/// Run the pipeline, checking that each pass keeps the behavior of the program.
+/// The result is the optimized program, or the first pass that broke it.
+pub fn validate_pipeline(pm: &mut PassManager, program: Valid<Program>) -> Result<Valid<Program>, Box<Failure>> {
+    let mut checker = Checker {
+        behavior: behavior(&program.0),
+        input: program.0.clone(),
+        running: None,
+        failure: None,
+    };
+
+    let result = catch_unwind(AssertUnwindSafe(|| pm.run_observed(program, &mut checker)));
+
+    match (result, checker.running) {
+        (Ok(program), _) => checker.failure.map_or(Ok(program), |failure| Err(Box::new(failure))),
+        (Err(e), Some(pass)) => Err(Box::new(Failure::Crash {
+            pass,
+            input: checker.input,
+            message: panic_message(&*e),
+        })),
+        (Err(e), None) => std::panic::resume_unwind(e),
+    }
+}
+
+// Compares the behavior of the program after each pass with the one before.
+struct Checker {
+    behavior: Behavior,
+    // the program given to the last pass.
+    input: Program,
+    // the pass running, if any.
+    running: Option<String>,
+    failure: Option<Failure>,
+}
+
+impl Observer for Checker {
+    fn before_pass(&mut self, pass: &Pass, program: &Valid<Program>) {
+        self.input = program.0.clone();
+        self.running = Some(pass.name.clone());
+    }
+
+    fn after_pass(&mut self, pass: &Pass, program: &Valid<Program>) -> bool {
+        self.running = None;
+
+        let after = behavior(&program.0);
+        if same_behavior(&self.behavior, &after) {
+            self.behavior = after;
+            return true;
+        }
+
+        self.failure = Some(Failure::Miscompile {
+            pass: pass.name.clone(),
+            input: self.input.clone(),
+            before: self.behavior.clone(),
+            after,
+        });
+        false
+    }
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
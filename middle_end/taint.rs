@@ -1,8 +1,5 @@
 //! Module for taint analysis
 pub mod taint_analysis;
 
-use std::collections::{BTreeMap as Map, BTreeSet as Set};
-use std::fmt::Display;
-
 // Re-export analyze function
 pub use taint_analysis::analyze;
\ No newline at end of file
@@ -37,58 +37,56 @@ impl AbstractValue for TaintValue {
     }
 }
 
-// Global state for tracking taint flows
+// Global state for tracking taint flows, threaded through the analysis as its
+// context.
 #[derive(Clone, Debug, Default)]
-struct TaintState {
+pub struct TaintState {
     sources: Set<FuncId>,
     sinks: Set<FuncId>,
     sink_map: Map<FuncId, Set<FuncId>>
 }
 
-impl AbstractEnv for TaintEnv {
+impl AbstractEnv<TaintState> for TaintEnv {
     fn join_with(&mut self, rhs: &Self, _block: &BbId, _join_type: i64) -> bool {
-        let mut changed = false;
-        for (x, rhs_val) in &rhs.values {
-            let lhs_val = self.values.entry(x.clone()).or_insert(TaintValue::BOTTOM);
-            let old = lhs_val.clone();
-            *lhs_val = lhs_val.join(rhs_val);
-            changed |= *lhs_val != old;
-        }
-        changed
+        self.pointwise_join(rhs)
     }
 
-    fn analyze_inst(&mut self, inst: &Instruction, cfg: &Cfg) {
+    fn analyze_inst(&mut self, inst: &Instruction, _cfg: &Cfg, state: &mut TaintState) {
         use Instruction::*;
         match inst {
-            Copy { lhs, op } => {
-                if let Operand::Var(v) = op {
-                    let val = self.get(v);
-                    self.values.insert(lhs.clone(), val);
-                }
+            Copy { lhs, op: Operand::Var(v) } => {
+                let val = self.get(v);
+                self.values.insert(lhs.clone(), val);
             }
             Load { lhs, src } => {
                 let val = self.get(src);
                 self.values.insert(lhs.clone(), val);
             }
-            Store { dst, op } => {
-                if let Operand::Var(v) = op {
-                    let val = self.get(v);
-                    self.values.insert(dst.clone(), val);
-                }
+            Store { dst, op: Operand::Var(v) } => {
+                let val = self.get(v);
+                self.values.insert(dst.clone(), val);
             }
             CallExt { lhs, ext_callee, args } => {
-                if ext_callee.name().starts_with("src") {
+                if state.sources.contains(ext_callee) {
                     // Source call - taint the result
                     if let Some(l) = lhs {
                         self.values.insert(l.clone(), TaintValue::alpha(ext_callee.clone()));
                     }
+                } else if state.sinks.contains(ext_callee) {
+                    // Sink call - record the sources reaching its arguments
+                    let reaching = state.sink_map.entry(ext_callee.clone()).or_default();
+                    for arg in args {
+                        if let Operand::Var(v) = arg {
+                            reaching.extend(self.get(v).0);
+                        }
+                    }
                 }
             }
             _ => {}
         }
     }
 
-    fn analyze_term(&mut self, term: &Terminal, cfg: &Cfg) -> Set<BbId> {
+    fn analyze_term(&mut self, term: &Terminal, _cfg: &Cfg, _state: &mut TaintState) -> Set<BbId> {
         use Terminal::*;
         match term {
             CallDirect { lhs, callee: _, args, next_bb: _ }
@@ -109,18 +107,18 @@ impl AbstractEnv for TaintEnv {
         Set::new()
     }
 
-    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg) -> (Vec<Self>, Set<BbId>) {
+    fn analyze_bb(&self, bb: &BasicBlock, cfg: &Cfg, state: &mut TaintState) -> (Vec<Self>, Set<BbId>) {
         let mut states = vec![];
         let mut curr_state = self.clone();
 
         for (i, inst) in bb.insts.iter().enumerate() {
             curr_state.curr_inst = Some((bb.id.clone(), i));
-            curr_state.analyze_inst(inst, cfg);
+            curr_state.analyze_inst(inst, cfg, state);
             states.push(curr_state.clone());
         }
 
         curr_state.curr_inst = Some((bb.id.clone(), bb.insts.len()));
-        curr_state.analyze_term(&bb.term, cfg);
+        curr_state.analyze_term(&bb.term, cfg, state);
         states.push(curr_state);
 
         (states, Set::new())
@@ -147,7 +145,7 @@ fn collect_external_funcs(func: &Function) -> (Set<FuncId>, Set<FuncId>) {
     (sources, sinks)
 }
 
-pub fn analyze(program: &Valid<Program>, func: FuncId, pts_to: Map<String, Set<String>>) -> String {
+pub fn analyze(program: &Valid<Program>, func: FuncId, _pts_to: Map<String, Set<String>>) -> String {
     let function = &program.0.functions[&func];
     let (sources, sinks) = collect_external_funcs(function);
     
@@ -157,21 +155,16 @@ pub fn analyze(program: &Valid<Program>, func: FuncId, pts_to: Map<String, Set<S
         sink_map: Map::new()
     };
 
-    // Initialize environment with explicit type annotation
-    // We specify TaintValue as the type parameter since that's our concrete implementation
-    let taint_env: PointwiseEnv<TaintValue> = PointwiseEnv {
-        values: Map::new(),
-        curr_inst: None
-    };
+    let taint_env = TaintEnv::new(Map::new());
 
     let cfg = Cfg::new(function, program.0.globals.clone(), program.0.structs.clone());
     
-    // Add explicit type parameter to forward_analysis call
-    forward_analysis::<TaintEnv>(
+    forward_analysis_with(
         function,
         &cfg,
         &taint_env,
-        &taint_env
+        &taint_env,
+        &mut taint_state,
     );
 
     // Rest of the formatting code remains the same...
@@ -0,0 +1,8 @@
+#!/bin/bash
+# run-bench-solver.sh
+if [ $# -lt 1 ]; then
+    echo "Usage: $0 [--runs N] <lir-file-or-directory>..."
+    exit 1
+fi
+
+./target/release/bench_solver "$@"
@@ -1,10 +1,8 @@
 #!/bin/bash
 # run-solver.sh
-if [ $# -ne 1 ]; then
-    echo "Usage: $0 <constraints-file>"
+if [ $# -lt 1 ] || [ $# -gt 2 ]; then
+    echo "Usage: $0 <constraints-file> [--steensgaard]"
     exit 1
 fi
 
-constraints_file="$1"
-
-./target/release/constraint_solve "$constraints_file"
\ No newline at end of file
+./target/release/constraint_solve "$@"
\ No newline at end of file
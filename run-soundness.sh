@@ -0,0 +1,8 @@
+#!/bin/bash
+# run-soundness.sh
+if [ $# -lt 2 ]; then
+    echo "Usage: $0 interval|constant|points-to|steensgaard|flow-points-to <lir-file-or-directory>..."
+    exit 1
+fi
+
+./target/release/check_soundness "$@"
@@ -0,0 +1,8 @@
+#!/bin/bash
+# run-validate.sh
+if [ $# -lt 2 ]; then
+    echo "Usage: $0 <passes> <lir-file-or-directory>..."
+    exit 1
+fi
+
+./target/release/validate_passes "$@"